@@ -2,97 +2,244 @@ use rand_pcg::Pcg64Mcg;
 use rand::prelude::*;
 use std::collections::VecDeque;
 use serde::{ Serialize, Deserialize };
-use crate::{ Game, GameConfig, Event };
-use libtetris::Controller;
+use crate::{ Game, GameConfig, Event, Targeting, ConfigError };
+use crate::targeting::{ badge_bonus, danger, random_opponent };
+use libtetris::{ Controller, IncomingGarbage };
 
+/// The full state of a battle, RNGs included.
+///
+/// Cloning or serializing a battle snapshots it, so it can be restored or branched at any tick.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Battle {
-    pub player_1: Game,
-    pub player_2: Game,
-    p1_rng: Pcg64Mcg,
-    p2_rng: Pcg64Mcg,
+    pub players: Vec<Game>,
+    rngs: Vec<Pcg64Mcg>,
     garbage_rng: Pcg64Mcg,
+    targets: Vec<Option<usize>>,
+    last_attacker: Vec<Option<usize>>,
+    badges: Vec<u32>,
     pub time: u32,
     pub replay: Replay
 }
 
 impl Battle {
+    /// Creates a battle with one player for each config and piece seed.
+    /// 
+    /// Fails if the number of configs and seeds differ or if a game can't be played with one of
+    /// the configs.
     pub fn new(
-        p1_config: GameConfig, p2_config: GameConfig,
-        p1_seed: <Pcg64Mcg as SeedableRng>::Seed,
-        p2_seed: <Pcg64Mcg as SeedableRng>::Seed,
+        configs: Vec<GameConfig>,
+        seeds: Vec<<Pcg64Mcg as SeedableRng>::Seed>,
         garbage_seed: <Pcg64Mcg as SeedableRng>::Seed
-    ) -> Self {
-        let mut p1_rng = Pcg64Mcg::from_seed(p1_seed);
-        let mut p2_rng = Pcg64Mcg::from_seed(p2_seed);
+    ) -> Result<Self, ConfigError> {
+        if configs.len() != seeds.len() {
+            return Err(ConfigError::Invalid(format!(
+                "{} configs were given for {} players", configs.len(), seeds.len()
+            )));
+        }
+        let mut rngs: Vec<_> = seeds.iter().map(|&seed| Pcg64Mcg::from_seed(seed)).collect();
         let garbage_rng = Pcg64Mcg::from_seed(garbage_seed);
-        let player_1 = Game::new(p1_config, &mut p1_rng);
-        let player_2 = Game::new(p2_config, &mut p2_rng);
-        Battle {
+        let players = configs.iter().zip(&mut rngs)
+            .map(|(config, rng)| Game::new(config.clone(), rng))
+            .collect::<Result<_, _>>()?;
+        let count = configs.len();
+        Ok(Battle {
             replay: Replay {
-                p1_name: String::new(), p2_name: String::new(),
-                p1_config, p2_config, p1_seed, p2_seed, garbage_seed,
+                names: vec![String::new(); count],
+                configs, seeds, garbage_seed,
                 updates: VecDeque::new()
             },
-            player_1, player_2,
-            p1_rng, p2_rng, garbage_rng,
+            players, rngs, garbage_rng,
+            targets: vec![None; count],
+            last_attacker: vec![None; count],
+            badges: vec![0; count],
             time: 0,
-        }
+        })
     }
 
-    pub fn update(&mut self, p1: Controller, p2: Controller) -> BattleUpdate {
+    /// Advances the battle by one tick. There must be one controller for each player.
+    pub fn update(&mut self, controllers: &[Controller]) -> BattleUpdate {
+        assert_eq!(controllers.len(), self.players.len(), "every player needs a controller");
         self.time += 1;
 
-        self.replay.updates.push_back((p1, p2));
+        self.replay.updates.push_back(controllers.to_vec());
 
-        let p1_events = self.player_1.update(p1, &mut self.p1_rng, &mut self.garbage_rng);
-        let p2_events = self.player_2.update(p2, &mut self.p2_rng, &mut self.garbage_rng);
+        let was_alive: Vec<_> = self.players.iter().map(|game| !game.is_game_over()).collect();
+        let garbage_rng = &mut self.garbage_rng;
+        let events: Vec<_> = self.players.iter_mut().zip(&mut self.rngs).zip(controllers)
+            .map(|((game, rng), &controller)| game.update(controller, rng, garbage_rng))
+            .collect();
 
-        for event in &p1_events {
-            if let &Event::GarbageSent(amt) = event {
-                self.player_2.garbage_queue += amt;
+        for (player, &alive) in was_alive.iter().enumerate() {
+            // Knocked out players keep reporting game over, so only count the first time.
+            if alive && self.players[player].is_game_over() {
+                if let Some(attacker) = self.last_attacker[player] {
+                    self.badges[attacker] += 1 + self.badges[player];
+                }
             }
         }
-        for event in &p2_events {
-            if let &Event::GarbageSent(amt) = event {
-                self.player_1.garbage_queue += amt;
+
+        self.update_targets();
+
+        for (player, events) in events.iter().enumerate() {
+            for event in events {
+                if let &Event::GarbageSent(amt) = event {
+                    self.send_garbage(player, amt);
+                }
             }
         }
 
         BattleUpdate {
-            player_1: PlayerUpdate {
-                events: p1_events,
-                garbage_queue: self.player_1.garbage_queue
-            },
-            player_2: PlayerUpdate {
-                events: p2_events,
-                garbage_queue: self.player_2.garbage_queue
-            },
+            players: events.into_iter().zip(&self.players)
+                .map(|(events, game)| PlayerUpdate {
+                    events,
+                    garbage_queue: game.garbage_queue.incoming()
+                })
+                .collect(),
             time: self.time
         }
     }
+
+    /// The opponent the player is currently sending garbage to.
+    ///
+    /// This is `None` for players who are knocked out or split their garbage evenly.
+    pub fn target(&self, player: usize) -> Option<usize> {
+        self.targets[player]
+    }
+
+    /// The badge points the player has earned by knocking out other players.
+    pub fn badges(&self, player: usize) -> u32 {
+        self.badges[player]
+    }
+
+    fn opponents(&self, player: usize) -> Vec<usize> {
+        (0..self.players.len())
+            .filter(|&p| p != player && !self.players[p].is_game_over())
+            .collect()
+    }
+
+    fn update_targets(&mut self) {
+        for player in 0..self.players.len() {
+            if self.players[player].is_game_over() {
+                self.targets[player] = None;
+                continue
+            }
+            let opponents = self.opponents(player);
+            self.targets[player] = match self.replay.configs[player].targeting {
+                Targeting::Random | Targeting::Attackers => match self.targets[player] {
+                    Some(target) if opponents.contains(&target) => Some(target),
+                    _ => random_opponent(&opponents, &mut self.garbage_rng)
+                },
+                Targeting::KOs => opponents.iter().copied()
+                    .max_by_key(|&p| danger(&self.players[p])),
+                Targeting::Badges => opponents.iter().copied()
+                    .max_by_key(|&p| (self.badges[p], danger(&self.players[p]))),
+                Targeting::EvenSplit => None
+            };
+        }
+    }
+
+    fn send_garbage(&mut self, from: usize, lines: u32) {
+        let lines = lines + lines * badge_bonus(self.badges[from]) / 100;
+        let opponents = self.opponents(from);
+        if opponents.is_empty() {
+            return
+        }
+
+        let mut attacks = vec![];
+        match self.replay.configs[from].targeting {
+            Targeting::EvenSplit => {
+                let share = lines / opponents.len() as u32;
+                let mut amounts = vec![share; opponents.len()];
+                let extra = lines as usize % opponents.len();
+                if extra > 0 {
+                    let lucky = rand::seq::index::sample(
+                        &mut self.garbage_rng, opponents.len(), extra
+                    );
+                    for i in lucky.iter() {
+                        amounts[i] += 1;
+                    }
+                }
+                attacks.extend(opponents.iter().copied().zip(amounts));
+            }
+            Targeting::Attackers if opponents.iter().any(|&p| self.targets[p] == Some(from)) => {
+                for &p in &opponents {
+                    if self.targets[p] == Some(from) {
+                        attacks.push((p, lines));
+                    }
+                }
+            }
+            _ => attacks.extend(self.targets[from].map(|target| (target, lines)))
+        }
+
+        for (target, lines) in attacks {
+            if lines > 0 {
+                self.players[target].receive_garbage(lines);
+                self.last_attacker[target] = Some(from);
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BattleUpdate {
-    pub player_1: PlayerUpdate,
-    pub player_2: PlayerUpdate,
+    pub players: Vec<PlayerUpdate>,
     pub time: u32
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PlayerUpdate {
     pub events: Vec<Event>,
-    pub garbage_queue: u32
+    pub garbage_queue: Vec<IncomingGarbage>
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Replay {
-    pub p1_name: String,
-    pub p2_name: String,
-    pub p1_seed: <Pcg64Mcg as SeedableRng>::Seed,
-    pub p2_seed: <Pcg64Mcg as SeedableRng>::Seed,
+    pub names: Vec<String>,
+    pub seeds: Vec<<Pcg64Mcg as SeedableRng>::Seed>,
     pub garbage_seed: <Pcg64Mcg as SeedableRng>::Seed,
-    pub p1_config: GameConfig,
-    pub p2_config: GameConfig,
-    pub updates: VecDeque<(Controller, Controller)>
+    pub configs: Vec<GameConfig>,
+    /// The controller of every player on each tick.
+    pub updates: VecDeque<Vec<Controller>>
+}
+
+impl Replay {
+    /// Plays back the first `time` ticks of the replay, giving a battle that can be continued
+    /// with different inputs.
+    /// 
+    /// Fails if the battle can't be started with the replay's configs.
+    pub fn battle_at(&self, time: u32) -> Result<Battle, ConfigError> {
+        let mut battle = Battle::new(
+            self.configs.clone(), self.seeds.clone(), self.garbage_seed
+        )?;
+        battle.replay.names = self.names.clone();
+        for controllers in self.updates.iter().take(time as usize) {
+            battle.update(controllers);
+        }
+        Ok(battle)
+    }
+}
+
+#[cfg(feature = "fumen")]
+impl Replay {
+    /// Plays the replay back and encodes every placement the player made as a fumen string.
+    pub fn fumen(&self, player: usize) -> Result<String, ConfigError> {
+        let mut battle = self.battle_at(0)?;
+        let mut fumen = libtetris::fumen::Fumen::default();
+        for controllers in &self.updates {
+            // Pieces lock onto the board as it was before the update.
+            let board = battle.players[player].board.clone();
+            let update = battle.update(controllers);
+            for event in &update.players[player].events {
+                if let Event::PiecePlaced { piece, .. } = *event {
+                    let page = fumen.add_page();
+                    board.write_fumen_page(page);
+                    page.piece = Some(piece.into());
+                }
+            }
+        }
+        if fumen.pages.is_empty() {
+            battle.players[player].board.write_fumen_page(fumen.add_page());
+        }
+        Ok(fumen.encode())
+    }
 }
\ No newline at end of file
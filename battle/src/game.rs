@@ -1,8 +1,9 @@
 use serde::{ Serialize, Deserialize };
 use libtetris::*;
 use rand::prelude::*;
-use crate::GameConfig;
+use crate::{ GameConfig, GarbageHoles, GarbageQueue, ConfigError };
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Game {
     pub board: Board<ColoredRow>,
     state: GameState,
@@ -13,8 +14,9 @@ pub struct Game {
     left_das: u32,
     right_das: u32,
     going_right: bool,
-    pub garbage_queue: u32,
-    pub attacking: u32
+    pub garbage_queue: GarbageQueue,
+    pub attacking: u32,
+    last_hole: Option<usize>
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -40,6 +42,7 @@ pub enum Event {
     GameOver
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 enum GameState {
     SpawnDelay(u32),
     LineClearDelay(u32),
@@ -47,7 +50,7 @@ enum GameState {
     GameOver
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 struct FallingState {
     piece: FallingPiece,
     lowest_y: i32,
@@ -58,13 +61,16 @@ struct FallingState {
 }
 
 impl Game {
-    pub fn new(config: GameConfig, piece_rng: &mut impl Rng) -> Self {
-        let mut board = Board::new();
+    /// Starts a game with the given rules, failing if a game can't be played with them.
+    pub fn new(config: GameConfig, piece_rng: &mut impl Rng) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let mut board = Board::with_dimensions(config.width, config.height);
+        board.randomizer = config.randomizer.clone();
         for _ in 0..config.next_queue_size {
             board.add_next_piece(board.generate_next_piece(piece_rng));
         }
-        Game {
-            board, config,
+        Ok(Game {
+            board,
             prev: Default::default(),
             used: Default::default(),
             did_hold: false,
@@ -72,18 +78,23 @@ impl Game {
             right_das: config.delayed_auto_shift,
             going_right: false,
             state: GameState::SpawnDelay(config.spawn_delay),
-            garbage_queue: 0,
-            attacking: 0
-        }
+            garbage_queue: GarbageQueue::default(),
+            attacking: 0,
+            last_hole: None,
+            config
+        })
     }
 
     pub fn update(
         &mut self, current: Controller, piece_rng: &mut impl Rng, garbage_rng: &mut impl Rng
     ) -> Vec<Event> {
+        self.garbage_queue.tick();
+
         update_input(&mut self.used.left, self.prev.left, current.left);
         update_input(&mut self.used.right, self.prev.right, current.right);
         update_input(&mut self.used.rotate_right, self.prev.rotate_right, current.rotate_right);
         update_input(&mut self.used.rotate_left, self.prev.rotate_left, current.rotate_left);
+        update_input(&mut self.used.rotate_180, self.prev.rotate_180, current.rotate_180);
         update_input(&mut self.used.soft_drop, self.prev.soft_drop, current.soft_drop);
         update_input(&mut self.used.hold, self.prev.hold, current.hold);
         self.used.hard_drop = !self.prev.hard_drop && current.hard_drop;
@@ -226,28 +237,37 @@ impl Game {
                 }
 
                 // Rotate
-                if self.used.rotate_right {
-                    if falling.piece.cw(&self.board) {
-                        self.used.rotate_right = false;
-                        falling.rotation_move_count += 1;
-                        falling.lock_delay = self.config.lock_delay;
-                        if falling.piece.tspin != TspinStatus::None {
-                            events.push(Event::PieceTSpined);
-                        } else {
-                            events.push(Event::PieceRotated);
-                        }
+                if self.used.rotate_right &&
+                        falling.piece.cw(&self.board, &self.config.rotation_system) {
+                    self.used.rotate_right = false;
+                    falling.rotation_move_count += 1;
+                    falling.lock_delay = self.config.lock_delay;
+                    if falling.piece.tspin != TspinStatus::None {
+                        events.push(Event::PieceTSpined);
+                    } else {
+                        events.push(Event::PieceRotated);
                     }
                 }
-                if self.used.rotate_left {
-                    if falling.piece.ccw(&self.board) {
-                        self.used.rotate_left = false;
-                        falling.rotation_move_count += 1;
-                        falling.lock_delay = self.config.lock_delay;
-                        if falling.piece.tspin != TspinStatus::None {
-                            events.push(Event::PieceTSpined);
-                        } else {
-                            events.push(Event::PieceRotated);
-                        }
+                if self.used.rotate_left &&
+                        falling.piece.ccw(&self.board, &self.config.rotation_system) {
+                    self.used.rotate_left = false;
+                    falling.rotation_move_count += 1;
+                    falling.lock_delay = self.config.lock_delay;
+                    if falling.piece.tspin != TspinStatus::None {
+                        events.push(Event::PieceTSpined);
+                    } else {
+                        events.push(Event::PieceRotated);
+                    }
+                }
+                if self.used.rotate_180 &&
+                        falling.piece.flip(&self.board, &self.config.rotation_system) {
+                    self.used.rotate_180 = false;
+                    falling.rotation_move_count += 1;
+                    falling.lock_delay = self.config.lock_delay;
+                    if falling.piece.tspin != TspinStatus::None {
+                        events.push(Event::PieceTSpined);
+                    } else {
+                        events.push(Event::PieceRotated);
                     }
                 }
 
@@ -360,7 +380,7 @@ impl Game {
         dist: Option<i32>
     ) {
         self.did_hold = false;
-        let locked = self.board.lock_piece(falling.piece);
+        let locked = self.board.lock_piece(falling.piece, &self.config.attack_table);
 
         events.push(Event::PiecePlaced {
             piece: falling.piece,
@@ -380,26 +400,58 @@ impl Game {
         }
     }
 
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        matches!(self.state, GameState::GameOver)
+    }
+
+    /// Adds an attack to the garbage queue.
+    pub fn receive_garbage(&mut self, lines: u32) {
+        self.garbage_queue.push(lines, self.config.garbage.delay);
+    }
+
     fn deal_garbage(&mut self, events: &mut Vec<Event>, rng: &mut impl Rng) {
-        if self.attacking > self.garbage_queue {
-            self.attacking -= self.garbage_queue;
-            self.garbage_queue = 0;
-        } else {
-            self.garbage_queue -= self.attacking;
-            self.attacking = 0;
-        }
-        if self.garbage_queue > 0 {
+        self.attacking = self.garbage_queue.cancel(self.attacking);
+
+        let ready = self.garbage_queue.ready();
+        if ready > 0 {
+            let width = self.board.width();
+            let holes = self.config.garbage.holes;
+            let max_rows = match holes {
+                GarbageHoles::Cheese => 1,
+                _ => self.config.max_garbage_add
+            };
             let mut dead = false;
-            let mut col = rng.gen_range(0, 10);
+            let mut col = match holes {
+                GarbageHoles::Random { .. } => rng.gen_range(0, width),
+                _ => 0
+            };
             let mut garbage_columns = vec![];
-            for _ in 0..self.garbage_queue.min(self.config.max_garbage_add) {
-                if rng.gen_bool(1.0/3.0) {
-                    col = rng.gen_range(0, 10);
-                }
+            for _ in 0..ready.min(max_rows) {
+                let last_hole = self.last_hole;
+                col = self.garbage_queue.pop_line(|attack_column| match holes {
+                    GarbageHoles::Random { change_chance: (numerator, denominator) } => {
+                        if rng.gen_ratio(numerator, denominator) {
+                            rng.gen_range(0, width)
+                        } else {
+                            col
+                        }
+                    }
+                    GarbageHoles::PerAttack => {
+                        attack_column.unwrap_or_else(|| rng.gen_range(0, width))
+                    }
+                    GarbageHoles::Cheese => match last_hole {
+                        None => rng.gen_range(0, width),
+                        Some(last) => (last + rng.gen_range(1, width)) % width
+                    }
+                });
                 garbage_columns.push(col);
                 dead |= self.board.add_garbage(col);
+                self.last_hole = Some(col);
             }
-            self.garbage_queue -= self.garbage_queue.min(self.config.max_garbage_add);
             events.push(Event::GarbageAdded(garbage_columns));
             if dead {
                 events.push(Event::GameOver);
@@ -0,0 +1,81 @@
+use serde::{ Serialize, Deserialize };
+use std::collections::VecDeque;
+use libtetris::IncomingGarbage;
+
+/// The attacks a player has received but which haven't entered their board yet, oldest first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GarbageQueue {
+    attacks: VecDeque<Attack>
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+struct Attack {
+    lines: u32,
+    delay: u32,
+    column: Option<usize>
+}
+
+impl GarbageQueue {
+    /// Adds an attack to the back of the queue.
+    pub fn push(&mut self, lines: u32, delay: u32) {
+        if lines > 0 {
+            self.attacks.push_back(Attack { lines, delay, column: None });
+        }
+    }
+
+    /// The total number of lines in the queue.
+    pub fn total(&self) -> u32 {
+        self.attacks.iter().map(|a| a.lines).sum()
+    }
+
+    /// The number of lines that can enter the board now.
+    ///
+    /// Attacks enter the board in order, so a delayed attack also holds back the attacks behind it.
+    pub fn ready(&self) -> u32 {
+        self.attacks.iter().take_while(|a| a.delay == 0).map(|a| a.lines).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attacks.is_empty()
+    }
+
+    /// Cancels up to `lines` lines, oldest attacks first. Returns the number of lines left over.
+    pub fn cancel(&mut self, mut lines: u32) -> u32 {
+        while let Some(attack) = self.attacks.front_mut() {
+            if lines == 0 {
+                break
+            }
+            let cancelled = lines.min(attack.lines);
+            attack.lines -= cancelled;
+            lines -= cancelled;
+            if attack.lines == 0 {
+                self.attacks.pop_front();
+            }
+        }
+        lines
+    }
+
+    /// Describes each attack in the queue, oldest first.
+    pub fn incoming(&self) -> Vec<IncomingGarbage> {
+        self.attacks.iter().map(|a| IncomingGarbage { lines: a.lines, delay: a.delay }).collect()
+    }
+
+    pub(crate) fn tick(&mut self) {
+        for attack in &mut self.attacks {
+            attack.delay = attack.delay.saturating_sub(1);
+        }
+    }
+
+    /// Removes one line from the oldest attack. `pick_column` is given the column previously
+    /// picked for the attack and returns the column of the hole in this line.
+    pub(crate) fn pop_line(&mut self, pick_column: impl FnOnce(Option<usize>) -> usize) -> usize {
+        let attack = self.attacks.front_mut().expect("no garbage to pop");
+        let column = pick_column(attack.column);
+        attack.column = Some(column);
+        attack.lines -= 1;
+        if attack.lines == 0 {
+            self.attacks.pop_front();
+        }
+        column
+    }
+}
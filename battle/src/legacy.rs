@@ -0,0 +1,66 @@
+//! Serialized layouts of `Replay` from older versions, for reading old replay files.
+
+use std::collections::VecDeque;
+use serde::Deserialize;
+use libtetris::Controller;
+use crate::{ GameConfig, Replay };
+
+/// `Replay` as it was serialized before the rules became configurable.
+#[derive(Deserialize)]
+pub struct ReplayV0 {
+    p1_name: String,
+    p2_name: String,
+    p1_seed: [u8; 16],
+    p2_seed: [u8; 16],
+    garbage_seed: [u8; 16],
+    p1_config: GameConfigV0,
+    p2_config: GameConfigV0,
+    updates: VecDeque<(Controller, Controller)>
+}
+
+#[derive(Deserialize)]
+struct GameConfigV0 {
+    spawn_delay: u32,
+    line_clear_delay: u32,
+    delayed_auto_shift: u32,
+    auto_repeat_rate: u32,
+    soft_drop_speed: u32,
+    lock_delay: u32,
+    gravity: i32,
+    next_queue_size: u32,
+    max_garbage_add: u32,
+    move_lock_rule: u32,
+    garbage_blocking: bool
+}
+
+impl From<ReplayV0> for Replay {
+    fn from(v: ReplayV0) -> Replay {
+        Replay {
+            names: vec![v.p1_name, v.p2_name],
+            seeds: vec![v.p1_seed, v.p2_seed],
+            garbage_seed: v.garbage_seed,
+            configs: vec![v.p1_config.into(), v.p2_config.into()],
+            updates: v.updates.into_iter().map(|(p1, p2)| vec![p1, p2]).collect()
+        }
+    }
+}
+
+impl From<GameConfigV0> for GameConfig {
+    fn from(v: GameConfigV0) -> GameConfig {
+        // The rules that weren't configurable yet were the same as the defaults now.
+        GameConfig {
+            spawn_delay: v.spawn_delay,
+            line_clear_delay: v.line_clear_delay,
+            delayed_auto_shift: v.delayed_auto_shift,
+            auto_repeat_rate: v.auto_repeat_rate,
+            soft_drop_speed: v.soft_drop_speed,
+            lock_delay: v.lock_delay,
+            gravity: v.gravity,
+            next_queue_size: v.next_queue_size,
+            max_garbage_add: v.max_garbage_add,
+            move_lock_rule: v.move_lock_rule,
+            garbage_blocking: v.garbage_blocking,
+            ..GameConfig::default()
+        }
+    }
+}
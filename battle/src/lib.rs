@@ -1,4 +1,6 @@
 use serde::{ Serialize, Deserialize };
+use libtetris::{ RotationSystem, AttackTable, AnyRandomizer, MAX_WIDTH, ROWS };
+use std::path::Path;
 
 mod battle;
 pub use battle::{ Battle, BattleUpdate, PlayerUpdate, Replay };
@@ -6,9 +8,16 @@ mod controller;
 pub use controller::PieceMoveExecutor;
 mod game;
 pub use game::{ Event, Game };
+mod garbage;
+pub use garbage::GarbageQueue;
+mod targeting;
+pub use targeting::{ Targeting, badge_bonus };
+mod solo;
+pub use solo::{ Mode, Outcome, SoloGame };
+pub mod legacy;
 
 /// Units are in ticks
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct GameConfig {
     pub spawn_delay: u32,
@@ -23,7 +32,50 @@ pub struct GameConfig {
     pub next_queue_size: u32,
     pub max_garbage_add: u32,
     pub move_lock_rule: u32,
-    pub garbage_blocking: bool
+    pub garbage_blocking: bool,
+    pub rotation_system: RotationSystem,
+    pub attack_table: AttackTable,
+    /// The number of columns on the board.
+    pub width: usize,
+    /// The height of the visible area of the board.
+    pub height: usize,
+    pub garbage: GarbageConfig,
+    /// Who this player's garbage goes to when there are more than two players.
+    pub targeting: Targeting,
+    /// Decides the pieces in this player's next queue.
+    pub randomizer: AnyRandomizer
+}
+
+/// Controls how received garbage enters the board.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GarbageConfig {
+    pub holes: GarbageHoles,
+    /// Ticks between receiving an attack and it being able to enter the board. Attacks can still
+    /// be cancelled during this time.
+    pub delay: u32
+}
+
+/// How the holes in garbage rows are placed.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GarbageHoles {
+    /// Each batch of garbage starts in a random column, and each row moves the hole to a random
+    /// column with the chance `numerator / denominator`.
+    Random { change_chance: (u32, u32) },
+    /// All rows of an attack share a random column.
+    PerAttack,
+    /// Cheese race style garbage. Only one row enters the board at a time, and every row has its
+    /// hole in a different column than the previous row.
+    Cheese
+}
+
+impl Default for GarbageConfig {
+    fn default() -> Self {
+        GarbageConfig {
+            holes: GarbageHoles::Random { change_chance: (1, 3) },
+            delay: 0
+        }
+    }
 }
 
 impl Default for GameConfig {
@@ -40,7 +92,14 @@ impl Default for GameConfig {
             next_queue_size: 5,
             max_garbage_add: 10,
             move_lock_rule: 15,
-            garbage_blocking: false
+            garbage_blocking: false,
+            rotation_system: RotationSystem::Srs,
+            attack_table: AttackTable::default(),
+            width: 10,
+            height: 20,
+            garbage: GarbageConfig::default(),
+            targeting: Targeting::default(),
+            randomizer: AnyRandomizer::default()
         }
     }
 }
@@ -58,7 +117,66 @@ impl GameConfig {
             next_queue_size: 5,
             max_garbage_add: 20,
             move_lock_rule: 15,
-            garbage_blocking: true
+            garbage_blocking: true,
+            rotation_system: RotationSystem::Srs,
+            attack_table: AttackTable::default(),
+            width: 10,
+            height: 20,
+            garbage: GarbageConfig::default(),
+            targeting: Targeting::default(),
+            randomizer: AnyRandomizer::default()
         }
     }
-}
\ No newline at end of file
+
+    /// Checks that a game can be played with these rules.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.width < 4 || self.width > MAX_WIDTH {
+            return Err(ConfigError::Invalid(format!(
+                "width must be between 4 and {}, not {}", MAX_WIDTH, self.width
+            )));
+        }
+        if self.height < 4 || self.height + 4 > ROWS {
+            return Err(ConfigError::Invalid(format!(
+                "height must be between 4 and {}, not {}", ROWS - 4, self.height
+            )));
+        }
+        if let GarbageHoles::Random { change_chance: (numerator, denominator) } =
+                self.garbage.holes {
+            if denominator == 0 || numerator > denominator {
+                return Err(ConfigError::Invalid(format!(
+                    "garbage hole change chance {}/{} is not a probability", numerator, denominator
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the game rules from the JSON file at `path`, or returns the default rules if there is no
+/// such file.
+pub fn load_game_config(path: impl AsRef<Path>) -> Result<GameConfig, ConfigError> {
+    let config: GameConfig = match std::fs::File::open(path) {
+        Ok(file) => serde_json::from_reader(file).map_err(ConfigError::Format)?,
+        Err(_) => return Ok(GameConfig::default())
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Format(serde_json::Error),
+    /// The rules were read successfully, but a game can't be played with them.
+    Invalid(String)
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Format(e) => write!(f, "{}", e),
+            ConfigError::Invalid(reason) => write!(f, "{}", reason)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
\ No newline at end of file
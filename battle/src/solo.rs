@@ -0,0 +1,203 @@
+use rand_pcg::Pcg64Mcg;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use libtetris::{ Controller, Score, ScoreTable, Statistics };
+use crate::{ Event, Game, GameConfig, PlayerUpdate, ConfigError };
+
+/// The goal of a single player game.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Mode {
+    /// Clear `lines` lines as fast as possible.
+    Sprint { lines: u32 },
+    /// Score as many points as possible in `ticks` ticks.
+    Ultra { ticks: u32 },
+    /// Clear `lines` rows of cheese garbage as fast as possible. The board is kept topped up to
+    /// `visible` rows of garbage until all of it has been added.
+    Cheese { lines: u32, visible: u32 },
+    /// Stay alive for `ticks` ticks while being attacked. The schedule lists the tick each attack
+    /// arrives on and its size, sorted by tick. Attacks go through the garbage queue and can be
+    /// cancelled like in versus.
+    Survival { ticks: u32, schedule: Vec<(u32, u32)> }
+}
+
+impl Mode {
+    /// 40 line sprint.
+    pub fn sprint() -> Self {
+        Mode::Sprint { lines: 40 }
+    }
+
+    /// Two minute score attack.
+    pub fn ultra() -> Self {
+        Mode::Ultra { ticks: 2 * 60 * 60 }
+    }
+
+    /// 100 line cheese race with 10 rows of garbage on the board at a time.
+    pub fn cheese_race() -> Self {
+        Mode::Cheese { lines: 100, visible: 10 }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The goal was reached, or the time ran out without topping out.
+    Won,
+    /// Topped out.
+    Lost
+}
+
+/// Drives a `Game` through a single player mode, keeping score and checking the goal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SoloGame {
+    pub game: Game,
+    pub mode: Mode,
+    /// How points are awarded. Can be changed before the game starts.
+    pub scoring: ScoreTable,
+    pub score: Score,
+    pub statistics: Statistics,
+    pub time: u32,
+    rng: Pcg64Mcg,
+    garbage_rng: Pcg64Mcg,
+    outcome: Option<Outcome>,
+    next_attack: usize,
+    cheese_on_board: u32,
+    cheese_to_add: u32,
+    cheese_cleared: u32,
+    last_hole: Option<usize>
+}
+
+impl SoloGame {
+    /// Starts a game, failing if a game can't be played with `config`.
+    pub fn new(
+        config: GameConfig,
+        mode: Mode,
+        seed: <Pcg64Mcg as SeedableRng>::Seed,
+        garbage_seed: <Pcg64Mcg as SeedableRng>::Seed
+    ) -> Result<Self, ConfigError> {
+        let mut rng = Pcg64Mcg::from_seed(seed);
+        let game = Game::new(config, &mut rng)?;
+        let mut solo = SoloGame {
+            game, rng,
+            garbage_rng: Pcg64Mcg::from_seed(garbage_seed),
+            scoring: ScoreTable::default(),
+            score: Score::default(),
+            statistics: Statistics::default(),
+            time: 0,
+            outcome: None,
+            next_attack: 0,
+            cheese_on_board: 0,
+            cheese_to_add: 0,
+            cheese_cleared: 0,
+            last_hole: None,
+            mode
+        };
+        if let Mode::Cheese { lines, visible } = solo.mode {
+            solo.cheese_to_add = lines;
+            solo.add_cheese(visible);
+        }
+        Ok(solo)
+    }
+
+    /// Whether the game has ended, and how. The game doesn't change after it has ended.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// The lines left to clear in sprint and cheese modes, or the ticks left in ultra and
+    /// survival modes.
+    pub fn remaining(&self) -> u32 {
+        match self.mode {
+            Mode::Sprint { lines } => lines.saturating_sub(self.statistics.lines as u32),
+            Mode::Cheese { lines, .. } => lines.saturating_sub(self.cheese_cleared),
+            Mode::Ultra { ticks } | Mode::Survival { ticks, .. } => ticks.saturating_sub(self.time)
+        }
+    }
+
+    pub fn update(&mut self, controller: Controller) -> PlayerUpdate {
+        if self.outcome.is_some() {
+            return PlayerUpdate {
+                events: vec![],
+                garbage_queue: self.game.garbage_queue.incoming()
+            };
+        }
+        self.time += 1;
+
+        if let Mode::Survival { schedule, .. } = &self.mode {
+            while let Some(&(time, lines)) = schedule.get(self.next_attack) {
+                if time > self.time {
+                    break
+                }
+                self.game.receive_garbage(lines);
+                self.next_attack += 1;
+            }
+        }
+
+        let mut events = self.game.update(controller, &mut self.rng, &mut self.garbage_rng);
+
+        let mut placed = false;
+        for event in &events {
+            match event {
+                Event::PiecePlaced { locked, hard_drop_distance, .. } => {
+                    placed = true;
+                    self.score.placed(&self.scoring, locked);
+                    if let Some(distance) = *hard_drop_distance {
+                        self.score.dropped(&self.scoring, distance as u32, true);
+                    }
+                    self.statistics.update(locked);
+                    // Cheese is always at the bottom of the board.
+                    let cheese = locked.cleared_lines.iter()
+                        .filter(|&&y| y < self.cheese_on_board as i32)
+                        .count() as u32;
+                    self.cheese_on_board -= cheese;
+                    self.cheese_cleared += cheese;
+                }
+                Event::SoftDropped => self.score.dropped(&self.scoring, 1, false),
+                _ => {}
+            }
+        }
+
+        let mut topped_out = self.game.is_game_over();
+        if let (true, Mode::Cheese { visible, .. }) = (placed, &self.mode) {
+            // The next piece hasn't spawned yet, so the rows can't overlap it.
+            let rows = visible.saturating_sub(self.cheese_on_board);
+            let (columns, dead) = self.add_cheese(rows);
+            if !columns.is_empty() {
+                events.push(Event::GarbageAdded(columns));
+            }
+            topped_out |= dead;
+        }
+
+        self.outcome = if topped_out {
+            Some(Outcome::Lost)
+        } else if self.remaining() == 0 {
+            Some(Outcome::Won)
+        } else {
+            None
+        };
+
+        PlayerUpdate {
+            events,
+            garbage_queue: self.game.garbage_queue.incoming()
+        }
+    }
+
+    /// Adds up to `rows` rows of cheese to the bottom of the board. Returns the hole columns and
+    /// whether the board overflowed.
+    fn add_cheese(&mut self, rows: u32) -> (Vec<usize>, bool) {
+        let width = self.game.board.width();
+        let rows = rows.min(self.cheese_to_add);
+        let mut columns = vec![];
+        let mut dead = false;
+        for _ in 0..rows {
+            let col = match self.last_hole {
+                None => self.garbage_rng.gen_range(0, width),
+                Some(last) => (last + self.garbage_rng.gen_range(1, width)) % width
+            };
+            dead |= self.game.board.add_garbage(col);
+            columns.push(col);
+            self.last_hole = Some(col);
+        }
+        self.cheese_to_add -= rows;
+        self.cheese_on_board += rows;
+        (columns, dead)
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{ Serialize, Deserialize };
+use rand::prelude::*;
+use crate::Game;
+
+/// Who a player's garbage goes to when they have more than one opponent left, modelled on
+/// Tetris 99.
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Targeting {
+    /// A random opponent, kept until they are knocked out.
+    #[default]
+    Random,
+    /// Every opponent that is targeting this player. If there are none, a random opponent.
+    Attackers,
+    /// The opponent closest to topping out.
+    KOs,
+    /// The opponent with the most badges.
+    Badges,
+    /// Every attack is split evenly between all opponents.
+    EvenSplit
+}
+
+/// The extra garbage, in percent, sent by a player with the given number of badge points.
+///
+/// Knocking out a player earns one point plus all of the points that player had.
+pub fn badge_bonus(badges: u32) -> u32 {
+    match badges {
+        0..=1 => 0,
+        2..=5 => 25,
+        6..=13 => 50,
+        14..=29 => 75,
+        _ => 100
+    }
+}
+
+/// How close a player is to topping out, counting the garbage waiting to enter their board.
+pub(crate) fn danger(game: &Game) -> i32 {
+    let highest = game.board.column_heights().iter().copied().max().unwrap_or(0);
+    highest + game.garbage_queue.total() as i32
+}
+
+/// Picks a random opponent. The RNG is left alone when there is no choice to make, so that
+/// two player battles use it exactly as they did before targeting existed.
+pub(crate) fn random_opponent(opponents: &[usize], rng: &mut impl Rng) -> Option<usize> {
+    match opponents.len() {
+        0 => None,
+        1 => Some(opponents[0]),
+        n => Some(opponents[rng.gen_range(0, n)])
+    }
+}
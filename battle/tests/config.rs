@@ -0,0 +1,17 @@
+//! Games refuse to start with rules they can't be played with.
+
+use battle::{ Battle, GameConfig, GarbageHoles, Mode, SoloGame };
+
+#[test]
+fn invalid_configs_are_rejected() {
+    let too_wide = GameConfig { width: 17, ..GameConfig::default() };
+    assert!(Battle::new(vec![too_wide], vec![[1; 16]], [2; 16]).is_err());
+
+    let mut no_chance = GameConfig::default();
+    no_chance.garbage.holes = GarbageHoles::Random { change_chance: (1, 0) };
+    let mode = Mode::Sprint { lines: 40 };
+    assert!(SoloGame::new(no_chance, mode, [1; 16], [2; 16]).is_err());
+
+    let two_configs = vec![GameConfig::default(), GameConfig::default()];
+    assert!(Battle::new(two_configs, vec![[1; 16]], [2; 16]).is_err());
+}
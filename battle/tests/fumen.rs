@@ -0,0 +1,41 @@
+//! A replay turns into a fumen with one page for each piece a player placed.
+
+use battle::{ Battle, Event, GameConfig };
+use libtetris::*;
+
+#[test]
+fn replay_fumen_matches_placements() {
+    let mut battle = Battle::new(vec![GameConfig::fast_config()], vec![[1; 16]], [2; 16]).unwrap();
+
+    let mut expected = vec![];
+    let mut held = 0;
+    for tick in 0..120 {
+        // Hold every third piece and spread the pieces out so the player survives a while.
+        let cycle = tick / 4;
+        let controller = match tick % 4 {
+            0 => Controller { hold: cycle % 3 == 0, ..Default::default() },
+            1 => Controller { left: cycle % 2 == 0, right: cycle % 2 == 1, ..Default::default() },
+            2 => Controller { hard_drop: true, ..Default::default() },
+            _ => Controller::default()
+        };
+        let board = battle.players[0].board.clone();
+        let update = battle.update(&[controller]);
+        for event in &update.players[0].events {
+            match *event {
+                Event::PiecePlaced { piece, .. } => expected.push((board.get_field(), piece)),
+                Event::PieceHeld(_) => held += 1,
+                _ => {}
+            }
+        }
+    }
+    assert!(held > 0, "no piece was held");
+    assert!(expected.len() > held, "too few pieces were placed");
+
+    let fumen = fumen::Fumen::decode(&battle.replay.fumen(0).unwrap()).unwrap();
+    assert_eq!(fumen.pages.len(), expected.len());
+    for (i, (page, (field, piece))) in fumen.pages.iter().zip(&expected).enumerate() {
+        assert_eq!(&Board::<u16>::from_fumen_page(page).get_field(), field, "page {}", i + 1);
+        let found = FallingPiece::from(page.piece.unwrap());
+        assert!(found.same_location(piece), "page {}: {:?} != {:?}", i + 1, found, piece);
+    }
+}
@@ -77,22 +77,27 @@
 //! piece laid flat in the center is represented as `0x7F 0x1E 0x81 0x4D 0x81 0x7F 0x1E`.
 #![allow(dead_code)]
 
-use libtetris::{ Board, Piece, FallingPiece, LockResult };
+use libtetris::{ Board, Piece, FallingPiece, LockResult, AttackTable, AnyRandomizer, ROWS };
 use std::collections::{ HashMap, VecDeque };
 use arrayvec::ArrayVec;
-use enumset::EnumSet;
 use enum_map::EnumMap;
 use serde::{ Serialize, Deserialize };
+use serde::de::DeserializeOwned;
 use rand::prelude::*;
 use bumpalo::collections::vec::Vec as BumpVec;
 use crate::evaluation::Evaluation;
+use crate::selection::{ Branch, SelectionPolicy };
 
 pub struct DagState<E: 'static, R: 'static> {
     board: Board,
     generations: VecDeque<rented::Generation<E, R>>,
     root: u32,
     gens_passed: u32,
-    use_hold: bool
+    use_hold: bool,
+    attack_table: AttackTable,
+    /// How far the evaluation of a speculated node is moved from the expected value towards the
+    /// worst case, in percent.
+    risk_aversion: u32
 }
 
 #[derive(Serialize, Deserialize)]
@@ -116,7 +121,11 @@ pub struct MoveCandidate<E> {
     pub board: Board,
     pub evaluation: E,
     pub hold: bool,
-    pub original_rank: u32
+    pub original_rank: u32,
+    /// The number of times the search has gone through the resulting node.
+    pub visits: u32,
+    /// The principal variation starting with this move, if it was requested.
+    pub plan: Vec<(FallingPiece, LockResult)>
 }
 
 rental! {
@@ -139,16 +148,25 @@ enum Children<'c, R> {
     // we need to know the piece to resolve speculations computed before a new piece was added,
     // but given to us after a new piece was added.
     Known(Piece, Vec<Option<&'c mut [Child<R>]>>),
-    Speculated(Vec<Option<EnumMap<Piece, Option<&'c mut [Child<R>]>>>>)
+    Speculated(Vec<Option<Speculation<'c, R>>>)
+}
+
+struct Speculation<'c, R> {
+    /// How likely each piece is to be the generation piece, in thousandths. Every piece with
+    /// children has a chance of at least 1.
+    chances: EnumMap<Piece, u16>,
+    children: EnumMap<Piece, Option<&'c mut [Child<R>]>>
 }
 
 struct Node<'c, E> {
     parents: BumpVec<'c, u32>,
     evaluation: E,
+    visits: u32,
     marked: bool,
     death: bool,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 struct Child<R> {
     placement: FallingPiece,
     reward: R,
@@ -160,20 +178,71 @@ struct Child<R> {
 struct SimplifiedBoard<'c> {
     grid: &'c [u16],
     combo: u32,
-    bag: EnumSet<Piece>,
+    randomizer: AnyRandomizer,
     reserve: Piece,
     back_to_back: bool,
+    b2b_chain: u32,
+    reserve_is_hold: bool
+}
+
+/// An owned copy of a `DagState` with the arena-allocated data moved onto the heap, so that it can
+/// be serialized.
+#[derive(Serialize, Deserialize)]
+struct SavedDag<E, R> {
+    board: Board,
+    root: u32,
+    use_hold: bool,
+    attack_table: AttackTable,
+    generations: Vec<SavedGeneration<E, R>>
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedGeneration<E, R> {
+    nodes: Vec<SavedNode<E>>,
+    children: SavedChildren<R>,
+    deduplicator: Vec<(SavedBoard, u32)>
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedNode<E> {
+    parents: Vec<u32>,
+    evaluation: E,
+    visits: u32,
+    death: bool
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedChildren<R> {
+    Known(Piece, Vec<Option<Vec<Child<R>>>>),
+    Speculated(Vec<Option<SavedSpeculation<R>>>)
+}
+
+/// The chance and children of each piece, like `Speculation`.
+type SavedSpeculation<R> = (EnumMap<Piece, u16>, EnumMap<Piece, Option<Vec<Child<R>>>>);
+
+#[derive(Serialize, Deserialize)]
+struct SavedBoard {
+    grid: Vec<u16>,
+    combo: u32,
+    randomizer: AnyRandomizer,
+    reserve: Piece,
+    back_to_back: bool,
+    b2b_chain: u32,
     reserve_is_hold: bool
 }
 
 impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
-    pub fn new(board: Board, use_hold: bool) -> Self {
+    pub fn new(
+        board: Board, use_hold: bool, attack_table: AttackTable, risk_aversion: u32
+    ) -> Self {
         let mut this = DagState {
             board,
             generations: VecDeque::new(),
             root: 0,
             gens_passed: 0,
-            use_hold
+            use_hold,
+            attack_table,
+            risk_aversion
         };
         this.init_generations();
         this
@@ -191,6 +260,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                 nodes: vec![Node {
                     parents: BumpVec::new_in(bump),
                     evaluation: E::default(),
+                    visits: 0,
                     marked: false,
                     death: false
                 }],
@@ -209,8 +279,11 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         }
     }
 
+    /// Picks a leaf to search with `policy`, after trying to follow the forced analysis lines.
     pub fn find_and_mark_leaf(
-        &mut self, forced_analysis_lines: &mut Vec<Vec<FallingPiece>>
+        &mut self,
+        forced_analysis_lines: &mut Vec<Vec<FallingPiece>>,
+        policy: &impl SelectionPolicy<E, R>
     ) -> Option<(NodeId, Board)> {
         for i in (0..forced_analysis_lines.len()).rev() {
             // Attempt to search forced lines first
@@ -244,21 +317,24 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         }
 
         self.find_and_mark_leaf_with_chooser(|next_gen_nodes, children| {
-            // Since children is sorted best-to-worst, the minimum evaluation will be the last item
-            // in the iterator. filter_map allows us to ignore death nodes.
-            let evaluation = &child_eval_fn(next_gen_nodes);
-            let min_eval = children.iter().rev().filter_map(evaluation).next()?;
-            let weights = children.iter().enumerate().map(
-                |(i, c)| evaluation(c).map_or(0,
-                    |e| e.weight(&min_eval, i)
-                )
-            );
-            // Choose a node randomly (the Monte-Carlo part)
-            let sampler = rand::distributions::WeightedIndex::new(weights).ok()?;
-            Some(&children[thread_rng().sample(sampler)])
+            let evaluation = child_eval_fn(next_gen_nodes);
+            let branches: Vec<_> = children.iter().map(|c| Branch {
+                evaluation: evaluation(c),
+                visits: next_gen_nodes[c.node as usize].visits
+            }).collect();
+            Some(&children[policy.choose(&branches)?])
         })
     }
 
+    /// Whether the piece the children of `node` are placing is the first one that isn't known
+    /// yet, as opposed to one coming after a speculated piece.
+    pub fn is_first_unknown(&self, node: &NodeId) -> bool {
+        let gen = node.generation.saturating_sub(self.gens_passed) as usize;
+        self.generations.iter().take(gen).all(
+            |gen| gen.rent(|gen| matches!(gen.children, Children::Known(..)))
+        )
+    }
+
     fn find_and_mark_leaf_with_chooser(
         &mut self,
         mut chooser: impl for<'a> FnMut(&[Node<E>], &'a [Child<R>]) -> Option<&'a Child<R>>
@@ -267,21 +343,26 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         let mut gen_index = 0;
         let mut node_key = self.root as usize;
         loop {
+            self.generations[gen_index].rent_mut(|gen| gen.nodes[node_key].visits += 1);
+
             // Get the list of childs of the current node, or None if this is a leaf
             let children = self.generations[gen_index].maybe_ref_rent(|gen| match &gen.children {
                 Children::Known(_, childrens) => childrens[node_key].as_deref(),
                 Children::Speculated(childrens) => {
                     // We must select a single group of children to search further. We do this by
-                    // finding the set of valid next pieces and randomly selecting one uniformly.
-                    // We then take the group of children associated with that piece.
-                    let children = childrens[node_key].as_ref()?;
+                    // finding the set of valid next pieces and randomly selecting one according
+                    // to its chance of being the next piece. We then take the group of children
+                    // associated with that piece.
+                    let speculation = childrens[node_key].as_ref()?;
                     let mut pick_from = ArrayVec::<[_; 7]>::new();
-                    for (p, c) in children {
+                    for (p, c) in &speculation.children {
                         if let Some(c) = c {
                             pick_from.push((p, &**c));
                         }
                     }
-                    let (piece, children) = *pick_from.choose(&mut thread_rng()).unwrap();
+                    let (piece, children) = *pick_from.choose_weighted(
+                        &mut thread_rng(), |&(p, _)| speculation.chances[p]
+                    ).unwrap();
                     board.add_next_piece(piece);
                     Some(children)
                 }
@@ -294,7 +375,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                         &gen.nodes,
                         children
                     )?;
-                    advance(&mut board, child.placement);
+                    advance(&mut board, child.placement, &self.attack_table);
                     gen_index += 1;
                     node_key = child.node as usize;
                     Some(())
@@ -336,8 +417,14 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         self.backpropogate(gen, vec![node.slab_key as usize]);
     }
 
+    /// Adds the children of a node for each piece that could be the generation piece.
+    ///
+    /// `chances` is how likely each piece is, usually the distribution of the randomizer. It
+    /// decides how often each group of children is searched and how much it counts towards the
+    /// evaluation of the node.
     pub fn update_speculated(
         &mut self, node: NodeId,
+        chances: EnumMap<Piece, f64>,
         mut children: EnumMap<Piece, Option<Vec<ChildData<E, R>>>>
     ) {
         // make sure we weren't given a NodeId for an expired node. it could happen.
@@ -362,15 +449,19 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                     ))
                 }
                 Children::Speculated(c) => {
-                    let mut childs = EnumMap::new();
+                    let mut speculation = Speculation {
+                        chances: EnumMap::new(),
+                        children: EnumMap::new()
+                    };
                     for (p, data) in children {
                         if let Some(data) = data {
-                            childs[p] = Some(build_children(
+                            speculation.chances[p] = ((chances[p] * 1000.0).round() as u16).max(1);
+                            speculation.children[p] = Some(build_children(
                                 current.arena, &mut next, data, node.slab_key, use_hold
                             ));
                         }
                     }
-                    c[node.slab_key as usize] = Some(childs);
+                    c[node.slab_key as usize] = Some(speculation);
                 }
             }
             current.data.nodes[node.slab_key as usize].marked = false;
@@ -383,6 +474,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         // Use a queue to iterate in breadth-first order. This allows us to know that we shouldn't
         // add an element to the queue if it's already present; we know that all of its children
         // will have been processed first before we get to the parent node.
+        let risk_aversion = self.risk_aversion.min(100) as usize;
 
         while !to_update.is_empty() {
             let mut next_gen_to_update = vec![];
@@ -430,35 +522,40 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                             }
                         }
                         Children::Speculated(children) => {
-                            if let Some(children) = children[node_id as usize].as_mut() {
-                                // The eval of a speculated node should be the expected value,
-                                // which is actually just the average since each piece in the bag
-                                // has an equal probability of being chosen. So we'll track the
-                                // number of pieces in the bag, the total score, etc. to calculate
-                                // it later. We track the eval of the worst possibility to later
-                                // use for death evaluations.
-                                let mut possibilities = 0;
-                                let mut total = E::default();
-                                let mut deaths = 0;
+                            if let Some(speculation) = children[node_id].as_mut() {
+                                // The eval of a speculated node should be the expected value, so
+                                // each possibility is weighted by the chance of its piece coming
+                                // next. We track the eval of the worst possibility to use for
+                                // death evaluations, and to move the result towards the worst
+                                // case as much as the risk aversion says to.
+                                let mut outcomes = ArrayVec::<[_; 7]>::new();
                                 let mut worst = None;
-                                for children in children.values_mut()
-                                        .filter_map(Option::as_mut) {
-                                    possibilities += 1;
-                                    match process_children(children) {
-                                        Some(eval) => {
-                                            match worst {
-                                                None => worst = Some(eval.clone()),
-                                                Some(v) if eval < v => worst = Some(eval.clone()),
-                                                _ => {}
-                                            }
-                                            total = total + eval;
-                                        },
-                                        None => deaths += 1
+                                for (p, children) in &mut speculation.children {
+                                    if let Some(children) = children {
+                                        let eval = process_children(children);
+                                        match (&worst, &eval) {
+                                            (None, Some(eval)) => worst = Some(eval.clone()),
+                                            (Some(v), Some(eval)) if eval < v =>
+                                                worst = Some(eval.clone()),
+                                            _ => {}
+                                        }
+                                        outcomes.push((speculation.chances[p] as usize, eval));
                                     }
                                 }
-                                worst.map(|worst| (
-                                    total + worst.modify_death() * deaths
-                                ) / possibilities)
+                                worst.map(|worst| {
+                                    let total: usize = outcomes.iter().map(|&(c, _)| c).sum();
+                                    let mut expected = E::default();
+                                    let mut worst_case = worst.clone();
+                                    for (chance, eval) in outcomes {
+                                        let eval = eval.unwrap_or_else(|| {
+                                            worst_case = worst.clone().modify_death();
+                                            worst_case.clone()
+                                        });
+                                        expected = expected + eval * chance / total;
+                                    }
+                                    (expected * (100 - risk_aversion) + worst_case * risk_aversion)
+                                        / 100
+                                })
                             } else {
                                 // returns from closure and continues the loop
                                 return
@@ -531,8 +628,8 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                 Children::Speculated(childs) => {
                     let mut newchildren = Vec::with_capacity(childs.len());
                     for (j, child) in std::mem::take(childs).into_iter().enumerate() {
-                        newchildren.push(child.and_then(|mut cases|
-                            std::mem::take(&mut cases[piece])
+                        newchildren.push(child.and_then(|mut speculation|
+                            std::mem::take(&mut speculation.children[piece])
                         ));
                         to_update.push(j);
                     }
@@ -551,14 +648,20 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
     }
 
     pub fn get_plan(&self) -> Vec<(FallingPiece, LockResult)> {
-        let mut node = self.root;
+        self.plan_from(0, self.root, self.board.clone())
+    }
+
+    /// Follows the best moves from the specified node for as long as they are known.
+    fn plan_from(
+        &self, gen: usize, mut node: u32, mut board: Board
+    ) -> Vec<(FallingPiece, LockResult)> {
         let mut plan = vec![];
-        let mut board = self.board.clone();
-        for gen in &self.generations {
+        for gen in self.generations.iter().skip(gen) {
             let done = gen.rent(|gen| match &gen.children {
                 Children::Known(_, c) => match c[node as usize].as_ref().and_then(|c| c.first()) {
                     Some(child) => {
-                        plan.push((child.placement, advance(&mut board, child.placement)));
+                        let lock = advance(&mut board, child.placement, &self.attack_table);
+                        plan.push((child.placement, lock));
                         node = child.node;
                         false
                     }
@@ -571,17 +674,20 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         plan
     }
 
-    pub fn reset(&mut self, field: [[bool; 10]; 40], b2b: bool, combo: u32) -> Option<i32> {
+    pub fn reset(
+        &mut self, field: Vec<Vec<bool>>, b2b: bool, b2b_chain: u32, combo: u32
+    ) -> Option<i32> {
         let garbage_lines;
-        if b2b == self.board.b2b_bonus && combo == self.board.combo {
-            let mut b = Board::<u16>::new();
-            b.set_field(field);
+        if b2b == self.board.b2b_bonus && b2b_chain == self.board.b2b_chain &&
+                combo == self.board.combo {
+            let mut b = Board::<u16>::with_dimensions(self.board.width(), self.board.height());
+            b.set_field(&field);
             let dif = self.board.column_heights().iter()
                 .zip(b.column_heights().iter())
                 .map(|(&y1, &y2)| y2 - y1)
                 .min().unwrap();
             let mut is_garbage_receive = true;
-            for y in 0..(40 - dif) {
+            for y in 0..(ROWS as i32 - dif) {
                 if b.get_row(y + dif) != self.board.get_row(y) {
                     is_garbage_receive = false;
                     break;
@@ -596,9 +702,10 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             garbage_lines = None;
         }
 
-        self.board.set_field(field);
+        self.board.set_field(&field);
         self.board.combo = combo;
         self.board.b2b_bonus = b2b;
+        self.board.b2b_chain = b2b_chain;
 
         self.gens_passed += self.generations.len() as u32 + 1;
         self.root = 0;
@@ -608,7 +715,9 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         garbage_lines
     }
 
-    pub fn get_next_candidates(&self) -> Vec<MoveCandidate<E>> {
+    /// Lists the moves that can be played from the root, sorted best to worst. The principal
+    /// variations of the best `plans` moves are included.
+    pub fn get_next_candidates(&self, plans: usize) -> Vec<MoveCandidate<E>> {
         if self.generations.len() < 2 { return vec![]; }
         self.generations[0].rent(|gen| self.generations[1].rent(|child_gen| {
             let mut candidates = vec![];
@@ -619,14 +728,22 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                             continue
                         }
                         let mut board = self.board.clone();
-                        let lock = advance(&mut board, child.placement);
-                        let eval = child_gen.nodes[child.node as usize].evaluation.clone();
+                        let lock = advance(&mut board, child.placement, &self.attack_table);
+                        let node = &child_gen.nodes[child.node as usize];
+                        let plan = if candidates.len() < plans {
+                            let mut plan = vec![(child.placement, lock.clone())];
+                            plan.extend(self.plan_from(1, child.node, board.clone()));
+                            plan
+                        } else {
+                            vec![]
+                        };
                         candidates.push(MoveCandidate {
                             mv: child.placement,
                             hold: self.board.hold_piece != board.hold_piece,
-                            evaluation: eval + child.reward.clone(),
+                            evaluation: node.evaluation.clone() + child.reward.clone(),
                             original_rank: i as u32,
-                            lock, board,
+                            visits: node.visits,
+                            lock, board, plan
                         });
                     }
                 }
@@ -637,7 +754,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
 
     pub fn advance_move(&mut self, mv: FallingPiece) {
         if self.try_advance_move(mv).is_none() {
-            self.board.lock_piece(mv);
+            self.board.lock_piece(mv, &self.attack_table);
 
             self.gens_passed += self.generations.len() as u32 + 1;
             self.root = 0;
@@ -658,7 +775,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         )?;
 
         self.root = new_root;
-        advance(&mut self.board, mv);
+        advance(&mut self.board, mv, &self.attack_table);
         self.generations.pop_front();
         self.gens_passed += 1;
 
@@ -688,16 +805,357 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         &self.board
     }
 
+    /// An estimate of the number of bytes used by the search. Space that has been reserved but not
+    /// used yet isn't counted.
+    pub fn memory_usage(&self) -> u64 {
+        self.generations.iter().map(|gen| gen.rent_all(|gen| {
+            let children = match &gen.data.children {
+                Children::Known(_, c) => c.len() * std::mem::size_of::<Option<&mut [Child<R>]>>(),
+                Children::Speculated(c) =>
+                    c.len() * std::mem::size_of::<Option<Speculation<R>>>()
+            };
+            // every hashmap entry has an extra control byte.
+            let deduplicator = gen.data.deduplicator.len()
+                * (std::mem::size_of::<(SimplifiedBoard, u32)>() + 1);
+            let nodes = gen.data.nodes.len() * std::mem::size_of::<Node<E>>();
+            (gen.arena.allocated_bytes() + nodes + children + deduplicator) as u64
+        })).sum()
+    }
+
+    /// Discards the least visited parts of the search until it uses at most `target` bytes, or
+    /// until only the moves from the root are left.
+    ///
+    /// Nodes being thought about are forgotten, like in `DagState::restore`.
+    pub fn prune(&mut self, target: u64) {
+        let mut min_visits = 2;
+        while self.memory_usage() > target {
+            let root_visits = self.generations[0].rent(|gen| gen.nodes[self.root as usize].visits);
+            self.compact(min_visits);
+            if min_visits > root_visits {
+                break
+            }
+            min_visits *= 2;
+        }
+    }
+
+    /// Removes the children of the nodes visited fewer than `min_visits` times, except for the
+    /// root, along with every node that can no longer be reached from the root.
+    ///
+    /// The generations are copied into new arenas one at a time, freeing each old arena before
+    /// the next generation is copied.
+    fn compact(&mut self, min_visits: u32) {
+        let root = self.root as usize;
+        let lens: Vec<_> = self.generations.iter().map(|gen| gen.rent(|gen| gen.nodes.len()))
+            .collect();
+
+        // First find the new slab key of every node that is kept, and whether it keeps its
+        // children.
+        let mut new_keys: Vec<Vec<Option<u32>>> = vec![];
+        let mut expanded: Vec<Vec<bool>> = vec![];
+        let mut reachable = vec![false; lens[0]];
+        reachable[root] = true;
+        for (i, gen) in self.generations.iter().enumerate() {
+            let mut count = 0;
+            new_keys.push(reachable.iter().map(|&r| if r {
+                count += 1;
+                Some(count - 1)
+            } else {
+                None
+            }).collect());
+
+            let mut next_reachable = vec![false; lens.get(i+1).copied().unwrap_or(0)];
+            let keep = gen.rent(|gen| {
+                let keep: Vec<_> = gen.nodes.iter().enumerate().map(|(j, node)| reachable[j] &&
+                    (node.visits >= min_visits || i == 0 && j == root)
+                ).collect();
+                let mut mark = |children: &Option<&mut [Child<R>]>| {
+                    for child in children.iter().flat_map(|c| c.iter()) {
+                        next_reachable[child.node as usize] = true;
+                    }
+                };
+                match &gen.children {
+                    Children::Known(_, c) => for (j, children) in c.iter().enumerate() {
+                        if keep[j] {
+                            mark(children);
+                        }
+                    }
+                    Children::Speculated(c) => for (j, speculation) in c.iter().enumerate() {
+                        if let (true, Some(speculation)) = (keep[j], speculation) {
+                            for (_, children) in &speculation.children {
+                                mark(children);
+                            }
+                        }
+                    }
+                }
+                keep
+            });
+            reachable = next_reachable;
+            expanded.push(keep);
+        }
+
+        // Then copy the nodes that are kept to their new slab keys.
+        for i in 0..self.generations.len() {
+            let parents = i.checked_sub(1).map(|p| (&*expanded[p], &*new_keys[p]));
+            let next_keys = new_keys.get(i+1).map_or(&[][..], |keys| &keys[..]);
+            let (keys, keep) = (&new_keys[i], &expanded[i]);
+            let compacted = self.generations[i].rent(|gen| rented::Generation::new(
+                Box::new(bumpalo::Bump::new()),
+                |bump| gen.compact(bump, keys, keep, parents, next_keys)
+            ));
+            self.generations[i] = compacted;
+        }
+        self.root = 0;
+        // make sure the NodeIds handed out before pruning are seen as expired.
+        self.gens_passed += self.generations.len() as u32 + 1;
+    }
+
+    /// Serializes the search so that it can be continued later using `DagState::load`.
+    /// 
+    /// Marks on nodes that are being thought about are not saved.
+    pub fn save(&self) -> Vec<u8> where E: Serialize, R: Serialize {
+        bincode::serialize(&self.to_saved()).unwrap()
+    }
+
+    fn to_saved(&self) -> SavedDag<E, R> {
+        SavedDag {
+            board: self.board.clone(),
+            root: self.root,
+            use_hold: self.use_hold,
+            attack_table: self.attack_table.clone(),
+            generations: self.generations.iter().map(|gen| gen.rent(|gen| SavedGeneration {
+                nodes: gen.nodes.iter().map(|node| SavedNode {
+                    parents: node.parents.to_vec(),
+                    evaluation: node.evaluation.clone(),
+                    visits: node.visits,
+                    death: node.death
+                }).collect(),
+                children: match &gen.children {
+                    Children::Known(piece, childrens) => SavedChildren::Known(
+                        *piece,
+                        childrens.iter().map(|c| c.as_deref().map(<[_]>::to_vec)).collect()
+                    ),
+                    Children::Speculated(childrens) => SavedChildren::Speculated(
+                        childrens.iter().map(|c| c.as_ref().map(|speculation| {
+                            let mut saved = EnumMap::new();
+                            for (piece, c) in &speculation.children {
+                                saved[piece] = c.as_deref().map(<[_]>::to_vec);
+                            }
+                            (speculation.chances, saved)
+                        })).collect()
+                    )
+                },
+                deduplicator: gen.deduplicator.iter().map(|(board, &node)| (SavedBoard {
+                    grid: board.grid.to_vec(),
+                    combo: board.combo,
+                    randomizer: board.randomizer.clone(),
+                    reserve: board.reserve,
+                    back_to_back: board.back_to_back,
+                    b2b_chain: board.b2b_chain,
+                    reserve_is_hold: board.reserve_is_hold
+                }, node)).collect()
+            })).collect()
+        }
+    }
+
+    /// Restores a search saved by `DagState::save`.
+    /// 
+    /// Returns `None` if the data is not a valid saved search.
+    pub fn load(
+        data: &[u8], risk_aversion: u32
+    ) -> Option<Self> where E: DeserializeOwned, R: DeserializeOwned {
+        Self::from_saved(bincode::deserialize(data).ok()?, risk_aversion)
+    }
+
+    fn from_saved(saved: SavedDag<E, R>, risk_aversion: u32) -> Option<Self> {
+        if !saved.is_consistent() {
+            return None
+        }
+
+        let generations = saved.generations.into_iter().map(|gen| rented::Generation::new(
+            Box::new(bumpalo::Bump::new()),
+            move |bump| Generation {
+                nodes: gen.nodes.into_iter().map(|node| Node {
+                    parents: BumpVec::from_iter_in(node.parents, bump),
+                    evaluation: node.evaluation,
+                    visits: node.visits,
+                    marked: false,
+                    death: node.death
+                }).collect(),
+                children: match gen.children {
+                    SavedChildren::Known(piece, childrens) => Children::Known(
+                        piece,
+                        childrens.into_iter()
+                            .map(|c| c.map(|c| bump.alloc_slice_fill_iter(c)))
+                            .collect()
+                    ),
+                    SavedChildren::Speculated(childrens) => Children::Speculated(
+                        childrens.into_iter().map(|c| c.map(|(chances, cases)| {
+                            let mut children = EnumMap::new();
+                            for (piece, c) in cases {
+                                children[piece] = c.map(|c| bump.alloc_slice_fill_iter(c));
+                            }
+                            Speculation { chances, children }
+                        })).collect()
+                    )
+                },
+                deduplicator: gen.deduplicator.into_iter().map(|(board, node)| (SimplifiedBoard {
+                    grid: bump.alloc_slice_copy(&board.grid),
+                    combo: board.combo,
+                    randomizer: board.randomizer,
+                    reserve: board.reserve,
+                    back_to_back: board.back_to_back,
+                    b2b_chain: board.b2b_chain,
+                    reserve_is_hold: board.reserve_is_hold
+                }, node)).collect()
+            }
+        )).collect();
+
+        Some(DagState {
+            board: saved.board,
+            generations,
+            root: saved.root,
+            gens_passed: 0,
+            use_hold: saved.use_hold,
+            attack_table: saved.attack_table,
+            risk_aversion
+        })
+    }
+
+    /// Replaces the search with one saved by `DagState::save`, returning whether the data was valid.
+    pub fn restore(&mut self, data: &[u8]) -> bool where E: DeserializeOwned, R: DeserializeOwned {
+        match Self::load(data, self.risk_aversion) {
+            Some(mut loaded) => {
+                // make sure the NodeIds handed out by the old search are seen as expired.
+                loaded.gens_passed = self.gens_passed + self.generations.len() as u32 + 1;
+                *self = loaded;
+                true
+            }
+            None => false
+        }
+    }
+
     pub fn is_dead(&self) -> bool {
         self.generations[0].rent(|gen| match &gen.children {
             Children::Known(_, childrens) =>
-                childrens[self.root as usize].as_ref().map_or(false, |s| s.is_empty()),
+                childrens[self.root as usize].as_ref().is_some_and(|s| s.is_empty()),
+            // Dead if every piece that can come next has no moves.
             Children::Speculated(childrens) =>
-                childrens[self.root as usize].as_ref().map_or(false, |s| s.is_empty()),
+                childrens[self.root as usize].as_ref().is_some_and(|s| {
+                    s.children.iter().filter_map(|(_, c)| c.as_ref()).all(|c| c.is_empty())
+                }),
         })
     }
 }
 
+impl<E, R> SavedDag<E, R> {
+    /// Checks that all of the links between nodes are in bounds, so that corrupt data can't cause
+    /// panics later on.
+    fn is_consistent(&self) -> bool {
+        let gens = &self.generations;
+        let root_valid = gens.first().is_some_and(|gen| (self.root as usize) < gen.nodes.len());
+        root_valid && gens.iter().enumerate().all(|(i, gen)| {
+            // the parents of the root generation were discarded when it became the root.
+            let prev_len = if i == 0 { usize::MAX } else { gens[i-1].nodes.len() };
+            let next_len = gens.get(i+1).map_or(0, |gen| gen.nodes.len());
+            let children_valid = |c: &Option<Vec<Child<R>>>| c.as_ref().is_none_or(
+                |c| c.iter().all(|c| (c.node as usize) < next_len)
+            );
+            let children_len = match &gen.children {
+                SavedChildren::Known(_, c) => {
+                    if !c.iter().all(children_valid) {
+                        return false
+                    }
+                    c.len()
+                }
+                SavedChildren::Speculated(c) => {
+                    if !c.iter().flatten().all(|(_, cases)| cases.values().all(children_valid)) {
+                        return false
+                    }
+                    c.len()
+                }
+            };
+            children_len == gen.nodes.len() &&
+                gen.nodes.iter().all(|n| n.parents.iter().all(|&p| (p as usize) < prev_len)) &&
+                gen.deduplicator.iter().all(|&(_, node)| (node as usize) < gen.nodes.len())
+        })
+    }
+}
+
+impl<'c, E: Clone, R: Clone> Generation<'c, E, R> {
+    /// Copies the nodes that have a new slab key in `keys` into `bump`. Nodes that aren't in
+    /// `keep` lose their children.
+    ///
+    /// `parents` is which nodes of the previous generation keep their children and their new slab
+    /// keys, and `next_keys` is the new slab keys of the next generation.
+    fn compact<'b>(
+        &self,
+        bump: &'b bumpalo::Bump,
+        keys: &[Option<u32>],
+        keep: &[bool],
+        parents: Option<(&[bool], &[Option<u32>])>,
+        next_keys: &[Option<u32>]
+    ) -> Generation<'b, E, R> {
+        let kept = (0..keys.len()).filter(|&j| keys[j].is_some());
+        let nodes = kept.clone().map(|j| &self.nodes[j]).map(|node| Node {
+            parents: match parents {
+                // the parents of the root generation were discarded when it became the root.
+                None => BumpVec::from_iter_in(node.parents.iter().copied(), bump),
+                // nodes are no longer children of the parents that lost their children.
+                Some((expanded, keys)) => BumpVec::from_iter_in(
+                    node.parents.iter()
+                        .filter(|&&p| expanded[p as usize])
+                        .map(|&p| keys[p as usize].unwrap()),
+                    bump
+                )
+            },
+            evaluation: node.evaluation.clone(),
+            visits: node.visits,
+            marked: false,
+            death: node.death
+        }).collect();
+
+        let copy = |j: usize, children: &Option<&mut [Child<R>]>| match children {
+            Some(children) if keep[j] => Some(&mut *bump.alloc_slice_fill_iter(
+                children.iter().map(|c| Child {
+                    node: next_keys[c.node as usize].unwrap(),
+                    ..c.clone()
+                })
+            )),
+            _ => None
+        };
+        let children = match &self.children {
+            Children::Known(piece, c) => Children::Known(
+                *piece,
+                kept.map(|j| copy(j, &c[j])).collect()
+            ),
+            Children::Speculated(c) => Children::Speculated(
+                kept.map(|j| {
+                    let speculation = c[j].as_ref().filter(|_| keep[j])?;
+                    let mut children = EnumMap::new();
+                    for (piece, c) in &speculation.children {
+                        children[piece] = copy(j, c);
+                    }
+                    Some(Speculation { chances: speculation.chances, children })
+                }).collect()
+            )
+        };
+
+        let deduplicator = self.deduplicator.iter().filter_map(|(board, &node)| {
+            Some((SimplifiedBoard {
+                grid: bump.alloc_slice_copy(board.grid),
+                combo: board.combo,
+                randomizer: board.randomizer.clone(),
+                reserve: board.reserve,
+                back_to_back: board.back_to_back,
+                b2b_chain: board.b2b_chain,
+                reserve_is_hold: board.reserve_is_hold
+            }, keys[node as usize]?))
+        }).collect();
+
+        Generation { nodes, children, deduplicator }
+    }
+}
+
 fn child_eval_fn<'a, E, R>(child_gen_nodes: &'a [Node<E>]) -> impl Fn(&Child<R>) -> Option<E> + 'a
 where
     E: Evaluation<R>,
@@ -714,8 +1172,8 @@ where
 }
 
 /// keeps queue state consistent while arbitrarily placing pieces
-fn advance(board: &mut Board, placement: FallingPiece) -> LockResult {
-    let result = board.lock_piece(placement);
+fn advance(board: &mut Board, placement: FallingPiece, attack_table: &AttackTable) -> LockResult {
+    let result = board.lock_piece(placement, attack_table);
     let next = board.advance_queue().unwrap();
     if next != placement.kind.0 {
         let unheld = board.hold(next);
@@ -740,7 +1198,7 @@ fn build_children<'arena, E: Evaluation<R> + 'static, R: Clone + 'static>(
         |(i, data)| {
             // this arrayvec will almost always be shorter than 40 elements,
             // since it won't store the upper empty rows. this is to save memory.
-            let mut simple_grid = ArrayVec::<[_; 40]>::new();
+            let mut simple_grid = ArrayVec::<[_; ROWS]>::new();
             let terrain_height = data.board.column_heights().iter().copied().max().unwrap();
             for y in 0..terrain_height {
                 simple_grid.push(*data.board.get_row(y));
@@ -749,8 +1207,9 @@ fn build_children<'arena, E: Evaluation<R> + 'static, R: Clone + 'static>(
             let simple_board = SimplifiedBoard {
                 grid: &simple_grid,
                 back_to_back: data.board.b2b_bonus,
+                b2b_chain: data.board.b2b_chain,
                 combo: data.board.combo,
-                bag: data.board.next_bag(),
+                randomizer: data.board.randomizer.clone(),
                 reserve: if hold_allowed {
                     data.board.hold_piece.unwrap_or_else(
                         || data.board.next_queue().next().unwrap()
@@ -768,6 +1227,7 @@ fn build_children<'arena, E: Evaluation<R> + 'static, R: Clone + 'static>(
                     children_gen.data.nodes.push(Node {
                         parents: BumpVec::new_in(&children_gen.arena),
                         evaluation: data.evaluation,
+                        visits: 0,
                         death: false,
                         marked: false
                     });
@@ -823,3 +1283,113 @@ fn remove_last<T>(slice: &mut &mut [T]) {
     let (_, rest) = data.split_last_mut().expect("Slice is empty");
     *slice = rest;
 }
+
+#[cfg(test)]
+mod tests {
+    use libtetris::*;
+    use crate::evaluation::{ Evaluator, Standard };
+    use crate::selection::AnySelectionPolicy;
+    use super::*;
+
+    type Dag = DagState<<Standard as Evaluator>::Value, <Standard as Evaluator>::Reward>;
+
+    /// A search without hold that knows the first bag and has to speculate after it.
+    fn new_dag() -> Dag {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::I, Piece::O, Piece::L, Piece::J, Piece::S, Piece::Z] {
+            board.add_next_piece(piece);
+        }
+        DagState::new(board, false, AttackTable::default(), 0)
+    }
+
+    fn search(dag: &mut Dag, iterations: usize) {
+        let eval = Standard::default();
+        for _ in 0..iterations {
+            let (node, board) = dag.find_and_mark_leaf(
+                &mut vec![], &AnySelectionPolicy::default()
+            ).unwrap();
+            match board.get_next_piece() {
+                Ok(_) => dag.update_known(node, children(board, &eval)),
+                Err(possibilities) => {
+                    let mut children_of = EnumMap::new();
+                    for piece in possibilities {
+                        let mut b = board.clone();
+                        b.add_next_piece(piece);
+                        children_of[piece] = Some(children(b, &eval));
+                    }
+                    dag.update_speculated(node, board.randomizer.distribution(), children_of);
+                }
+            }
+        }
+    }
+
+    fn children(
+        mut board: Board, eval: &Standard
+    ) -> Vec<ChildData<<Standard as Evaluator>::Value, <Standard as Evaluator>::Reward>> {
+        let piece = board.advance_queue().unwrap();
+        let spawned = match SpawnRule::Row19Or20.spawn(piece, &board) {
+            Some(spawned) => spawned,
+            None => return vec![]
+        };
+        find_moves(&board, spawned, MovementMode::ZeroG, &RotationSystem::Srs, false)
+            .into_iter()
+            .map(|mv| {
+                let mut result = board.clone();
+                let lock = result.lock_piece(mv.location, &AttackTable::default());
+                let (evaluation, reward) = eval.evaluate(&lock, &result, mv.inputs.time, piece);
+                ChildData { mv: mv.location, board: result, evaluation, reward }
+            })
+            .collect()
+    }
+
+    fn root_visits(dag: &Dag) -> u32 {
+        dag.generations[0].rent(|gen| gen.nodes[dag.root as usize].visits)
+    }
+
+    /// The moves from the root, best first, with their evaluations and visit counts.
+    fn root_children(dag: &Dag) -> Vec<(FallingPiece, i64, u32)> {
+        dag.get_next_candidates(0).into_iter()
+            .map(|c| (c.mv, c.evaluation.score(), c.visits))
+            .collect()
+    }
+
+    #[test]
+    fn loading_a_saved_search_restores_it() {
+        let mut dag = new_dag();
+        search(&mut dag, 300);
+
+        let loaded = Dag::load(&dag.save(), 0).unwrap();
+        assert_eq!(loaded.board().get_field(), dag.board().get_field());
+        assert_eq!(loaded.nodes(), dag.nodes());
+        assert_eq!(loaded.depth(), dag.depth());
+        assert_eq!(root_visits(&loaded), root_visits(&dag));
+        assert_eq!(root_children(&loaded), root_children(&dag));
+        assert!(!root_children(&loaded).is_empty());
+    }
+
+    #[test]
+    fn pruning_keeps_the_moves_from_the_root() {
+        let mut dag = new_dag();
+        search(&mut dag, 300);
+        let nodes = dag.nodes();
+        let visits = root_visits(&dag);
+        let moves = root_children(&dag);
+        let target = dag.memory_usage() / 2;
+
+        dag.prune(target);
+        assert!(dag.memory_usage() <= target);
+        assert!(dag.nodes() < nodes);
+        assert_eq!(root_visits(&dag), visits);
+        assert_eq!(root_children(&dag), moves);
+
+        // the pruned search is still consistent, so it can be saved and searched further.
+        assert!(Dag::load(&dag.save(), 0).is_some());
+        search(&mut dag, 100);
+        assert!(root_visits(&dag) > visits);
+    }
+
+    #[test]
+    fn loading_rejects_invalid_data() {
+        assert!(Dag::load(&[1, 2, 3], 0).is_none());
+    }
+}
@@ -1,31 +1,47 @@
-use crossbeam_channel::{ Sender, Receiver, TryRecvError, unbounded, select };
+use crossbeam_channel::{ Sender, Receiver, TryRecvError, unbounded, select, after, never };
 use std::sync::Arc;
+use enum_map::EnumMap;
 use libtetris::*;
 use opening_book::Book;
 use crate::evaluation::Evaluator;
 use crate::modes::ModeSwitchedBot;
-use crate::{ Options, Info, BotMsg, BotPollState };
+use crate::modes::normal::BotState;
+use crate::{ Options, Info, BotMsg, BotPollState, Opponent, LaunchError };
 
 pub struct Interface {
     send: Sender<BotMsg>,
     recv: Receiver<(Move, Info)>,
+    search: Receiver<SearchReply>
+}
+
+/// The bot's answers to `export_search` and `import_search`.
+enum SearchReply {
+    Exported(Option<Vec<u8>>),
+    Imported(bool)
 }
 
 impl Interface {
     /// Launches a bot thread with the specified starting board and options.
+    /// 
+    /// Fails if the options ask for no threads or if the board is wider than the evaluator
+    /// supports.
     pub fn launch(
         board: Board,
         options: Options,
-        evaluator: impl Evaluator + Send + 'static,
+        evaluator: impl Evaluator + 'static,
         book: Option<Arc<Book>>
-    ) -> Self {
+    ) -> Result<Self, LaunchError> {
+        crate::check_launch(&board, &options, &evaluator)?;
         let (bot_send, recv) = unbounded();
         let (send, bot_recv) = unbounded();
-        std::thread::spawn(move || run(bot_recv, bot_send, board, evaluator, options, book));
+        let (search_send, search) = unbounded();
+        std::thread::spawn(move || run(
+            bot_recv, bot_send, search_send, board, evaluator, options, book
+        ));
 
-        Interface {
-            send, recv
-        }
+        Ok(Interface {
+            send, recv, search
+        })
     }
 
     /// Request the bot to provide a move as soon as possible.
@@ -45,8 +61,27 @@ impl Interface {
     /// 
     /// Once a move is chosen, the move will become available by calling `poll_next_move` or
     /// `block_next_move`. To update the bot state according to this move, call `play_next_move`.
-    pub fn suggest_next_move(&self, incoming: u32) {
-        self.send.send(BotMsg::SuggestMove(incoming)).ok();
+    /// 
+    /// `incoming` lists the attacks waiting to enter the bot's board, oldest first.
+    pub fn suggest_next_move(&self, incoming: &[IncomingGarbage]) {
+        self.send.send(BotMsg::SuggestMove(incoming.to_vec())).ok();
+    }
+
+    /// Tells the bot about the state of its opponent.
+    /// 
+    /// The evaluator receives the most recent opponent state when picking a move, so this should
+    /// be called before `suggest_next_move` whenever the opponent's board changes.
+    pub fn update_opponent(&self, opponent: Opponent) {
+        self.send.send(BotMsg::UpdateOpponent(opponent)).ok();
+    }
+
+    /// Overrides the chance of each piece being the next unknown piece, for when you know more
+    /// about the randomizer than the board does. The chances should add up to 1. The unknown
+    /// pieces after that one still come from the board's randomizer.
+    /// 
+    /// Passing `None` goes back to the chances given by the board's randomizer.
+    pub fn set_piece_distribution(&self, distribution: Option<EnumMap<Piece, f64>>) {
+        self.send.send(BotMsg::SetPieceDistribution(distribution)).ok();
     }
 
     /// Checks to see if the bot has provided the previously requested move yet.
@@ -78,14 +113,14 @@ impl Interface {
 
     /// Adds a new piece to the end of the queue.
     /// 
-    /// If speculation is enabled, the piece *must* be in the bag. For example, if in the current
-    /// bag you've provided the sequence IJOZT, then the next time you call this function you can
-    /// only provide either an L or an S piece.
+    /// If speculation is enabled, the piece *must* be one the board's randomizer can produce. For
+    /// example, with a 7-bag randomizer, if in the current bag you've provided the sequence IJOZT,
+    /// then the next time you call this function you can only provide either an L or an S piece.
     pub fn add_next_piece(&self, piece: Piece) {
         self.send.send(BotMsg::NewPiece(piece)).ok();
     }
 
-    /// Resets the playfield, back-to-back status, and combo count.
+    /// Resets the playfield, back-to-back status, back-to-back chain length, and combo count.
     /// 
     /// This should only be used when garbage is received or when your client could not place the
     /// piece in the correct position for some reason (e.g. 15 move rule), since this forces the
@@ -93,47 +128,89 @@ impl Interface {
     /// 
     /// Note: combo is not the same as the displayed combo in guideline games. Here, it is the
     /// number of consecutive line clears achieved. So, generally speaking, if "x Combo" appears
-    /// on the screen, you need to use x+1 here.
-    pub fn reset(&self, field: [[bool; 10]; 40], b2b_active: bool, combo: u32) {
+    /// on the screen, you need to use x+1 here. Likewise, `b2b_chain` is the number of
+    /// consecutive back-to-back clears, which only matters for attack tables that reward long
+    /// chains.
+    pub fn reset(&self, field: Vec<Vec<bool>>, b2b_active: bool, b2b_chain: u32, combo: u32) {
         self.send.send(BotMsg::Reset {
-            field, b2b: b2b_active, combo
+            field, b2b: b2b_active, b2b_chain, combo
         }).ok();
     }
 
+    /// Saves the bot's state and search tree so that the analysis can be continued later using
+    /// `import_search`.
+    /// 
+    /// Returns `None` if the bot is dead, hasn't started searching yet, or isn't using its normal
+    /// search (e.g. because it's PC looping).
+    pub fn export_search(&self) -> Option<Vec<u8>> {
+        self.send.send(BotMsg::ExportSearch).ok()?;
+        match self.search.recv().ok()? {
+            SearchReply::Exported(data) => data,
+            SearchReply::Imported(_) => None
+        }
+    }
+
+    /// Replaces the bot's state and search tree with one saved by `export_search`.
+    /// 
+    /// The playfield, queue and hold piece are replaced as well. The data must have been saved by
+    /// a bot using the same evaluator. Returns `false` if it wasn't or if the bot is dead, in which
+    /// case the bot keeps its current search.
+    pub fn import_search(&self, data: Vec<u8>) -> bool {
+        if self.send.send(BotMsg::ImportSearch(data)).is_err() {
+            return false
+        }
+        matches!(self.search.recv(), Ok(SearchReply::Imported(true)))
+    }
+
     /// Specifies a line that Cold Clear should analyze before making any moves.
     pub fn force_analysis_line(&self, path: Vec<FallingPiece>) {
         self.send.send(BotMsg::ForceAnalysisLine(path)).ok();
     }
 }
 
-fn run(
+fn run<E: Evaluator + 'static>(
     recv: Receiver<BotMsg>,
     send: Sender<(Move, Info)>,
+    search_send: Sender<SearchReply>,
     mut board: Board,
-    eval: impl Evaluator + 'static,
+    eval: E,
     options: Options,
     book: Option<Arc<Book>>
 ) {
-    if options.threads == 0 {
-        panic!("Invalid number of threads: 0");
-    }
-
-    while board.next_queue().next().is_none() {
+    let mut opponent = None;
+    let mut distribution = None;
+    let mut imported = None;
+    while imported.is_none() && board.next_queue().next().is_none() {
         match recv.recv() {
             Err(_) => return,
             Ok(BotMsg::NewPiece(piece)) => board.add_next_piece(piece),
-            Ok(BotMsg::Reset { field, b2b, combo }) =>{
-                board.set_field(field);
+            Ok(BotMsg::Reset { field, b2b, b2b_chain, combo }) =>{
+                board.set_field(&field);
                 board.combo = combo;
                 board.b2b_bonus = b2b;
+                board.b2b_chain = b2b_chain;
             }
             Ok(BotMsg::SuggestMove(_)) => {}
+            Ok(BotMsg::UpdateOpponent(state)) => opponent = Some(state),
+            Ok(BotMsg::SetPieceDistribution(chances)) => distribution = chances,
+            Ok(BotMsg::ExportSearch) => { search_send.send(SearchReply::Exported(None)).ok(); }
+            Ok(BotMsg::ImportSearch(data)) => {
+                imported = BotState::<E>::from_search(&data, options.clone());
+                search_send.send(SearchReply::Imported(imported.is_some())).ok();
+            }
             Ok(BotMsg::ForceAnalysisLine(_)) => {}
             Ok(BotMsg::PlayMove(_)) => {}
         }
     }
 
-    let mut bot = ModeSwitchedBot::new(board, options, book.as_deref());
+    let mut bot = match imported {
+        Some(state) => ModeSwitchedBot::with_state(state, options.clone(), book.as_deref()),
+        None => ModeSwitchedBot::new(board, options.clone(), book.as_deref())
+    };
+    if let Some(opponent) = opponent {
+        bot.message(BotMsg::UpdateOpponent(opponent));
+    }
+    bot.message(BotMsg::SetPieceDistribution(distribution));
 
     let pool = rayon::ThreadPoolBuilder::new()
         .num_threads(options.threads as usize)
@@ -155,12 +232,21 @@ fn run(
             });
         }
 
+        // Wake up when a time limit passes, even if nothing else happens.
+        let wake = bot.wake_after().map_or_else(never, after);
         select! {
             recv(result_recv) -> result => bot.task_complete(result.unwrap()),
             recv(recv) -> msg => match msg {
+                Ok(BotMsg::ExportSearch) => {
+                    search_send.send(SearchReply::Exported(bot.export_search())).ok();
+                }
+                Ok(BotMsg::ImportSearch(data)) => {
+                    search_send.send(SearchReply::Imported(bot.import_search(&data))).ok();
+                }
                 Ok(msg) => bot.message(msg),
                 Err(_) => break
-            }
+            },
+            recv(wake) -> _ => {}
         }
 
         if bot.is_dead() {
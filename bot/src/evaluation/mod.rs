@@ -1,22 +1,39 @@
-use libtetris::{ LockResult, Board, Piece };
+use libtetris::{ LockResult, Board, Piece, IncomingGarbage, MAX_WIDTH };
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use crate::dag::MoveCandidate;
+use crate::Opponent;
 
 mod standard;
-pub use self::standard::Standard;
+pub use self::standard::{ Standard, Analysis };
 pub mod changed;
 
 pub trait Evaluator : Send + Sync {
-    type Value: Evaluation<Self::Reward> + Send + 'static;
-    type Reward: Clone + Send + 'static;
+    type Value: Evaluation<Self::Reward> + Serialize + DeserializeOwned + Send + 'static;
+    type Reward: Clone + Serialize + DeserializeOwned + Send + 'static;
 
     fn name(&self) -> String;
 
+    /// The widest board the evaluator can evaluate. Bots refuse to play on wider boards.
+    fn max_width(&self) -> usize {
+        MAX_WIDTH
+    }
+
     fn evaluate(
         &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
     ) -> (Self::Value, Self::Reward);
 
+    /// Picks the move to play from the candidates, which are sorted best to worst.
+    ///
+    /// `incoming` lists the attacks waiting to enter the board, oldest first. Lines sent by the
+    /// move cancel the oldest attacks first.
+    ///
+    /// `opponent` is the most recent state of the opponent, if the bot has been told about one.
     fn pick_move(
-        &self, candidates: Vec<MoveCandidate<Self::Value>>, _incoming: u32
+        &self,
+        candidates: Vec<MoveCandidate<Self::Value>>,
+        _incoming: &[IncomingGarbage],
+        _opponent: Option<&Opponent>
     ) -> MoveCandidate<Self::Value> {
         candidates.into_iter().next().unwrap()
     }
@@ -32,6 +49,9 @@ pub trait Evaluation<R> : Eq + Ord + Default + Clone
     fn weight(self, min: &Self, rank: usize) -> i64;
 
     fn improve(&mut self, other: Self);
+
+    /// Summarizes the evaluation as a single number for reporting. Higher is better.
+    fn score(&self) -> i64;
 }
 
 impl<T: Evaluator> Evaluator for std::sync::Arc<T> {
@@ -42,6 +62,10 @@ impl<T: Evaluator> Evaluator for std::sync::Arc<T> {
         (**self).name()
     }
 
+    fn max_width(&self) -> usize {
+        (**self).max_width()
+    }
+
     fn evaluate(
         &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
     ) -> (T::Value, T::Reward) {
@@ -49,8 +73,11 @@ impl<T: Evaluator> Evaluator for std::sync::Arc<T> {
     }
 
     fn pick_move(
-        &self, candidates: Vec<MoveCandidate<Self::Value>>, incoming: u32
+        &self,
+        candidates: Vec<MoveCandidate<Self::Value>>,
+        incoming: &[IncomingGarbage],
+        opponent: Option<&Opponent>
     ) -> MoveCandidate<Self::Value> {
-        (**self).pick_move(candidates, incoming)
+        (**self).pick_move(candidates, incoming, opponent)
     }
 }
\ No newline at end of file
@@ -22,6 +22,8 @@ pub struct Standard {
     pub tslot: [i32; 4],
     pub well_depth: i32,
     pub max_well_depth: i32,
+    /// Indexed by the column of the well. There is a weight for each column of a 10 wide board,
+    /// so boards wider than that aren't supported.
     pub well_column: [i32; 10],
 
     pub b2b_clear: i32,
@@ -34,14 +36,22 @@ pub struct Standard {
     pub tspin3: i32,
     pub mini_tspin1: i32,
     pub mini_tspin2: i32,
+    /// Added to the line clear reward when a piece other than T spins.
+    pub spin_clear: i32,
+    /// Added to the line clear reward when a piece other than T does a mini spin.
+    pub mini_spin_clear: i32,
     pub perfect_clear: i32,
     pub combo_garbage: i32,
     pub move_time: i32,
     pub wasted_t: i32,
 
+    /// Counts the T pieces left in the current 7-bag. Only has an effect with a 7-bag randomizer.
     pub use_bag: bool,
     pub timed_jeopardy: bool,
     pub stack_pc_damage: bool,
+    /// Plays a safe move that should top out the opponent instead of the best move when there
+    /// is one.
+    pub prefer_kills: bool,
     pub sub_name: Option<String>
 }
 
@@ -79,12 +89,15 @@ impl Default for Standard {
             tspin3: 602,
             mini_tspin1: -158,
             mini_tspin2: -93,
+            spin_clear: 0,
+            mini_spin_clear: 0,
             perfect_clear: 999,
             combo_garbage: 150,
 
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            prefer_kills: false,
             sub_name: None
         }
     }
@@ -121,6 +134,8 @@ impl Standard {
             tspin3: 628,
             mini_tspin1: -188,
             mini_tspin2: -682,
+            spin_clear: 0,
+            mini_spin_clear: 0,
             perfect_clear: 991,
             combo_garbage: 272,
             move_time: -1,
@@ -128,9 +143,123 @@ impl Standard {
             use_bag: true,
             timed_jeopardy: false,
             stack_pc_damage: false,
+            prefer_kills: false,
             sub_name: None
         }
     }
+
+    /// Computes the static evaluation of a board broken down by feature. Each term is already
+    /// multiplied by its weight, so the terms add up to the value `evaluate` gives the board.
+    pub fn analyze(&self, board: &Board) -> Analysis {
+        let mut analysis = Analysis::default();
+
+        if board.b2b_bonus {
+            analysis.back_to_back = self.back_to_back;
+        }
+
+        let height = board.height() as i32;
+        let highest_point = *board.column_heights().iter().max().unwrap();
+        analysis.top_quarter = self.top_quarter * (highest_point - height * 3 / 4).max(0);
+        analysis.top_half = self.top_half * (highest_point - height / 2).max(0);
+
+        let seven_bag = matches!(board.randomizer, AnyRandomizer::SevenBag(_));
+        let ts = if self.use_bag && seven_bag {
+            board.next_bag().contains(Piece::T) as usize
+                + (board.next_bag().len() <= 3) as usize
+                + (board.hold_piece == Some(Piece::T)) as usize
+        } else {
+            1 + (board.hold_piece == Some(Piece::T)) as usize
+        };
+
+        let mut board = board.clone();
+        for _ in 0..ts {
+            let cutout_location = sky_tslot_left(&board)
+                .or_else(|| sky_tslot_right(&board))
+                .or_else(|| {
+                    let tst = tst_twist_left(&board).or_else(|| tst_twist_right(&board))?;
+                    cave_tslot(&board, tst).or_else(|| {
+                        let corners = board.occupied(tst.x-1, tst.y-1) as usize
+                            + board.occupied(tst.x+1, tst.y-1) as usize
+                            + board.occupied(tst.x-1, tst.y+1) as usize
+                            + board.occupied(tst.x+1, tst.y+1) as usize;
+                        if corners >= 3 && board.on_stack(&tst) {
+                            Some(tst)
+                        } else {
+                            None
+                        }
+                    })
+                })
+                .or_else(|| fin_left(&board))
+                .or_else(|| fin_right(&board));
+            let result = match cutout_location {
+                Some(location) => cutout_tslot(board.clone(), location),
+                None => break
+            };
+            analysis.tslot += self.tslot[result.lines];
+            if let Some(b) = result.result {
+                board = b;
+            } else {
+                break
+            }
+        }
+
+        let highest_point = *board.column_heights().iter().max().unwrap();
+        analysis.height = self.height * highest_point;
+
+        let mut well = 0;
+        for x in 1..board.width() {
+            if board.column_heights()[x] <= board.column_heights()[well] {
+                well = x;
+            }
+        }
+
+        let mut depth = 0;
+        'yloop: for y in board.column_heights()[well] .. height {
+            for x in 0..board.width() as i32 {
+                if x as usize != well && !board.occupied(x, y) {
+                    break 'yloop;
+                }
+            }
+            depth += 1;
+        }
+        let depth = depth.min(self.max_well_depth);
+        analysis.well_depth = self.well_depth * depth;
+        if depth != 0 {
+            analysis.well_column = self.well_column[well];
+        }
+
+        if self.row_transitions != 0 {
+            let wall = 1 << board.width();
+            analysis.row_transitions = self.row_transitions * (0..ROWS as i32)
+                .map(|y| *board.get_row(y) as u32)
+                .map(|r| (r | wall) ^ (1 | r << 1))
+                .map(|d| d.count_ones() as i32)
+                .sum::<i32>();
+        }
+
+        if self.bumpiness | self.bumpiness_sq != 0 {
+            let (bump, bump_sq) = bumpiness(&board, well);
+            analysis.bumpiness = bump * self.bumpiness;
+            analysis.bumpiness_sq = bump_sq * self.bumpiness_sq;
+        }
+
+        if self.cavity_cells | self.cavity_cells_sq |
+                self.overhang_cells | self.overhang_cells_sq != 0 {
+            let (cavity_cells, overhang_cells) = cavities_and_overhangs(&board);
+            analysis.cavity_cells = self.cavity_cells * cavity_cells;
+            analysis.cavity_cells_sq = self.cavity_cells_sq * cavity_cells * cavity_cells;
+            analysis.overhang_cells = self.overhang_cells * overhang_cells;
+            analysis.overhang_cells_sq = self.overhang_cells_sq * overhang_cells * overhang_cells;
+        }
+
+        if self.covered_cells | self.covered_cells_sq != 0 {
+            let (covered_cells, covered_cells_sq) = covered_cells(&board);
+            analysis.covered_cells = self.covered_cells * covered_cells;
+            analysis.covered_cells_sq = self.covered_cells_sq * covered_cells_sq;
+        }
+
+        analysis
+    }
 }
 
 impl Evaluator for Standard {
@@ -146,15 +275,36 @@ impl Evaluator for Standard {
         info
     }
 
+    fn max_width(&self) -> usize {
+        self.well_column.len()
+    }
+
     fn pick_move(
-        &self, candidates: Vec<MoveCandidate<Value>>, incoming: u32
+        &self,
+        candidates: Vec<MoveCandidate<Value>>,
+        incoming: &[IncomingGarbage],
+        opponent: Option<&Opponent>
     ) -> MoveCandidate<Value> {
         let mut backup = None;
+        let mut safe = None;
         for mv in candidates.into_iter() {
-            if incoming == 0 || mv.board.column_heights()[3..6].iter().all(
-                |h| incoming as i32 - mv.lock.garbage_sent as i32 + h <= 20
+            let center = mv.board.width() / 2;
+            let landing = landing_garbage(incoming, mv.lock.garbage_sent) as i32;
+            if landing == 0 || mv.board.column_heights()[center-2..center+1].iter().all(
+                |h| landing + h <= mv.board.height() as i32
             ) {
-                return mv
+                // Unless we go for kills, the best safe move is all we're looking for.
+                let opponent = match opponent {
+                    Some(opponent) if self.prefer_kills => opponent,
+                    _ => return mv
+                };
+                if opponent.killed_by(mv.lock.garbage_sent, incoming) {
+                    return mv
+                }
+                if safe.is_none() {
+                    safe = Some(mv);
+                }
+                continue
             }
 
             match backup {
@@ -164,13 +314,12 @@ impl Evaluator for Standard {
             }
         }
 
-        return backup.unwrap();
+        safe.or(backup).unwrap()
     }
 
     fn evaluate(
         &self, lock: &LockResult, board: &Board, move_time: u32, placed: Piece
     ) -> (Value, Reward) {
-        let mut transient_eval = 0;
         let mut acc_eval = 0;
 
         if lock.perfect_clear {
@@ -180,10 +329,7 @@ impl Evaluator for Standard {
             if lock.b2b {
                 acc_eval += self.b2b_clear;
             }
-            if let Some(combo) = lock.combo {
-                let combo = combo.min(11) as usize;
-                acc_eval += self.combo_garbage * libtetris::COMBO_GARBAGE[combo] as i32;
-            }
+            acc_eval += self.combo_garbage * lock.combo_garbage as i32;
             match lock.placement_kind {
                 PlacementKind::Clear1 => {
                     acc_eval += self.clear1;
@@ -212,6 +358,29 @@ impl Evaluator for Standard {
                 PlacementKind::MiniTspin2 => {
                     acc_eval += self.mini_tspin2;
                 }
+                PlacementKind::Spin1 | PlacementKind::MiniSpin1 => {
+                    acc_eval += self.clear1;
+                }
+                PlacementKind::Spin2 | PlacementKind::MiniSpin2 => {
+                    acc_eval += self.clear2;
+                }
+                PlacementKind::Spin3 | PlacementKind::MiniSpin3 => {
+                    acc_eval += self.clear3;
+                }
+                PlacementKind::Spin4 | PlacementKind::MiniSpin4 => {
+                    acc_eval += self.clear4;
+                }
+                _ => {}
+            }
+            match lock.placement_kind {
+                PlacementKind::Spin1 | PlacementKind::Spin2 |
+                PlacementKind::Spin3 | PlacementKind::Spin4 => {
+                    acc_eval += self.spin_clear;
+                }
+                PlacementKind::MiniSpin1 | PlacementKind::MiniSpin2 |
+                PlacementKind::MiniSpin3 | PlacementKind::MiniSpin4 => {
+                    acc_eval += self.mini_spin_clear;
+                }
                 _ => {}
             }
         }
@@ -231,115 +400,15 @@ impl Evaluator for Standard {
         };
         acc_eval += self.move_time * move_time;
 
-        if board.b2b_bonus {
-            transient_eval += self.back_to_back;
-        }
-
-        let highest_point = *board.column_heights().iter().max().unwrap() as i32;
-        transient_eval += self.top_quarter * (highest_point - 15).max(0);
-        transient_eval += self.top_half * (highest_point - 10).max(0);
-
+        let height = board.height() as i32;
+        let highest_point = *board.column_heights().iter().max().unwrap();
         acc_eval += self.jeopardy
-            * (highest_point - 10).max(0)
+            * (highest_point - height / 2).max(0)
             * if self.timed_jeopardy { move_time } else { 10 }
             / 10;
 
-        let ts = if self.use_bag {
-            board.next_bag().contains(Piece::T) as usize
-                + (board.next_bag().len() <= 3) as usize
-                + (board.hold_piece == Some(Piece::T)) as usize
-        } else {
-            1 + (board.hold_piece == Some(Piece::T)) as usize
-        };
-
-        let mut board = board.clone();
-        for _ in 0..ts {
-            let cutout_location = sky_tslot_left(&board)
-                .or_else(|| sky_tslot_right(&board))
-                .or_else(|| {
-                    let tst = tst_twist_left(&board).or_else(|| tst_twist_right(&board))?;
-                    cave_tslot(&board, tst).or_else(|| {
-                        let corners = board.occupied(tst.x-1, tst.y-1) as usize
-                            + board.occupied(tst.x+1, tst.y-1) as usize
-                            + board.occupied(tst.x-1, tst.y+1) as usize
-                            + board.occupied(tst.x+1, tst.y+1) as usize;
-                        if corners >= 3 && board.on_stack(&tst) {
-                            Some(tst)
-                        } else {
-                            None
-                        }
-                    })
-                })
-                .or_else(|| fin_left(&board))
-                .or_else(|| fin_right(&board));
-            let result = match cutout_location {
-                Some(location) => cutout_tslot(board.clone(), location),
-                None => break
-            };
-            transient_eval += self.tslot[result.lines];
-            if let Some(b) = result.result {
-                board = b;
-            } else {
-                break
-            }
-        }
-
-        let highest_point = *board.column_heights().iter().max().unwrap() as i32;
-        transient_eval += self.height * highest_point;
-
-        let mut well = 0;
-        for x in 1..10 {
-            if board.column_heights()[x] <= board.column_heights()[well] {
-                well = x;
-            }
-        }
-
-        let mut depth = 0;
-        'yloop: for y in board.column_heights()[well] .. 20 {
-            for x in 0..10 {
-                if x as usize != well && !board.occupied(x, y) {
-                    break 'yloop;
-                }
-            }
-            depth += 1;
-        }
-        let depth = depth.min(self.max_well_depth);
-        transient_eval += self.well_depth * depth;
-        if depth != 0 {
-            transient_eval += self.well_column[well];
-        }
-
-        if self.row_transitions != 0 {
-            transient_eval += self.row_transitions * (0..40)
-                .map(|y| *board.get_row(y))
-                .map(|r| (r | 0b1_00000_00000) ^ (1 | r << 1))
-                .map(|d| d.count_ones() as i32)
-                .sum::<i32>();
-        }
-
-        if self.bumpiness | self.bumpiness_sq != 0 {
-            let (bump, bump_sq) = bumpiness(&board, well);
-            transient_eval += bump * self.bumpiness;
-            transient_eval += bump_sq * self.bumpiness_sq;
-        }
-
-        if self.cavity_cells | self.cavity_cells_sq |
-                self.overhang_cells | self.overhang_cells_sq != 0 {
-            let (cavity_cells, overhang_cells) = cavities_and_overhangs(&board);
-            transient_eval += self.cavity_cells * cavity_cells;
-            transient_eval += self.cavity_cells_sq * cavity_cells * cavity_cells;
-            transient_eval += self.overhang_cells * overhang_cells;
-            transient_eval += self.overhang_cells_sq * overhang_cells * overhang_cells;
-        }
-
-        if self.covered_cells | self.covered_cells_sq != 0 {
-            let (covered_cells, covered_cells_sq) = covered_cells(&board);
-            transient_eval += self.covered_cells * covered_cells;
-            transient_eval += self.covered_cells_sq * covered_cells_sq;
-        }
-
         (Value {
-            value: transient_eval,
+            value: self.analyze(board).total(),
             spike: 0
         }, Reward {
             value: acc_eval,
@@ -348,6 +417,22 @@ impl Evaluator for Standard {
     }
 }
 
+/// The number of garbage lines that enter the board after a move that sends `sent` lines.
+/// 
+/// Sent lines cancel the oldest attacks first. Delayed attacks don't enter the board yet, and
+/// neither do the attacks behind them.
+fn landing_garbage(incoming: &[IncomingGarbage], mut sent: u32) -> u32 {
+    let mut landing = 0;
+    for attack in incoming {
+        if attack.delay != 0 {
+            break
+        }
+        landing += attack.lines.saturating_sub(sent);
+        sent = sent.saturating_sub(attack.lines);
+    }
+    landing
+}
+
 /// Evaluates the bumpiness of the playfield.
 /// 
 /// The first returned value is the total amount of height change outside of an apparent well. The
@@ -358,7 +443,7 @@ fn bumpiness(board: &Board, well: usize) -> (i32, i32) {
     let mut bumpiness_sq = -1;
 
     let mut prev = if well == 0 { 1 } else { 0 };
-    for i in 1..10 {
+    for i in 1..board.width() {
         if i == well {
             continue
         }
@@ -380,7 +465,7 @@ fn cavities_and_overhangs(board: &Board) -> (i32, i32) {
     let mut overhangs = 0;
 
     for y in 0..*board.column_heights().iter().max().unwrap() {
-        for x in 0..10 {
+        for x in 0..board.width() {
             if board.occupied(x as i32, y) || y >= board.column_heights()[x] {
                 continue
             }
@@ -392,7 +477,7 @@ fn cavities_and_overhangs(board: &Board) -> (i32, i32) {
                 }
             }
 
-            if x < 8 {
+            if x + 2 < board.width() {
                 if board.column_heights()[x+1] <= y-1 && board.column_heights()[x+2] <= y {
                     overhangs += 1;
                     continue
@@ -414,7 +499,7 @@ fn covered_cells(board: &Board) -> (i32, i32) {
     let mut covered = 0;
     let mut covered_sq = 0;
 
-    for x in 0..10 {
+    for x in 0..board.width() {
         for y in (0..board.column_heights()[x] - 2).rev() {
             if !board.occupied(x as i32, y) {
                 let cells = 6.min(board.column_heights()[x] - y - 1);
@@ -631,7 +716,7 @@ struct Cutout {
 
 fn cutout_tslot(mut board: Board, mut piece: FallingPiece) -> Cutout {
     piece.tspin = TspinStatus::Full;
-    let result = board.lock_piece(piece);
+    let result = board.lock_piece(piece, &AttackTable::default());
 
     match result.placement_kind {
         PlacementKind::Tspin => Cutout {
@@ -650,6 +735,39 @@ fn cutout_tslot(mut board: Board, mut piece: FallingPiece) -> Cutout {
     }
 }
 
+/// The weighted terms of the static evaluation of a board, computed by `Standard::analyze`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub struct Analysis {
+    pub back_to_back: i32,
+    pub bumpiness: i32,
+    pub bumpiness_sq: i32,
+    pub row_transitions: i32,
+    pub height: i32,
+    pub top_half: i32,
+    pub top_quarter: i32,
+    pub cavity_cells: i32,
+    pub cavity_cells_sq: i32,
+    pub overhang_cells: i32,
+    pub overhang_cells_sq: i32,
+    pub covered_cells: i32,
+    pub covered_cells_sq: i32,
+    pub tslot: i32,
+    pub well_depth: i32,
+    pub well_column: i32
+}
+
+impl Analysis {
+    /// The static evaluation of the board.
+    pub fn total(&self) -> i32 {
+        self.back_to_back + self.bumpiness + self.bumpiness_sq + self.row_transitions +
+            self.height + self.top_half + self.top_quarter +
+            self.cavity_cells + self.cavity_cells_sq +
+            self.overhang_cells + self.overhang_cells_sq +
+            self.covered_cells + self.covered_cells_sq +
+            self.tslot + self.well_depth + self.well_column
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct Reward {
     value: i32,
@@ -723,4 +841,8 @@ impl Evaluation<Reward> for Value {
         self.value = self.value.max(new_result.value);
         self.spike = self.spike.max(new_result.spike);
     }
+
+    fn score(&self) -> i64 {
+        self.value as i64
+    }
 }
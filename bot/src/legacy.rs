@@ -0,0 +1,79 @@
+//! Serialized layouts of `Info` from older versions, for reading old replays.
+
+use serde::Deserialize;
+use libtetris::{ FallingPiece, LockResult, PlacementKind };
+use crate::modes::{ normal, pcloop };
+use crate::Info;
+
+/// `Info` as it was serialized before candidate lines were reported.
+#[derive(Deserialize)]
+pub enum InfoV0 {
+    Normal(NormalInfoV0),
+    Book,
+    PcLoop(PcLoopInfoV0)
+}
+
+#[derive(Deserialize)]
+pub struct NormalInfoV0 {
+    nodes: u32,
+    depth: u32,
+    original_rank: u32,
+    plan: Vec<(FallingPiece, LockResultV0)>
+}
+
+#[derive(Deserialize)]
+pub struct PcLoopInfoV0 {
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    depth: u32,
+    plan: Vec<(FallingPiece, LockResultV0)>
+}
+
+#[derive(Deserialize)]
+struct LockResultV0 {
+    placement_kind: PlacementKind,
+    locked_out: bool,
+    b2b: bool,
+    perfect_clear: bool,
+    combo: Option<u32>,
+    garbage_sent: u32,
+    cleared_lines: Vec<i32>
+}
+
+impl From<InfoV0> for Info {
+    fn from(v: InfoV0) -> Info {
+        match v {
+            InfoV0::Normal(info) => Info::Normal(normal::Info {
+                nodes: info.nodes,
+                depth: info.depth,
+                original_rank: info.original_rank,
+                memory: 0,
+                plan: convert_plan(info.plan),
+                candidates: vec![]
+            }),
+            InfoV0::Book => Info::Book,
+            #[cfg(not(target_arch = "wasm32"))]
+            InfoV0::PcLoop(info) => Info::PcLoop(pcloop::Info {
+                depth: info.depth,
+                plan: convert_plan(info.plan)
+            }),
+            #[cfg(target_arch = "wasm32")]
+            InfoV0::PcLoop(info) => Info::PcLoop(pcloop::Info {
+                plan: convert_plan(info.plan)
+            })
+        }
+    }
+}
+
+fn convert_plan(plan: Vec<(FallingPiece, LockResultV0)>) -> Vec<(FallingPiece, LockResult)> {
+    plan.into_iter().map(|(piece, lock)| (piece, LockResult {
+        placement_kind: lock.placement_kind,
+        locked_out: lock.locked_out,
+        b2b: lock.b2b,
+        perfect_clear: lock.perfect_clear,
+        combo: lock.combo,
+        garbage_sent: lock.garbage_sent,
+        // The combo bonus wasn't tracked separately yet.
+        combo_garbage: 0,
+        cleared_lines: lock.cleared_lines.into_iter().collect()
+    })).collect()
+}
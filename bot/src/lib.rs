@@ -1,10 +1,13 @@
 use serde::{ Serialize, Deserialize };
+use enum_map::EnumMap;
 pub use opening_book::Book;
 
 #[macro_use]
 extern crate rental;
 
 pub mod evaluation;
+pub mod legacy;
+pub mod selection;
 mod modes;
 mod dag;
 
@@ -19,32 +22,126 @@ mod web;
 pub use web::Interface;
 
 use libtetris::*;
-pub use crate::modes::normal::{ BotState, ThinkResult, Thinker };
+pub use crate::modes::normal::{ BotState, ThinkResult, Thinker, Candidate };
 pub use crate::modes::pcloop::PcPriority;
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+/// The version of the bot, as recorded in replay files.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Options {
     pub mode: MovementMode,
     pub spawn_rule: SpawnRule,
+    pub rotation_system: RotationSystem,
+    pub attack_table: AttackTable,
     pub use_hold: bool,
+    /// Whether the bot may use 180 degree rotations
+    pub use_180: bool,
     pub speculate: bool,
+    /// How much the bot plans for the worst possible unknown piece instead of the average one, in
+    /// percent. 0 plans for the expected outcome and 100 for the worst case.
+    pub risk_aversion: u32,
+    /// How the search decides which moves to look further into.
+    pub selection: selection::AnySelectionPolicy,
+    /// The number of best candidate moves to report in `Info` along with their principal
+    /// variations
+    pub multi_pv: u32,
     pub pcloop: Option<modes::pcloop::PcPriority>,
     pub min_nodes: u32,
     pub max_nodes: u32,
+    /// The memory the search may use, in megabytes. When it needs more, the least visited parts
+    /// of the search are discarded.
+    pub max_memory_mb: u32,
+    /// Minimum time in milliseconds to think about a move before providing it.
+    pub min_think_ms: u32,
+    /// Time in milliseconds after which the bot stops thinking about the current move.
+    pub max_think_ms: u32,
+    /// Time in milliseconds after a move is requested by which the bot must provide it, even if
+    /// the minimum amount of thinking hasn't been done yet.
+    pub move_deadline_ms: u32,
     pub threads: u32
 }
 
+/// What the bot knows about its opponent.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Opponent {
+    /// The opponent's playfield, bottom row first.
+    pub field: Vec<Vec<bool>>,
+    /// The opponent's next queue.
+    pub queue: Vec<Piece>,
+    /// The attacks waiting to enter the opponent's board, oldest first.
+    pub garbage: Vec<IncomingGarbage>,
+    /// The height of the visible area of the opponent's board.
+    pub height: usize,
+    /// The most garbage rows that enter the opponent's board after a single placement.
+    pub max_garbage_add: u32,
+    /// How long an attack waits before it can enter the opponent's board.
+    pub garbage_delay: u32
+}
+
+impl Opponent {
+    pub fn from_board<R: Row>(
+        board: &Board<R>, garbage: &[IncomingGarbage], max_garbage_add: u32, garbage_delay: u32
+    ) -> Self {
+        Opponent {
+            field: board.get_field(),
+            queue: board.next_queue().collect(),
+            garbage: garbage.to_vec(),
+            height: board.height(),
+            max_garbage_add,
+            garbage_delay
+        }
+    }
+
+    /// Whether sending `sent` lines should top out the opponent when they place their next piece,
+    /// unless they clear lines to cancel the attack.
+    ///
+    /// The lines cancel the attacks in `incoming`, which are waiting to enter our own board,
+    /// before the rest is sent.
+    pub fn killed_by(&self, sent: u32, incoming: &[IncomingGarbage]) -> bool {
+        let cancelled = incoming.iter().map(|g| g.lines).sum();
+        let sent = sent.saturating_sub(cancelled);
+        if sent == 0 {
+            return false
+        }
+        let mut landing = self.garbage.iter()
+            .take_while(|g| g.delay == 0)
+            .map(|g| g.lines)
+            .sum::<u32>();
+        // A delayed attack holds back the attacks behind it, including ours.
+        if self.garbage_delay == 0 && self.garbage.iter().all(|g| g.delay == 0) {
+            landing += sent;
+        }
+        let room = self.height.saturating_sub(self.stack_height()) as u32;
+        landing.min(self.max_garbage_add) >= room
+    }
+
+    /// The height of the opponent's tallest column.
+    pub fn stack_height(&self) -> usize {
+        self.field.iter().rposition(|row| row.iter().any(|&c| c)).map_or(0, |y| y + 1)
+    }
+
+    /// The total number of lines waiting to enter the opponent's board.
+    pub fn pending_garbage(&self) -> u32 {
+        self.garbage.iter().map(|g| g.lines).sum()
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 enum BotMsg {
     Reset {
-        #[serde(with = "BigArray")]
-        field: [[bool; 10]; 40],
+        field: Vec<Vec<bool>>,
         b2b: bool,
+        b2b_chain: u32,
         combo: u32
     },
     NewPiece(Piece),
-    SuggestMove(u32),
+    SuggestMove(Vec<IncomingGarbage>),
+    UpdateOpponent(Opponent),
+    SetPieceDistribution(Option<EnumMap<Piece, f64>>),
+    ExportSearch,
+    ImportSearch(Vec<u8>),
     PlayMove(FallingPiece),
     ForceAnalysisLine(Vec<FallingPiece>)
 }
@@ -64,6 +161,20 @@ impl Info {
             Info::Book => &[]
         }
     }
+
+    /// Encodes the plan as a fumen string with a page for each placement, starting from `board`.
+    #[cfg(feature = "fumen")]
+    pub fn plan_fumen(&self, board: &Board) -> String {
+        libtetris::placements_to_fumen(board, self.plan().iter().map(|&(p, _)| p)).encode()
+    }
+
+    /// The best candidate moves the bot considered, best first.
+    pub fn candidates(&self) -> &[Candidate] {
+        match self {
+            Info::Normal(info) => &info.candidates,
+            _ => &[]
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -72,20 +183,113 @@ pub enum BotPollState {
     Dead
 }
 
+/// Why a bot couldn't be launched.
+#[derive(Debug)]
+pub enum LaunchError {
+    /// `Options::threads` was 0.
+    NoThreads,
+    /// The board is wider than the evaluator has weights for.
+    UnsupportedWidth {
+        evaluator: String,
+        width: usize
+    }
+}
+
+impl std::fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LaunchError::NoThreads => write!(f, "Invalid number of threads: 0"),
+            LaunchError::UnsupportedWidth { evaluator, width } => write!(
+                f, "Unsupported board width for {}: {}", evaluator, width
+            )
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+fn check_launch(
+    board: &Board, options: &Options, evaluator: &impl evaluation::Evaluator
+) -> Result<(), LaunchError> {
+    if options.threads == 0 {
+        return Err(LaunchError::NoThreads)
+    }
+    if board.width() > evaluator.max_width() {
+        return Err(LaunchError::UnsupportedWidth {
+            evaluator: evaluator.name(), width: board.width()
+        })
+    }
+    Ok(())
+}
+
 impl Default for Options {
     fn default() -> Self {
         Options {
             mode: MovementMode::ZeroG,
             spawn_rule: SpawnRule::Row19Or20,
+            rotation_system: RotationSystem::Srs,
+            attack_table: AttackTable::default(),
             use_hold: true,
+            use_180: false,
             speculate: true,
+            risk_aversion: 0,
+            selection: Default::default(),
+            multi_pv: 1,
             pcloop: None,
             min_nodes: 0,
             max_nodes: 4_000_000_000,
+            max_memory_mb: u32::MAX,
+            min_think_ms: 0,
+            max_think_ms: u32::MAX,
+            move_deadline_ms: u32::MAX,
             threads: 1
         }
     }
 }
 
-use serde_big_array::big_array;
-big_array!( BigArray; 40, );
\ No newline at end of file
+#[cfg(all(test, feature = "fumen"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_fumen_round_trip() {
+        let mut board = Board::new();
+        board.add_garbage(2);
+        board.add_garbage(2);
+        board.hold_piece = Some(Piece::O);
+        board.add_next_piece(Piece::L);
+        board.add_next_piece(Piece::T);
+
+        // The O comes out of hold, then the L fills the garbage holes and clears both rows.
+        let moves = [
+            FallingPiece {
+                kind: PieceState(Piece::O, RotationState::North), x: 4, y: 2,
+                tspin: TspinStatus::None
+            },
+            FallingPiece {
+                kind: PieceState(Piece::L, RotationState::West), x: 2, y: 1,
+                tspin: TspinStatus::None
+            },
+        ];
+        let mut expected = board.clone();
+        let mut fields = vec![];
+        let mut plan = vec![];
+        for &mv in &moves {
+            fields.push(expected.get_field());
+            plan.push((mv, expected.lock_piece(mv, &AttackTable::default())));
+        }
+        assert_eq!(plan[1].1.cleared_lines.len(), 2);
+        let info = Info::PcLoop(modes::pcloop::Info { depth: 2, plan });
+
+        let fumen = fumen::Fumen::decode(&info.plan_fumen(&board)).unwrap();
+        assert_eq!(fumen.pages.len(), moves.len());
+        for ((page, mv), field) in fumen.pages.iter().zip(&moves).zip(&fields) {
+            assert_eq!(&Board::<u16>::from_fumen_page(page).get_field(), field);
+            assert!(FallingPiece::from(page.piece.unwrap()).same_location(mv));
+        }
+
+        let empty = fumen::Fumen::decode(&Info::Book.plan_fumen(&board)).unwrap();
+        assert_eq!(empty.pages.len(), 1);
+        assert_eq!(Board::<u16>::from_fumen_page(&empty.pages[0]).get_field(), board.get_field());
+    }
+}
@@ -1,9 +1,11 @@
 use libtetris::*;
 use opening_book::Book;
 use crate::evaluation::Evaluator;
-use crate::{ Options, Info, Move, BotMsg };
+use crate::{ Options, Info, Move, BotMsg, Opponent };
 use serde::{ Serialize, Deserialize };
 use arrayvec::ArrayVec;
+use enum_map::EnumMap;
+use std::time::{ Duration, Instant };
 
 pub mod normal;
 #[cfg(not(target_arch = "wasm32"))]
@@ -30,31 +32,58 @@ pub(crate) struct ModeSwitchedBot<'a, E: Evaluator> {
     mode: Mode<E>,
     options: Options,
     board: Board,
-    do_move: Option<u32>,
+    do_move: Option<(Vec<IncomingGarbage>, Instant)>,
+    think_start: Instant,
+    opponent: Option<Opponent>,
+    distribution: Option<EnumMap<Piece, f64>>,
     book: Option<&'a Book>
 }
 
 impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
+    /// Creates a bot that continues the search of an existing `BotState`.
+    pub fn with_state(
+        bot: normal::BotState<E>, options: Options, book: Option<&'a Book>
+    ) -> Self {
+        ModeSwitchedBot {
+            board: bot.board().clone(),
+            mode: Mode::Normal(bot),
+            options,
+            do_move: None,
+            think_start: Instant::now(),
+            opponent: None,
+            distribution: None,
+            book
+        }
+    }
+
     pub fn new(board: Board, options: Options, book: Option<&'a Book>) -> Self {
         #[cfg(target_arch = "wasm32")]
-        let mode = Mode::Normal(normal::BotState::new(board.clone(), options));
+        let mode = Mode::Normal(normal::BotState::new(board.clone(), options.clone()));
         #[cfg(not(target_arch = "wasm32"))]
         let mode = if options.pcloop.is_some() &&
                 board.get_row(0).is_empty() &&
                 can_pc_loop(&board, options.use_hold) {
-            Mode::PcLoop(pcloop::PcLooper::new(
-                board.clone(), options.use_hold, options.mode, options.pcloop.unwrap()
-            ))
+            Mode::PcLoop(pcloop::PcLooper::new(board.clone(), &options))
         } else {
-            Mode::Normal(normal::BotState::new(board.clone(), options))
+            Mode::Normal(normal::BotState::new(board.clone(), options.clone()))
         };
         ModeSwitchedBot {
             mode, options, board,
             do_move: None,
+            think_start: Instant::now(),
+            opponent: None,
+            distribution: None,
             book
         }
     }
 
+    /// Starts a normal search from the current board.
+    fn normal_bot(&self) -> normal::BotState<E> {
+        let mut bot = normal::BotState::new(self.board.clone(), self.options.clone());
+        bot.set_piece_distribution(self.distribution);
+        bot
+    }
+
     pub fn task_complete(&mut self, result: TaskResult<E::Value, E::Reward>) {
         match &mut self.mode {
             Mode::Normal(bot) => match result {
@@ -70,15 +99,15 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
 
     pub fn message(&mut self, msg: BotMsg) {
         match msg {
-            BotMsg::Reset { field, b2b, combo } => {
-                self.board.set_field(field);
+            BotMsg::Reset { field, b2b, b2b_chain, combo } => {
+                self.think_start = Instant::now();
+                self.board.set_field(&field);
                 self.board.b2b_bonus = b2b;
+                self.board.b2b_chain = b2b_chain;
                 self.board.combo = combo;
                 match &mut self.mode {
-                    Mode::Normal(bot) => bot.reset(field, b2b, combo),
-                    Mode::PcLoop(_) => self.mode = Mode::Normal(
-                        normal::BotState::new(self.board.clone(), self.options)
-                    )
+                    Mode::Normal(bot) => bot.reset(field, b2b, b2b_chain, combo),
+                    Mode::PcLoop(_) => self.mode = Mode::Normal(self.normal_bot())
                 }
             }
             BotMsg::NewPiece(piece) => {
@@ -90,10 +119,7 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                                 &self.board, self.options.use_hold
                             ) {
                                 self.mode = Mode::PcLoop(pcloop::PcLooper::new(
-                                    self.board.clone(),
-                                    self.options.use_hold,
-                                    self.options.mode,
-                                    self.options.pcloop.unwrap()
+                                    self.board.clone(), &self.options
                                 ));
                             } else {
                                 bot.add_next_piece(piece);
@@ -106,15 +132,23 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                     Mode::PcLoop(bot) => bot.add_next_piece(piece)
                 }
             }
-            BotMsg::SuggestMove(incoming) => self.do_move = Some(incoming),
+            BotMsg::SuggestMove(incoming) => self.do_move = Some((incoming, Instant::now())),
+            BotMsg::UpdateOpponent(opponent) => self.opponent = Some(opponent),
+            BotMsg::SetPieceDistribution(distribution) => {
+                self.distribution = distribution;
+                if let Mode::Normal(bot) = &mut self.mode {
+                    bot.set_piece_distribution(distribution);
+                }
+            }
             BotMsg::PlayMove(mv) => {
+                self.think_start = Instant::now();
                 let next = self.board.advance_queue().unwrap();
                 if mv.kind.0 != next {
                     if self.board.hold(next).is_none() {
                         self.board.advance_queue();
                     }
                 }
-                self.board.lock_piece(mv);
+                self.board.lock_piece(mv, &self.options.attack_table);
                 match &mut self.mode {
                     Mode::Normal(bot) => {
                         #[cfg(not(target_arch = "wasm32"))] {
@@ -122,10 +156,7 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                                 &self.board, self.options.use_hold
                             ) {
                                 self.mode = Mode::PcLoop(pcloop::PcLooper::new(
-                                    self.board.clone(),
-                                    self.options.use_hold,
-                                    self.options.mode,
-                                    self.options.pcloop.unwrap()
+                                    self.board.clone(), &self.options
                                 ));
                                 return;
                             }
@@ -134,8 +165,7 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                     }
                     Mode::PcLoop(bot) => {
                         if !bot.play_move(mv) {
-                            let bot = normal::BotState::new(self.board.clone(), self.options);
-                            self.mode = Mode::Normal(bot);
+                            self.mode = Mode::Normal(self.normal_bot());
                         }
                     }
                 }
@@ -144,19 +174,70 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                 Mode::Normal(bot) => bot.force_analysis_line(path),
                 _ => {}
             }
+            BotMsg::ImportSearch(data) => {
+                self.import_search(&data);
+            }
+            // The reply has to go back through the channels of whatever is driving the bot, so
+            // it is up to the driver to call `export_search`.
+            BotMsg::ExportSearch => {}
         }
     }
 
+    /// Serializes the search tree so that it can be continued later.
+    /// 
+    /// Returns `None` if the bot isn't using the normal search, e.g. because it's PC looping.
+    pub fn export_search(&self) -> Option<Vec<u8>> {
+        match &self.mode {
+            Mode::Normal(bot) => Some(bot.export_search()),
+            Mode::PcLoop(_) => None
+        }
+    }
+
+    /// Replaces the search with one saved by `export_search`, switching back to the normal search
+    /// if necessary.
+    /// 
+    /// Returns `false` and leaves the bot as it was if the data is not a valid saved search.
+    pub fn import_search(&mut self, data: &[u8]) -> bool {
+        let imported = match &mut self.mode {
+            Mode::Normal(bot) => bot.import_search(data),
+            Mode::PcLoop(_) => {
+                let bot = normal::BotState::from_search(data, self.options.clone());
+                bot.map(|mut bot| {
+                    bot.set_piece_distribution(self.distribution);
+                    self.mode = Mode::Normal(bot);
+                }).is_some()
+            }
+        };
+        if let (true, Mode::Normal(bot)) = (imported, &self.mode) {
+            self.board = bot.board().clone();
+            self.think_start = Instant::now();
+        }
+        imported
+    }
+
     pub fn think(&mut self, eval: &E, send_move: impl FnOnce((Move, Info))) -> Vec<Task> {
         match &mut self.mode {
             Mode::Normal(bot) => {
-                if let Some(incoming) = self.do_move {
-                    if let Some(result) = bot.suggest_move(eval, self.book, incoming) {
+                if let Some((incoming, requested)) = &self.do_move {
+                    let opponent = self.opponent.as_ref();
+                    let result = if requested.elapsed() >= millis(self.options.move_deadline_ms) {
+                        bot.force_move(eval, self.book, incoming, opponent)
+                    } else if self.think_start.elapsed() >= millis(self.options.min_think_ms) {
+                        bot.suggest_move(eval, self.book, incoming, opponent)
+                    } else {
+                        None
+                    };
+                    if let Some(result) = result {
                         send_move(result);
                         self.do_move = None;
                     }
                 }
 
+                if self.think_start.elapsed() >= millis(self.options.max_think_ms) &&
+                        bot.min_thinking_reached() {
+                    return vec![]
+                }
+
                 let mut thinks = vec![];
                 for _ in 0..10 {
                     if bot.outstanding_thinks >= self.options.threads {
@@ -181,7 +262,7 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                         }
                         Err(false) => {}
                         Err(true) => {
-                            let mut bot = normal::BotState::new(self.board.clone(), self.options);
+                            let mut bot = self.normal_bot();
                             let mut thinks = vec![];
                             if let Ok(thinker) = bot.think() {
                                 thinks.push(Task::NormalThink(thinker));
@@ -197,6 +278,19 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
         }
     }
 
+    /// How long until a time limit passes that may let the bot provide the requested move.
+    /// 
+    /// Returns `None` if no move is requested or all the time limits have already passed.
+    pub fn wake_after(&self) -> Option<Duration> {
+        let (_, requested) = self.do_move.as_ref()?;
+        let min_think = millis(self.options.min_think_ms).checked_sub(self.think_start.elapsed());
+        let deadline = millis(self.options.move_deadline_ms).checked_sub(requested.elapsed());
+        match (min_think, deadline) {
+            (Some(min_think), Some(deadline)) => Some(min_think.min(deadline)),
+            (min_think, deadline) => min_think.or(deadline)
+        }
+    }
+
     pub fn is_dead(&self) -> bool {
         if let Mode::Normal(bot) = &self.mode {
             bot.is_dead()
@@ -215,8 +309,13 @@ impl Task {
     }
 }
 
+fn millis(ms: u32) -> Duration {
+    Duration::from_millis(ms as u64)
+}
+
 fn can_pc_loop(board: &Board, hold_enabled: bool) -> bool {
-    if board.get_row(0) != <u16 as Row>::EMPTY {
+    // The PC solver only knows about 10 wide boards.
+    if board.width() != 10 || board.get_row(0) != <u16 as Row>::EMPTY {
         return false;
     }
     let pieces = board.next_queue().count();
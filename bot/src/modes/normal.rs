@@ -4,13 +4,14 @@ use libtetris::*;
 use opening_book::Book;
 // use crate::tree::{ ChildData, TreeState, NodeId };
 use crate::dag::{ DagState, NodeId, ChildData };
-use crate::Options;
-use crate::evaluation::Evaluator;
+use crate::{ Options, Opponent };
+use crate::evaluation::{ Evaluator, Evaluation };
 
 pub struct BotState<E: Evaluator> {
     tree: DagState<E::Value, E::Reward>,
     options: Options,
     forced_analysis_lines: Vec<Vec<FallingPiece>>,
+    distribution: Option<EnumMap<Piece, f64>>,
     pub outstanding_thinks: u32
 }
 
@@ -19,38 +20,94 @@ pub struct Thinker {
     node: NodeId,
     board: Board,
     options: Options,
+    /// The chance of each piece being the unknown piece after the queue of `board`.
+    distribution: EnumMap<Piece, f64>
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum ThinkResult<V, R> {
     Known(NodeId, Vec<ChildData<V, R>>),
-    Speculated(NodeId, EnumMap<Piece, Option<Vec<ChildData<V, R>>>>),
+    /// The children for each possible next piece, along with the chance of each piece.
+    Speculated(NodeId, EnumMap<Piece, f64>, EnumMap<Piece, Option<Vec<ChildData<V, R>>>>),
     Unmark(NodeId)
 }
 
 impl<E: Evaluator> BotState<E> {
     pub fn new(board: Board, options: Options) -> Self {
         BotState {
-            tree: DagState::new(board, options.use_hold),
+            tree: DagState::new(
+                board, options.use_hold, options.attack_table.clone(), options.risk_aversion
+            ),
             options,
             forced_analysis_lines: vec![],
+            distribution: None,
             outstanding_thinks: 0
         }
     }
 
+    /// Creates a bot that continues a search saved by `BotState::export_search`.
+    /// 
+    /// Returns `None` if the data is not a valid saved search.
+    pub fn from_search(data: &[u8], options: Options) -> Option<Self> {
+        Some(BotState {
+            tree: DagState::load(data, options.risk_aversion)?,
+            options,
+            forced_analysis_lines: vec![],
+            distribution: None,
+            outstanding_thinks: 0
+        })
+    }
+
+    /// Serializes the search tree so that it can be continued later.
+    pub fn export_search(&self) -> Vec<u8> {
+        self.tree.save()
+    }
+
+    /// Replaces the search tree with one saved by `BotState::export_search`.
+    /// 
+    /// Returns `false` and leaves the current search intact if the data is not a valid saved search.
+    pub fn import_search(&mut self, data: &[u8]) -> bool {
+        let imported = self.tree.restore(data);
+        if imported {
+            self.forced_analysis_lines.clear();
+        }
+        imported
+    }
+
+    pub fn board(&self) -> &Board {
+        self.tree.board()
+    }
+
+    /// Overrides the chance of each piece being the first unknown piece, which otherwise comes from
+    /// the randomizer of the board. Later pieces still come from the randomizer. `None` removes the
+    /// override.
+    pub fn set_piece_distribution(&mut self, distribution: Option<EnumMap<Piece, f64>>) {
+        self.distribution = distribution;
+    }
+
     /// Prepare a thinking cycle.
     /// 
     /// Returns `Err(true)` if a thinking cycle can be preformed, but it couldn't find 
     pub fn think(&mut self) -> Result<Thinker, bool> {
+        if !self.prune_to_memory_budget() {
+            return Err(false)
+        }
         if (!self.min_thinking_reached() || self.tree.nodes() < self.options.max_nodes)
                 && !self.tree.is_dead() {
             if let Some((node, board)) = self.tree.find_and_mark_leaf(
-                &mut self.forced_analysis_lines
+                &mut self.forced_analysis_lines, &self.options.selection
             ) {
                 self.outstanding_thinks += 1;
+                // The override only describes the first unknown piece. Further down, the board's
+                // randomizer has seen the speculated pieces before it.
+                let distribution = match self.distribution {
+                    Some(distribution) if self.tree.is_first_unknown(&node) => distribution,
+                    _ => board.randomizer.distribution()
+                };
                 return Ok(Thinker {
                     node, board,
-                    options: self.options,
+                    options: self.options.clone(),
+                    distribution
                 });
             } else {
                 return Err(true)
@@ -64,7 +121,8 @@ impl<E: Evaluator> BotState<E> {
         self.outstanding_thinks -= 1;
         match result {
             ThinkResult::Known(node, children) => self.tree.update_known(node, children),
-            ThinkResult::Speculated(node, children) => self.tree.update_speculated(node, children),
+            ThinkResult::Speculated(node, chances, children) =>
+                self.tree.update_speculated(node, chances, children),
             ThinkResult::Unmark(node) => self.tree.unmark(node)
         }
     }
@@ -78,9 +136,9 @@ impl<E: Evaluator> BotState<E> {
         self.tree.add_next_piece(piece);
     }
 
-    pub fn reset(&mut self, field: [[bool; 10]; 40], b2b: bool, combo: u32) {
+    pub fn reset(&mut self, field: Vec<Vec<bool>>, b2b: bool, b2b_chain: u32, combo: u32) {
         let plan = self.tree.get_plan();
-        if let Some(garbage_lines) = self.tree.reset(field, b2b, combo) {
+        if let Some(garbage_lines) = self.tree.reset(field, b2b, b2b_chain, combo) {
             for path in &mut self.forced_analysis_lines {
                 for mv in path {
                     mv.y += garbage_lines;
@@ -98,30 +156,67 @@ impl<E: Evaluator> BotState<E> {
         }
     }
 
+    /// Prunes the search if it uses more memory than it may, returning whether it fits now.
+    fn prune_to_memory_budget(&mut self) -> bool {
+        let budget = self.memory_budget();
+        if self.tree.memory_usage() > budget {
+            // leave some room so that we don't have to prune again right away.
+            self.tree.prune(budget / 4 * 3);
+        }
+        self.tree.memory_usage() <= budget
+    }
+
+    fn memory_budget(&self) -> u64 {
+        (self.options.max_memory_mb as u64) << 20
+    }
+
     pub fn min_thinking_reached(&self) -> bool {
-        self.tree.nodes() > self.options.min_nodes &&
+        // a search that can't grow any further is as done as it will get.
+        (self.tree.nodes() > self.options.min_nodes ||
+            self.tree.memory_usage() > self.memory_budget()) &&
             self.forced_analysis_lines.is_empty() &&
-            !self.tree.get_next_candidates().is_empty()
+            !self.tree.get_next_candidates(0).is_empty()
     }
 
     pub fn suggest_move(
         &mut self,
         eval: &E,
         book: Option<&Book>,
-        incoming: u32,
+        incoming: &[IncomingGarbage],
+        opponent: Option<&Opponent>
     ) -> Option<(Move, crate::Info)> {
         if !self.min_thinking_reached() {
             return None
         }
+        self.force_move(eval, book, incoming, opponent)
+    }
 
-        let candidates = self.tree.get_next_candidates();
+    /// Picks a move without waiting for the minimum amount of thinking to be done.
+    /// 
+    /// Returns `None` if there are no moves to pick from yet.
+    pub fn force_move(
+        &mut self,
+        eval: &E,
+        book: Option<&Book>,
+        incoming: &[IncomingGarbage],
+        opponent: Option<&Opponent>
+    ) -> Option<(Move, crate::Info)> {
+        let candidates = self.tree.get_next_candidates(self.options.multi_pv as usize);
         if candidates.is_empty() {
             return None
         }
+        let lines = candidates.iter().take(self.options.multi_pv as usize).map(|c| Candidate {
+            evaluation: c.evaluation.score(),
+            visits: c.visits,
+            plan: c.plan.clone()
+        }).collect();
         let mut book_move = None;
         if let Some(book) = book {
-            if self.tree.board().column_heights().iter().all(|&h| h <= 10) {
-                book_move = book.suggest_move(self.tree.board());
+            // Opening books are only built for the standard 10x20 board.
+            let board = self.tree.board();
+            let standard = board.width() == 10 && board.height() == 20;
+            if standard && board.column_heights().iter().all(|&h| h <= 10) {
+                book_move = book.suggest_move(board);
             }
         }
         let mut picked = None;
@@ -136,7 +231,7 @@ impl<E: Evaluator> BotState<E> {
         if picked.is_none() && book_move.is_some() {
             dbg!("book picked a move we can't do?");
         }
-        let child = picked.unwrap_or_else(|| eval.pick_move(candidates, incoming));
+        let child = picked.unwrap_or_else(|| eval.pick_move(candidates, incoming, opponent));
 
         let plan = if book_move.is_none() {
             self.tree.get_plan()
@@ -151,14 +246,18 @@ impl<E: Evaluator> BotState<E> {
                 nodes: if book_move.is_some() { 0 } else { self.tree.nodes() },
                 depth: if book_move.is_some() { 6 } else { self.tree.depth() as u32 },
                 original_rank: child.original_rank,
+                memory: self.tree.memory_usage(),
                 plan,
+                candidates: lines
             })
         };
 
         let inputs = find_moves(
             self.tree.board(),
             self.options.spawn_rule.spawn(child.mv.kind.0, self.tree.board()).unwrap(),
-            self.options.mode
+            self.options.mode,
+            &self.options.rotation_system,
+            self.options.use_180
         ).into_iter().find(|p| p.location == child.mv).unwrap().inputs;
         let mv = Move {
             hold: child.hold,
@@ -189,7 +288,7 @@ impl Thinker {
                     b.add_next_piece(p);
                     children[p] = Some(self.make_children(b, eval));
                 }
-                ThinkResult::Speculated(self.node, children)
+                ThinkResult::Speculated(self.node, self.distribution, children)
             } else {
                 ThinkResult::Unmark(self.node)
             }
@@ -209,7 +308,7 @@ impl Thinker {
                         b.add_next_piece(p);
                         children[p] = Some(self.make_children(b, eval));
                     }
-                    ThinkResult::Speculated(self.node, children)
+                    ThinkResult::Speculated(self.node, self.distribution, children)
                 } else {
                     ThinkResult::Unmark(self.node)
                 }
@@ -258,13 +357,20 @@ impl Thinker {
         spawned: FallingPiece,
         hold: bool
     ) {
-        for mv in find_moves(&board, spawned, self.options.mode) {
+        let moves = find_moves(
+            board, spawned, self.options.mode, &self.options.rotation_system, self.options.use_180
+        );
+        for mv in moves {
             let can_be_hd = board.above_stack(&mv.location) &&
-            board.column_heights().iter().all(|&y| y < 18);
+            board.column_heights().iter().all(|&y| y < board.height() as i32 - 2);
             let mut result = board.clone();
-            let lock = result.lock_piece(mv.location);
-            // Don't add deaths by lock out, don't add useless mini tspins
-            if !lock.locked_out && !(can_be_hd && lock.placement_kind == PlacementKind::MiniTspin) {
+            let lock = result.lock_piece(mv.location, &self.options.attack_table);
+            // Don't add deaths by lock out, don't add useless mini tspins and spins
+            let useless_spin = matches!(
+                lock.placement_kind,
+                PlacementKind::MiniTspin | PlacementKind::MiniSpin | PlacementKind::Spin
+            );
+            if !(lock.locked_out || can_be_hd && useless_spin) {
                 let move_time = mv.inputs.time + if hold { 1 } else { 0 };
                 let (evaluation, reward) = eval.evaluate(
                     &lock, &result, move_time, spawned.kind.0
@@ -285,5 +391,57 @@ pub struct Info {
     pub nodes: u32,
     pub depth: u32,
     pub original_rank: u32,
+    /// The estimated memory used by the search, in bytes.
+    pub memory: u64,
+    pub plan: Vec<(FallingPiece, LockResult)>,
+    /// The best candidate moves, best first. There are at most `Options::multi_pv` of them.
+    pub candidates: Vec<Candidate>
+}
+
+/// A move the bot considered playing.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct Candidate {
+    /// The evaluation of the move as summarized by `Evaluation::score`.
+    pub evaluation: i64,
+    /// The number of times the search has gone through the resulting position.
+    pub visits: u32,
+    /// The principal variation, starting with the move itself.
     pub plan: Vec<(FallingPiece, LockResult)>
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::Standard;
+    use super::*;
+
+    #[test]
+    fn distribution_override_only_applies_to_the_first_unknown_piece() {
+        let mut board = Board::new();
+        board.add_next_piece(Piece::T);
+        let options = Options { use_hold: false, ..Options::default() };
+        let mut bot = BotState::<Standard>::new(board.clone(), options);
+        let mut only_i = EnumMap::new();
+        only_i[Piece::I] = 1.0;
+        bot.set_piece_distribution(Some(only_i));
+
+        // The leaves that speculate about the first unknown piece still have the randomizer of
+        // the root, while the randomizer of deeper leaves has seen the speculated pieces.
+        let first_unknown = board.randomizer.distribution();
+        let eval = Standard::default();
+        let mut two_deep = 0;
+        for _ in 0..300 {
+            let thinker = bot.think().unwrap();
+            if thinker.board.get_next_piece().is_err() {
+                let randomizer = thinker.board.randomizer.distribution();
+                if randomizer == first_unknown {
+                    assert_eq!(thinker.distribution, only_i);
+                } else {
+                    assert_eq!(thinker.distribution, randomizer);
+                    two_deep += 1;
+                }
+            }
+            bot.finish_thinking(thinker.think(&eval));
+        }
+        assert!(two_deep > 0, "the search never speculated two pieces deep");
+    }
+}
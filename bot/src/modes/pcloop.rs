@@ -2,15 +2,20 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::atomic::{ AtomicBool, Ordering };
 use arrayvec::ArrayVec;
-use libtetris::{ Piece, FallingPiece, Board, LockResult, MovementMode };
+use libtetris::{
+    Piece, FallingPiece, Board, LockResult, MovementMode, RotationSystem, AttackTable
+};
 use crossbeam_channel::{ Sender, unbounded };
 use serde::{ Serialize, Deserialize };
-use crate::Move;
+use crate::{ Move, Options };
 
 pub struct PcLooper {
     current_pc: VecDeque<(Move, LockResult)>,
     abort: Arc<AtomicBool>,
     mode: MovementMode,
+    rotation_system: RotationSystem,
+    use_180: bool,
+    attack_table: AttackTable,
     next_pc_queue: VecDeque<Piece>,
     next_pc_hold: Option<Piece>,
     hold_enabled: bool,
@@ -26,15 +31,20 @@ pub struct PcSolver {
 }
 
 impl PcLooper {
-    pub fn new(board: Board, hold_enabled: bool, mode: MovementMode, priority: PcPriority) -> Self {
+    /// Panics if `options.pcloop` is `None`.
+    pub fn new(board: Board, options: &Options) -> Self {
         PcLooper {
             current_pc: VecDeque::new(),
             abort: Arc::new(AtomicBool::new(false)),
             next_pc_queue: board.next_queue().collect(),
-            next_pc_hold: if hold_enabled { board.hold_piece } else { None },
-            hold_enabled,
+            next_pc_hold: if options.use_hold { board.hold_piece } else { None },
+            hold_enabled: options.use_hold,
             solving: false,
-            mode, priority
+            mode: options.mode,
+            rotation_system: options.rotation_system.clone(),
+            use_180: options.use_180,
+            attack_table: options.attack_table.clone(),
+            priority: options.pcloop.unwrap()
         }
     }
 
@@ -74,7 +84,9 @@ impl PcLooper {
                 let placements = libtetris::find_moves(
                     &b,
                     libtetris::SpawnRule::Row19Or20.spawn(placement.kind.0, &b).unwrap(),
-                    self.mode
+                    self.mode,
+                    &self.rotation_system,
+                    self.use_180
                 );
 
                 let mut mv = None;
@@ -104,7 +116,7 @@ impl PcLooper {
                         mv.hold = true;
                     }
     
-                    solution.push((mv, b.lock_piece(placement)));
+                    solution.push((mv, b.lock_piece(placement, &self.attack_table)));
                 } else {
                     return;
                 }
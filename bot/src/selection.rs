@@ -0,0 +1,195 @@
+//! Policies for picking which child to search next when the search descends through the DAG.
+//!
+//! Every policy sees the children of a single node, sorted best to worst by their evaluation at
+//! the time they were created. The visit counts include searches that are still in progress, so
+//! deterministic policies spread concurrent searches out on their own.
+
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use crate::evaluation::Evaluation;
+
+/// A child of the node the search is at.
+pub struct Branch<E> {
+    /// The evaluation of the child, or `None` if it leads to death.
+    pub evaluation: Option<E>,
+    /// The number of times the search has gone through the child.
+    pub visits: u32
+}
+
+pub trait SelectionPolicy<E: Evaluation<R>, R> {
+    /// Picks the index of the branch to search next, or `None` if there is nothing to search.
+    ///
+    /// The branches are sorted best to worst by their original evaluation. Branches leading to
+    /// death must never be picked.
+    fn choose(&self, branches: &[Branch<E>]) -> Option<usize>;
+}
+
+/// Any of the built-in selection policies. This is what `Options` stores.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AnySelectionPolicy {
+    Heuristic(Heuristic),
+    Uct(Uct),
+    Puct(Puct),
+    Thompson(Thompson)
+}
+
+/// Picks a branch at random, weighted by `Evaluation::weight`. This is what Cold Clear has
+/// always done.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Heuristic;
+
+/// Upper confidence bounds applied to trees. Picks the branch with the highest evaluation plus a
+/// bonus that grows for branches that are visited less than their siblings.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Uct {
+    /// How much the bonus counts compared to the evaluation, which is scaled to lie between 0
+    /// and 1 among the siblings.
+    pub exploration: f64
+}
+
+/// Like `Uct`, but the bonus is also proportional to a prior taken from the `Heuristic` weights,
+/// as in AlphaZero.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Puct {
+    pub exploration: f64
+}
+
+/// Samples a value for each branch from a normal distribution around its evaluation, which gets
+/// narrower the more the branch is visited, and picks the branch with the highest sample.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thompson {
+    /// The standard deviation of the distribution of an unvisited branch, with the evaluation
+    /// scaled to lie between 0 and 1 among the siblings.
+    pub exploration: f64
+}
+
+impl Default for AnySelectionPolicy {
+    fn default() -> Self {
+        AnySelectionPolicy::Heuristic(Heuristic)
+    }
+}
+
+impl Default for Uct {
+    fn default() -> Self {
+        Uct { exploration: std::f64::consts::SQRT_2 }
+    }
+}
+
+impl Default for Puct {
+    fn default() -> Self {
+        Puct { exploration: 2.0 }
+    }
+}
+
+impl Default for Thompson {
+    fn default() -> Self {
+        Thompson { exploration: 0.5 }
+    }
+}
+
+impl<E: Evaluation<R>, R> SelectionPolicy<E, R> for AnySelectionPolicy {
+    fn choose(&self, branches: &[Branch<E>]) -> Option<usize> {
+        match self {
+            AnySelectionPolicy::Heuristic(p) => p.choose(branches),
+            AnySelectionPolicy::Uct(p) => p.choose(branches),
+            AnySelectionPolicy::Puct(p) => p.choose(branches),
+            AnySelectionPolicy::Thompson(p) => p.choose(branches)
+        }
+    }
+}
+
+impl<E: Evaluation<R>, R> SelectionPolicy<E, R> for Heuristic {
+    fn choose(&self, branches: &[Branch<E>]) -> Option<usize> {
+        let weights = heuristic_weights(branches)?;
+        let sampler = rand::distributions::WeightedIndex::new(weights).ok()?;
+        Some(thread_rng().sample(sampler))
+    }
+}
+
+impl<E: Evaluation<R>, R> SelectionPolicy<E, R> for Uct {
+    fn choose(&self, branches: &[Branch<E>]) -> Option<usize> {
+        let values = scaled_values(branches)?;
+        let log_total = (total_visits(branches) + 1.0).ln();
+        best(&values, |i, value| {
+            let visits = branches[i].visits as f64 + 1.0;
+            value + self.exploration * (log_total / visits).sqrt()
+        })
+    }
+}
+
+impl<E: Evaluation<R>, R> SelectionPolicy<E, R> for Puct {
+    fn choose(&self, branches: &[Branch<E>]) -> Option<usize> {
+        let values = scaled_values(branches)?;
+        let weights = heuristic_weights(branches)?;
+        let weight_sum = weights.iter().sum::<i64>().max(1) as f64;
+        let sqrt_total = (total_visits(branches) + 1.0).sqrt();
+        best(&values, |i, value| {
+            let prior = weights[i] as f64 / weight_sum;
+            let visits = branches[i].visits as f64 + 1.0;
+            value + self.exploration * prior * sqrt_total / visits
+        })
+    }
+}
+
+impl<E: Evaluation<R>, R> SelectionPolicy<E, R> for Thompson {
+    fn choose(&self, branches: &[Branch<E>]) -> Option<usize> {
+        let values = scaled_values(branches)?;
+        let mut rng = thread_rng();
+        best(&values, |i, value| {
+            let visits = branches[i].visits as f64 + 1.0;
+            value + self.exploration * standard_normal(&mut rng) / visits.sqrt()
+        })
+    }
+}
+
+/// The `Evaluation::weight` of each branch relative to the worst one, or 0 for deaths.
+fn heuristic_weights<E: Evaluation<R>, R>(branches: &[Branch<E>]) -> Option<Vec<i64>> {
+    // Since branches are sorted best-to-worst, the minimum evaluation will be the last one that
+    // doesn't lead to death.
+    let min_eval = branches.iter().rev().find_map(|b| b.evaluation.as_ref())?;
+    Some(branches.iter().enumerate().map(
+        |(i, b)| b.evaluation.clone().map_or(0, |e| e.weight(min_eval, i))
+    ).collect())
+}
+
+/// The `Evaluation::score` of each branch scaled to lie between 0 and 1, or `None` for deaths.
+fn scaled_values<E: Evaluation<R>, R>(branches: &[Branch<E>]) -> Option<Vec<Option<f64>>> {
+    let scores = branches.iter().filter_map(|b| b.evaluation.as_ref()).map(E::score);
+    let (min, max) = scores.fold(None, |range, s| match range {
+        None => Some((s, s)),
+        Some((min, max)) => Some((s.min(min), s.max(max)))
+    })?;
+    Some(branches.iter().map(|b| b.evaluation.as_ref().map(|e| match max - min {
+        0 => 0.5,
+        range => (e.score() - min) as f64 / range as f64
+    })).collect())
+}
+
+fn total_visits<E>(branches: &[Branch<E>]) -> f64 {
+    branches.iter().map(|b| b.visits as f64).sum()
+}
+
+/// The index of the branch with the highest priority, skipping deaths. Ties go to the branch
+/// that was originally evaluated best.
+fn best(values: &[Option<f64>], mut priority: impl FnMut(usize, f64) -> f64) -> Option<usize> {
+    let mut best: Option<(usize, f64)> = None;
+    for (i, &value) in values.iter().enumerate() {
+        if let Some(value) = value {
+            let p = priority(i, value);
+            if best.is_none_or(|(_, b)| p > b) {
+                best = Some((i, p));
+            }
+        }
+    }
+    best.map(|(i, _)| i)
+}
+
+/// Box-Muller transform.
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
@@ -2,10 +2,11 @@ use webutil::prelude::*;
 use webutil::worker::{ Worker, WorkerSender };
 use webutil::channel::{ channel, Receiver };
 use serde::{ Serialize, de::DeserializeOwned };
+use enum_map::EnumMap;
 use libtetris::*;
 use crate::evaluation::Evaluator;
 use crate::moves::Move;
-use crate::{ Options, Info, BotMsg, BotPollState };
+use crate::{ Options, Info, BotMsg, BotPollState, LaunchError };
 use crate::modes::{ ModeSwitchedBot, Task, TaskResult };
 use futures_util::{ select, pin_mut };
 use futures_util::FutureExt;
@@ -20,26 +21,27 @@ pub struct Interface(Option<Worker<BotMsg, Option<(Move, Info)>>>);
 
 impl Interface {
     /// Launches a bot worker with the specified starting board and options.
+    /// 
+    /// Fails if the options ask for no threads or if the board is wider than the evaluator
+    /// supports.
     pub async fn launch<E>(
         worker_uri: &str,
         board: Board,
         options: Options,
         evaluator: E
-    ) -> Self
+    ) -> Result<Self, LaunchError>
     where
         E: Evaluator + Clone + Serialize + DeserializeOwned + 'static,
         E::Value: Serialize + DeserializeOwned,
         E::Reward: Serialize + DeserializeOwned
     {
-        if options.threads == 0 {
-            panic!("Invalid number of threads: 0");
-        }
+        crate::check_launch(&board, &options, &evaluator)?;
 
         let worker = Worker::new(
             worker_uri, bot_thread, &(board, options, evaluator, worker_uri.to_owned())
         ).await.unwrap();
 
-        Interface(Some(worker))
+        Ok(Interface(Some(worker)))
     }
 
     /// Request the bot to provide a move as soon as possible.
@@ -102,16 +104,16 @@ impl Interface {
 
     /// Adds a new piece to the end of the queue.
     /// 
-    /// If speculation is enabled, the piece *must* be in the bag. For example, if in the current
-    /// bag you've provided the sequence IJOZT, then the next time you call this function you can
-    /// only provide either an L or an S piece.
+    /// If speculation is enabled, the piece *must* be one the board's randomizer can produce. For
+    /// example, with a 7-bag randomizer, if in the current bag you've provided the sequence IJOZT,
+    /// then the next time you call this function you can only provide either an L or an S piece.
     pub fn add_next_piece(&self, piece: Piece) {
         if let Some(worker) = &self.0 {
             worker.send(&BotMsg::NewPiece(piece)).unwrap();
         }
     }
 
-    /// Resets the playfield, back-to-back status, and combo count.
+    /// Resets the playfield, back-to-back status, back-to-back chain length, and combo count.
     /// 
     /// This should only be used when garbage is received or when your client could not place the
     /// piece in the correct position for some reason (e.g. 15 move rule), since this forces the
@@ -119,15 +121,28 @@ impl Interface {
     /// 
     /// Note: combo is not the same as the displayed combo in guideline games. Here, it is the
     /// number of consecutive line clears achieved. So, generally speaking, if "x Combo" appears
-    /// on the screen, you need to use x+1 here.
-    pub fn reset(&self, field: [[bool; 10]; 40], b2b_active: bool, combo: u32) {
+    /// on the screen, you need to use x+1 here. Likewise, `b2b_chain` is the number of
+    /// consecutive back-to-back clears, which only matters for attack tables that reward long
+    /// chains.
+    pub fn reset(&self, field: Vec<Vec<bool>>, b2b_active: bool, b2b_chain: u32, combo: u32) {
         if let Some(worker) = &self.0 {
             worker.send(&BotMsg::Reset {
-                field, b2b: b2b_active, combo
+                field, b2b: b2b_active, b2b_chain, combo
             }).unwrap();
         }
     }
 
+    /// Overrides the chance of each piece being the next unknown piece, for when you know more
+    /// about the randomizer than the board does. The chances should add up to 1. The unknown
+    /// pieces after that one still come from the board's randomizer.
+    /// 
+    /// Passing `None` goes back to the chances given by the board's randomizer.
+    pub fn set_piece_distribution(&self, distribution: Option<EnumMap<Piece, f64>>) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::SetPieceDistribution(distribution)).unwrap();
+        }
+    }
+
     /// Specifies a line that Cold Clear should analyze before making any moves.
     pub fn force_analysis_line(&self, path: Vec<FallingPiece>) {
         if let Some(worker) = &self.0 {
@@ -14,13 +14,16 @@ typedef enum CCTspinStatus {
     CC_NONE_TSPIN_STATUS,
     CC_MINI,
     CC_FULL,
+    CC_IMMOBILE,
 } CCTspinStatus;
 
 typedef enum CCMovement {
     CC_LEFT, CC_RIGHT,
     CC_CW, CC_CCW,
     /* Soft drop all the way down */
-    CC_DROP
+    CC_DROP,
+    /* 180 degree rotation */
+    CC_FLIP
 } CCMovement;
 
 typedef enum CCMovementMode {
@@ -34,6 +37,14 @@ typedef enum CCSpawnRule {
     CC_ROW_21_AND_FALL,
 } CCSpawnRule;
 
+typedef enum CCRotationSystem {
+    CC_SRS,
+    /* SRS with TETR.IO's symmetric I piece kicks */
+    CC_SRS_PLUS,
+    CC_ARS,
+    CC_NO_KICKS
+} CCRotationSystem;
+
 typedef enum CCBotPollStatus {
     CC_MOVE_PROVIDED,
     CC_WAITING,
@@ -46,6 +57,20 @@ typedef enum CCPcPriority {
     CC_PC_ATTACK
 } CCPcPriority;
 
+typedef enum CCSelectionPolicy {
+    CC_HEURISTIC,
+    CC_UCT,
+    CC_PUCT,
+    CC_THOMPSON
+} CCSelectionPolicy;
+
+typedef enum CCAttackTable {
+    /* Puyo Puyo Tetris guideline attack */
+    CC_ATTACK_DEFAULT,
+    /* TETR.IO attack, with back-to-back levels and multiplicative combos */
+    CC_ATTACK_TETRIO
+} CCAttackTable;
+
 typedef struct CCPlanPlacement {
     CCPiece piece;
     CCTspinStatus tspin;
@@ -73,8 +98,20 @@ typedef struct CCMove {
     uint32_t nodes;
     uint32_t depth;
     uint32_t original_rank;
+    /* Estimated memory used by the search, in bytes */
+    uint64_t memory;
 } CCMove;
 
+typedef struct CCCandidate {
+    /* Evaluation of the move, higher is better */
+    int64_t evaluation;
+    /* Number of times the search has gone through the position after the move */
+    uint32_t visits;
+    /* Principal variation, starting with the move itself */
+    uint32_t plan_length;
+    CCPlanPlacement plan[32];
+} CCCandidate;
+
 typedef struct CCOptions {
     CCMovementMode mode;
     CCSpawnRule spawn_rule;
@@ -84,6 +121,29 @@ typedef struct CCOptions {
     uint32_t threads;
     bool use_hold;
     bool speculate;
+    CCRotationSystem rotation_system;
+    bool use_180;
+    /* Minimum time in milliseconds to think about a move before providing it */
+    uint32_t min_think_ms;
+    /* Time in milliseconds after which the bot stops thinking about the current move */
+    uint32_t max_think_ms;
+    /* Time in milliseconds after a move is requested by which the bot must provide it */
+    uint32_t move_deadline_ms;
+    /* Number of best candidate moves to report along with their principal variations */
+    uint32_t multi_pv;
+    /* How much to plan for the worst possible unknown piece instead of the average one, in
+     * percent */
+    uint32_t risk_aversion;
+    /* How the search decides which moves to look further into */
+    CCSelectionPolicy selection;
+    /* How much the UCT, PUCT and Thompson policies favour rarely searched moves. Ignored by the
+     * heuristic policy. 0 or less uses the default of the policy */
+    double exploration;
+    /* Memory the search may use, in megabytes. The least visited parts of the search are
+     * discarded when it needs more */
+    uint32_t max_memory_mb;
+    /* How much garbage each kind of line clear sends */
+    CCAttackTable attack_table;
 } CCOptions;
 
 typedef struct CCWeights {
@@ -124,8 +184,37 @@ typedef struct CCWeights {
     bool use_bag;
     bool timed_jeopardy;
     bool stack_pc_damage;
+
+    /* Rewards for line clears with spins of pieces other than T */
+    int32_t spin_clear;
+    int32_t mini_spin_clear;
+
+    /* Play a safe move that should top out the opponent instead of the best move */
+    bool prefer_kills;
 } CCWeights;
 
+/* The weighted terms of the static evaluation of a board */
+typedef struct CCAnalysis {
+    int32_t back_to_back;
+    int32_t bumpiness;
+    int32_t bumpiness_sq;
+    int32_t row_transitions;
+    int32_t height;
+    int32_t top_half;
+    int32_t top_quarter;
+    int32_t cavity_cells;
+    int32_t cavity_cells_sq;
+    int32_t overhang_cells;
+    int32_t overhang_cells_sq;
+    int32_t covered_cells;
+    int32_t covered_cells_sq;
+    int32_t tslot;
+    int32_t well_depth;
+    int32_t well_column;
+    /* Sum of all of the terms */
+    int32_t total;
+} CCAnalysis;
+
 /* Launches a bot thread with a blank board, all seven pieces in the bag, and the specified queue
  * using the specified options and weights.
  *
@@ -136,6 +225,8 @@ typedef struct CCWeights {
  * `book` may be `NULL` to indicate that no book should be used.
  * The book may be destroyed at any time after this function returns.
  * 
+ * Returns `NULL` if `options->threads` is 0.
+ * 
  * Lifetime: The returned pointer is valid until it is passed to `cc_destroy_async`.
  */
 CCAsyncBot *cc_launch_async(CCOptions *options, CCWeights *weights, CCBook *book, CCPiece *queue,
@@ -159,12 +250,22 @@ CCAsyncBot *cc_launch_async(CCOptions *options, CCWeights *weights, CCBook *book
  * `book` may be `NULL` to indicate that no book should be used.
  * The book may be destroyed at any time after this function returns.
  * 
+ * Returns `NULL` if `options->threads` is 0.
+ * 
  * Lifetime: The returned pointer is valid until it is passed to `cc_destroy_async`.
  */
 CCAsyncBot *cc_launch_with_board_async(CCOptions *options, CCWeights *weights, CCBook *book,
     bool *field, uint32_t bag_remain, CCPiece *hold, bool b2b, uint32_t combo, CCPiece *queue,
     uint32_t count);
 
+/* Like `cc_launch_with_board_async`, but also takes the back-to-back chain length: the number of
+ * consecutive back-to-back clears, which only matters for attack tables that reward long chains.
+ * `cc_launch_with_board_async` starts with a chain length of 0.
+ */
+CCAsyncBot *cc_launch_with_board_b2b_async(CCOptions *options, CCWeights *weights, CCBook *book,
+    bool *field, uint32_t bag_remain, CCPiece *hold, bool b2b, uint32_t b2b_chain, uint32_t combo,
+    CCPiece *queue, uint32_t count);
+
 /* Terminates the bot thread and frees the memory associated with the bot.
  */
 void cc_destroy_async(CCAsyncBot *bot);
@@ -184,6 +285,13 @@ void cc_destroy_async(CCAsyncBot *bot);
  */
 void cc_reset_async(CCAsyncBot *bot, bool *field, bool b2b, uint32_t combo);
 
+/* Like `cc_reset_async`, but also resets the back-to-back chain length: the number of
+ * consecutive back-to-back clears, which only matters for attack tables that reward long chains.
+ * `cc_reset_async` resets it to 0.
+ */
+void cc_reset_b2b_async(CCAsyncBot *bot, bool *field, bool b2b, uint32_t b2b_chain,
+    uint32_t combo);
+
 /* Adds a new piece to the end of the queue.
  * 
  * If speculation is enabled, the piece must be in the bag. For example, if you start a new
@@ -192,6 +300,15 @@ void cc_reset_async(CCAsyncBot *bot, bool *field, bool b2b, uint32_t combo);
  */
 void cc_add_next_piece_async(CCAsyncBot *bot, CCPiece piece);
 
+/* Overrides the chance of each piece being the next unknown piece, for when you know more about
+ * the randomizer than CC does. The unknown pieces after that one still come from the 7-bag
+ * randomizer.
+ * 
+ * The chances parameter is a pointer to an array of 7 chances indexed by the CCPiece enum, which
+ * should add up to 1. Pass `NULL` to go back to the chances given by the 7-bag randomizer.
+ */
+void cc_set_piece_distribution_async(CCAsyncBot *bot, double *chances);
+
 /* Request the bot to provide a move as soon as possible.
  * 
  * In most cases, "as soon as possible" is a very short amount of time, and is only longer if
@@ -211,7 +328,7 @@ void cc_add_next_piece_async(CCAsyncBot *bot, CCPiece piece);
  * being placed correctly and the move will become available by calling `cc_poll_next_move`.
  * 
  * The incoming parameter specifies the number of lines of garbage the bot is expected to receive
- * after placing the next piece.
+ * after placing the next piece. It is treated as a single attack with no delay.
  */
 void cc_request_next_move(CCAsyncBot *bot, uint32_t incoming);
 
@@ -228,6 +345,10 @@ void cc_request_next_move(CCAsyncBot *bot, uint32_t incoming);
  * will be returned in the array pointed to by `plan`. `plan_length` should point to the length
  * of the array, and the number of plan placements provided will be returned through this pointer.
  * 
+ * If `candidates` and `candidates_length` are not `NULL` and this function provides a move, the
+ * best candidate moves (up to `multi_pv` of them) will be returned in the array pointed to by
+ * `candidates`, best first. `candidates_length` works the same way as `plan_length`.
+ * 
  * If the move has been provided, this function will return `CC_MOVE_PROVIDED`.
  * If the bot has not produced a result, this function will return `CC_WAITING`.
  * If the bot has found that it cannot survive, this function will return `CC_BOT_DEAD`
@@ -236,7 +357,9 @@ CCBotPollStatus cc_poll_next_move(
     CCAsyncBot *bot,
     CCMove *move,
     CCPlanPlacement* plan,
-    uint32_t *plan_length
+    uint32_t *plan_length,
+    CCCandidate *candidates,
+    uint32_t *candidates_length
 );
 
 /* This function is the same as `cc_poll_next_move` except when `cc_poll_next_move` would return
@@ -249,9 +372,27 @@ CCBotPollStatus cc_block_next_move(
     CCAsyncBot *bot,
     CCMove *move,
     CCPlanPlacement* plan,
-    uint32_t *plan_length
+    uint32_t *plan_length,
+    CCCandidate *candidates,
+    uint32_t *candidates_length
 );
 
+/* Saves the bot's state and search tree to the specified file so that a long analysis can be
+ * continued later using `cc_import_search_from_file`.
+ *
+ * Returns false if the bot is dead, hasn't started searching yet, is perfect clear looping, or if
+ * the file couldn't be written.
+ */
+bool cc_export_search_to_file(CCAsyncBot *bot, const char *path);
+
+/* Replaces the bot's state and search tree with one saved by `cc_export_search_to_file`. The
+ * playfield, queue and hold piece are replaced as well.
+ *
+ * Returns false if the bot is dead, if the file couldn't be read, or if it doesn't contain a search
+ * saved by a bot using the same weights. The bot keeps its current search in that case.
+ */
+bool cc_import_search_from_file(CCAsyncBot *bot, const char *path);
+
 /* Returns the default options in the options parameter */
 void cc_default_options(CCOptions *options);
 
@@ -261,6 +402,16 @@ void cc_default_weights(CCWeights *weights);
 /* Returns the fast game config weights in the weights parameter */
 void cc_fast_weights(CCWeights *weights);
 
+/* Computes the static evaluation of a board using the specified weights without searching, broken
+ * down by feature. Each term is already multiplied by its weight. This is useful for explaining
+ * why a board is good or bad.
+ * 
+ * The field, bag_remain and hold parameters work the same way as in `cc_launch_with_board_async`.
+ * The result is returned in the analysis parameter.
+ */
+void cc_analyze_board(CCWeights *weights, bool *field, uint32_t bag_remain, CCPiece *hold,
+    bool b2b, CCAnalysis *analysis);
+
 /*
  * Loads the specified opening book from the specified file path.
  * If an error occurs, `NULL` is returned instead.
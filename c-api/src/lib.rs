@@ -3,10 +3,13 @@ use std::mem::MaybeUninit;
 use std::os::raw::c_char;
 use std::sync::Arc;
 use enumset::EnumSet;
+use enum_map::EnumMap;
 use libtetris::{
-    Piece, TspinStatus, PieceMovement, SpawnRule, FallingPiece, LockResult, Board, MovementMode
+    Piece, TspinStatus, PieceMovement, SpawnRule, FallingPiece, LockResult, Board, MovementMode,
+    RotationSystem, IncomingGarbage, AttackTable
 };
 use cold_clear::PcPriority;
+use cold_clear::selection::{ AnySelectionPolicy, Heuristic, Uct, Puct, Thompson };
 
 type CCAsyncBot = cold_clear::Interface;
 
@@ -78,7 +81,8 @@ cenum! {
     enum CCTspinStatus => TspinStatus {
         CC_NONE => TspinStatus::None,
         CC_MINI => TspinStatus::Mini,
-        CC_FULL => TspinStatus::Full
+        CC_FULL => TspinStatus::Full,
+        CC_IMMOBILE => TspinStatus::Immobile
     }
 
     enum CCMovement => PieceMovement {
@@ -86,7 +90,8 @@ cenum! {
         CC_RIGHT => PieceMovement::Right,
         CC_CW => PieceMovement::Cw,
         CC_CCW => PieceMovement::Ccw,
-        CC_DROP => PieceMovement::SonicDrop
+        CC_DROP => PieceMovement::SonicDrop,
+        CC_FLIP => PieceMovement::Flip
     }
 
     enum CCSpawnRule => SpawnRule {
@@ -100,6 +105,13 @@ cenum! {
         CC_HARD_DROP_ONLY => MovementMode::HardDropOnly
     }
 
+    enum CCRotationSystem => RotationSystem {
+        CC_SRS => RotationSystem::Srs,
+        CC_SRS_PLUS => RotationSystem::SrsPlus,
+        CC_ARS => RotationSystem::Ars,
+        CC_NO_KICKS => RotationSystem::NoKicks
+    }
+
     enum CCPcPriority => Option<PcPriority> {
         CC_PC_OFF => None,
         CC_PC_FASTEST => Some(PcPriority::Fastest),
@@ -116,6 +128,24 @@ enum CCBotPollStatus {
     CC_BOT_DEAD
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+#[allow(non_camel_case_types)]
+enum CCSelectionPolicy {
+    CC_HEURISTIC,
+    CC_UCT,
+    CC_PUCT,
+    CC_THOMPSON
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+#[allow(non_camel_case_types)]
+enum CCAttackTable {
+    CC_ATTACK_DEFAULT,
+    CC_ATTACK_TETRIO
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 struct CCMove {
@@ -127,6 +157,7 @@ struct CCMove {
     nodes: u32,
     depth: u32,
     original_rank: u32,
+    memory: u64,
 }
 
 #[repr(C)]
@@ -139,6 +170,15 @@ struct CCPlanPlacement {
     cleared_lines: [i32; 4],
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CCCandidate {
+    evaluation: i64,
+    visits: u32,
+    plan_length: u32,
+    plan: [CCPlanPlacement; 32],
+}
+
 #[repr(C)]
 struct CCOptions {
     mode: CCMovementMode,
@@ -149,6 +189,38 @@ struct CCOptions {
     threads: u32,
     use_hold: bool,
     speculate: bool,
+    rotation_system: CCRotationSystem,
+    use_180: bool,
+    min_think_ms: u32,
+    max_think_ms: u32,
+    move_deadline_ms: u32,
+    multi_pv: u32,
+    risk_aversion: u32,
+    selection: CCSelectionPolicy,
+    exploration: f64,
+    max_memory_mb: u32,
+    attack_table: CCAttackTable,
+}
+
+#[repr(C)]
+struct CCAnalysis {
+    back_to_back: i32,
+    bumpiness: i32,
+    bumpiness_sq: i32,
+    row_transitions: i32,
+    height: i32,
+    top_half: i32,
+    top_quarter: i32,
+    cavity_cells: i32,
+    cavity_cells_sq: i32,
+    overhang_cells: i32,
+    overhang_cells_sq: i32,
+    covered_cells: i32,
+    covered_cells_sq: i32,
+    tslot: i32,
+    well_depth: i32,
+    well_column: i32,
+    total: i32,
 }
 
 #[repr(C)]
@@ -190,6 +262,11 @@ struct CCWeights {
     use_bag: bool,
     timed_jeopardy: bool,
     stack_pc_damage: bool,
+
+    spin_clear: i32,
+    mini_spin_clear: i32,
+
+    prefer_kills: bool,
 }
 
 fn convert_hold(hold: *mut CCPiece) -> Option<Piece> {
@@ -205,12 +282,61 @@ fn convert_from_c_options(options: &CCOptions) -> cold_clear::Options {
     cold_clear::Options {
         max_nodes: options.max_nodes,
         min_nodes: options.min_nodes,
+        max_memory_mb: options.max_memory_mb,
+        min_think_ms: options.min_think_ms,
+        max_think_ms: options.max_think_ms,
+        move_deadline_ms: options.move_deadline_ms,
         use_hold: options.use_hold,
+        use_180: options.use_180,
         speculate: options.speculate,
         pcloop: options.pcloop.into(),
         mode: options.mode.into(),
         spawn_rule: options.spawn_rule.into(),
-        threads: options.threads
+        rotation_system: options.rotation_system.into(),
+        attack_table: convert_from_c_attack_table(options.attack_table),
+        threads: options.threads,
+        multi_pv: options.multi_pv,
+        risk_aversion: options.risk_aversion,
+        selection: convert_from_c_selection(options.selection, options.exploration)
+    }
+}
+
+/// An exploration of 0 or less picks the default of the policy.
+fn convert_from_c_selection(policy: CCSelectionPolicy, exploration: f64) -> AnySelectionPolicy {
+    let default = exploration <= 0.0;
+    match policy {
+        CCSelectionPolicy::CC_HEURISTIC => AnySelectionPolicy::Heuristic(Heuristic),
+        CCSelectionPolicy::CC_UCT if default => AnySelectionPolicy::Uct(Uct::default()),
+        CCSelectionPolicy::CC_UCT => AnySelectionPolicy::Uct(Uct { exploration }),
+        CCSelectionPolicy::CC_PUCT if default => AnySelectionPolicy::Puct(Puct::default()),
+        CCSelectionPolicy::CC_PUCT => AnySelectionPolicy::Puct(Puct { exploration }),
+        CCSelectionPolicy::CC_THOMPSON if default =>
+            AnySelectionPolicy::Thompson(Thompson::default()),
+        CCSelectionPolicy::CC_THOMPSON => AnySelectionPolicy::Thompson(Thompson { exploration })
+    }
+}
+
+fn convert_selection(policy: AnySelectionPolicy) -> (CCSelectionPolicy, f64) {
+    match policy {
+        AnySelectionPolicy::Heuristic(_) => (CCSelectionPolicy::CC_HEURISTIC, 0.0),
+        AnySelectionPolicy::Uct(p) => (CCSelectionPolicy::CC_UCT, p.exploration),
+        AnySelectionPolicy::Puct(p) => (CCSelectionPolicy::CC_PUCT, p.exploration),
+        AnySelectionPolicy::Thompson(p) => (CCSelectionPolicy::CC_THOMPSON, p.exploration)
+    }
+}
+
+fn convert_from_c_attack_table(table: CCAttackTable) -> AttackTable {
+    match table {
+        CCAttackTable::CC_ATTACK_DEFAULT => AttackTable::default(),
+        CCAttackTable::CC_ATTACK_TETRIO => AttackTable::tetrio()
+    }
+}
+
+fn convert_attack_table(table: &AttackTable) -> CCAttackTable {
+    if *table == AttackTable::tetrio() {
+        CCAttackTable::CC_ATTACK_TETRIO
+    } else {
+        CCAttackTable::CC_ATTACK_DEFAULT
     }
 }
 
@@ -245,6 +371,8 @@ fn convert_from_c_weights(weights: &CCWeights) -> cold_clear::evaluation::Standa
         tspin3: weights.tspin3,
         mini_tspin1: weights.mini_tspin1,
         mini_tspin2: weights.mini_tspin2,
+        spin_clear: weights.spin_clear,
+        mini_spin_clear: weights.mini_spin_clear,
         perfect_clear: weights.perfect_clear,
         combo_garbage: weights.combo_garbage,
         move_time: weights.move_time,
@@ -253,6 +381,7 @@ fn convert_from_c_weights(weights: &CCWeights) -> cold_clear::evaluation::Standa
         use_bag: weights.use_bag,
         timed_jeopardy: weights.timed_jeopardy,
         stack_pc_damage: weights.stack_pc_damage,
+        prefer_kills: weights.prefer_kills,
         sub_name: None
     }
 }
@@ -269,12 +398,32 @@ unsafe extern "C" fn cc_launch_with_board_async(
     combo: u32,
     pieces: *const CCPiece,
     count: u32
+) -> *mut CCAsyncBot {
+    cc_launch_with_board_b2b_async(
+        options, weights, book, field, bag_remain, hold, b2b, 0, combo, pieces, count
+    )
+}
+
+#[no_mangle]
+unsafe extern "C" fn cc_launch_with_board_b2b_async(
+    options: &CCOptions,
+    weights: &CCWeights,
+    book: *const CCBook,
+    field: &[[bool; 10]; 40],
+    bag_remain: u32,
+    hold: *mut CCPiece,
+    b2b: bool,
+    b2b_chain: u32,
+    combo: u32,
+    pieces: *const CCPiece,
+    count: u32
 ) -> *mut CCAsyncBot {
     let mut board = Board::new_with_state(
-        *field,
+        field,
         EnumSet::try_from_u32(bag_remain).unwrap_or_default(),
         convert_hold(hold),
         b2b,
+        b2b_chain,
         combo
     );
     for i in 0..count as usize {
@@ -286,12 +435,15 @@ unsafe extern "C" fn cc_launch_with_board_async(
         Arc::increment_strong_count(book);
         Some(Arc::from_raw(book))
     };
-    Box::into_raw(Box::new(cold_clear::Interface::launch(
+    match cold_clear::Interface::launch(
         board,
         convert_from_c_options(options),
         convert_from_c_weights(weights),
         book
-    )))
+    ) {
+        Ok(bot) => Box::into_raw(Box::new(bot)),
+        Err(_) => std::ptr::null_mut()
+    }
 }
 
 #[no_mangle]
@@ -312,12 +464,15 @@ unsafe extern "C" fn cc_launch_async(
         Arc::increment_strong_count(book);
         Some(Arc::from_raw(book))
     };
-    Box::into_raw(Box::new(cold_clear::Interface::launch(
+    match cold_clear::Interface::launch(
         board,
         convert_from_c_options(options),
         convert_from_c_weights(weights),
         book
-    )))
+    ) {
+        Ok(bot) => Box::into_raw(Box::new(bot)),
+        Err(_) => std::ptr::null_mut()
+    }
 }
 
 #[no_mangle]
@@ -329,7 +484,14 @@ extern "C" fn cc_destroy_async(bot: *mut CCAsyncBot) {
 extern "C" fn cc_reset_async(
     bot: &mut CCAsyncBot, field: &[[bool; 10]; 40], b2b: bool, combo: u32
 ) {
-    bot.reset(*field, b2b, combo);
+    cc_reset_b2b_async(bot, field, b2b, 0, combo);
+}
+
+#[no_mangle]
+extern "C" fn cc_reset_b2b_async(
+    bot: &mut CCAsyncBot, field: &[[bool; 10]; 40], b2b: bool, b2b_chain: u32, combo: u32
+) {
+    bot.reset(field.iter().map(|row| row.to_vec()).collect(), b2b, b2b_chain, combo);
 }
 
 #[no_mangle]
@@ -337,9 +499,24 @@ extern "C" fn cc_add_next_piece_async(bot: &mut CCAsyncBot, piece: CCPiece) {
     bot.add_next_piece(piece.into());
 }
 
+#[no_mangle]
+extern "C" fn cc_set_piece_distribution_async(bot: &mut CCAsyncBot, chances: Option<&[f64; 7]>) {
+    bot.set_piece_distribution(chances.map(|chances| {
+        let mut distribution = EnumMap::default();
+        for (piece, chance) in &mut distribution {
+            *chance = chances[CCPiece::from(piece) as usize];
+        }
+        distribution
+    }));
+}
+
 #[no_mangle]
 extern "C" fn cc_request_next_move(bot: &mut CCAsyncBot, incoming: u32) {
-    bot.suggest_next_move(incoming);
+    if incoming == 0 {
+        bot.suggest_next_move(&[]);
+    } else {
+        bot.suggest_next_move(&[IncomingGarbage { lines: incoming, delay: 0 }]);
+    }
 }
 
 fn convert_plan_placement(
@@ -384,6 +561,40 @@ fn convert_plan(
     }
 }
 
+fn convert_candidates(
+    info: &cold_clear::Info,
+    candidates: *mut MaybeUninit<CCCandidate>,
+    candidates_length: *mut u32
+) {
+    if !candidates.is_null() && !candidates_length.is_null() {
+        let candidates_length = unsafe { &mut *candidates_length };
+        let candidates = unsafe {
+            std::slice::from_raw_parts_mut(candidates, *candidates_length as usize)
+        };
+        let n = info.candidates().len().min(candidates.len());
+        for (slot, candidate) in candidates.iter_mut().zip(info.candidates()) {
+            let mut plan = [CCPlanPlacement {
+                piece: CCPiece::CC_I,
+                tspin: CCTspinStatus::CC_NONE,
+                expected_x: [0; 4],
+                expected_y: [0; 4],
+                cleared_lines: [-1; 4]
+            }; 32];
+            let plan_length = candidate.plan.len().min(plan.len());
+            for (placement, p) in plan.iter_mut().zip(&candidate.plan) {
+                *placement = convert_plan_placement(p);
+            }
+            *slot = MaybeUninit::new(CCCandidate {
+                evaluation: candidate.evaluation,
+                visits: candidate.visits,
+                plan_length: plan_length as u32,
+                plan
+            });
+        }
+        *candidates_length = n as u32;
+    }
+}
+
 fn convert(m: libtetris::Move, info: cold_clear::Info) -> CCMove {
     let mut expected_x = [0; 4];
     let mut expected_y = [0; 4];
@@ -415,6 +626,11 @@ fn convert(m: libtetris::Move, info: cold_clear::Info) -> CCMove {
             cold_clear::Info::Normal(info) => info.original_rank as u32,
             cold_clear::Info::PcLoop(_) => 0,
             cold_clear::Info::Book => 0,
+        },
+        memory: match &info {
+            cold_clear::Info::Normal(info) => info.memory,
+            cold_clear::Info::PcLoop(_) => 0,
+            cold_clear::Info::Book => 0,
         }
     }
 }
@@ -424,12 +640,15 @@ extern "C" fn cc_poll_next_move(
     bot: &mut CCAsyncBot,
     mv: *mut CCMove,
     plan: *mut MaybeUninit<CCPlanPlacement>,
-    plan_length: *mut u32
+    plan_length: *mut u32,
+    candidates: *mut MaybeUninit<CCCandidate>,
+    candidates_length: *mut u32
 ) -> CCBotPollStatus {
     match bot.poll_next_move() {
         Ok((m, info)) => {
             bot.play_next_move(m.expected_location);
             convert_plan(&info, plan, plan_length);
+            convert_candidates(&info, candidates, candidates_length);
             unsafe { mv.write(convert(m, info)) };
             CCBotPollStatus::CC_MOVE_PROVIDED
         }
@@ -443,12 +662,15 @@ extern "C" fn cc_block_next_move(
     bot: &mut CCAsyncBot,
     mv: *mut CCMove,
     plan: *mut MaybeUninit<CCPlanPlacement>,
-    plan_length: *mut u32
+    plan_length: *mut u32,
+    candidates: *mut MaybeUninit<CCCandidate>,
+    candidates_length: *mut u32
 ) -> CCBotPollStatus {
     match bot.block_next_move() {
         Some((m, info)) => {
             bot.play_next_move(m.expected_location);
             convert_plan(&info, plan, plan_length);
+            convert_candidates(&info, candidates, candidates_length);
             unsafe { mv.write(convert(m, info)) };
             CCBotPollStatus::CC_MOVE_PROVIDED
         }
@@ -456,18 +678,48 @@ extern "C" fn cc_block_next_move(
     }
 }
 
+#[no_mangle]
+unsafe extern "C" fn cc_export_search_to_file(bot: &mut CCAsyncBot, path: *const c_char) -> bool {
+    let result = (|| {
+        let path = CStr::from_ptr(path).to_str().ok()?;
+        std::fs::write(path, bot.export_search()?).ok()
+    })();
+    result.is_some()
+}
+
+#[no_mangle]
+unsafe extern "C" fn cc_import_search_from_file(bot: &mut CCAsyncBot, path: *const c_char) -> bool {
+    let result = (|| {
+        let path = CStr::from_ptr(path).to_str().ok()?;
+        Some(bot.import_search(std::fs::read(path).ok()?))
+    })();
+    result.unwrap_or(false)
+}
+
 #[no_mangle]
 unsafe extern "C" fn cc_default_options(options: *mut CCOptions) {
     let o = cold_clear::Options::default();
+    let (selection, exploration) = convert_selection(o.selection);
     options.write(CCOptions {
         max_nodes: o.max_nodes,
         min_nodes: o.min_nodes,
+        max_memory_mb: o.max_memory_mb,
+        min_think_ms: o.min_think_ms,
+        max_think_ms: o.max_think_ms,
+        move_deadline_ms: o.move_deadline_ms,
         use_hold: o.use_hold,
+        use_180: o.use_180,
         speculate: o.speculate,
         pcloop: o.pcloop.into(),
         mode: o.mode.into(),
         spawn_rule: o.spawn_rule.into(),
-        threads: o.threads
+        rotation_system: o.rotation_system.into(),
+        attack_table: convert_attack_table(&o.attack_table),
+        threads: o.threads,
+        multi_pv: o.multi_pv,
+        risk_aversion: o.risk_aversion,
+        selection,
+        exploration
     });
 }
 
@@ -502,6 +754,8 @@ fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
         tspin3: w.tspin3,
         mini_tspin1: w.mini_tspin1,
         mini_tspin2: w.mini_tspin2,
+        spin_clear: w.spin_clear,
+        mini_spin_clear: w.mini_spin_clear,
         perfect_clear: w.perfect_clear,
         combo_garbage: w.combo_garbage,
         move_time: w.move_time,
@@ -509,7 +763,8 @@ fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
 
         use_bag: w.use_bag,
         timed_jeopardy: w.timed_jeopardy,
-        stack_pc_damage: w.stack_pc_damage
+        stack_pc_damage: w.stack_pc_damage,
+        prefer_kills: w.prefer_kills
     }
 }
 
@@ -523,6 +778,45 @@ unsafe extern "C" fn cc_fast_weights(weights: *mut CCWeights) {
     weights.write(convert_weights(cold_clear::evaluation::Standard::fast_config()));
 }
 
+#[no_mangle]
+unsafe extern "C" fn cc_analyze_board(
+    weights: &CCWeights,
+    field: &[[bool; 10]; 40],
+    bag_remain: u32,
+    hold: *mut CCPiece,
+    b2b: bool,
+    analysis: *mut CCAnalysis
+) {
+    let board = Board::new_with_state(
+        field,
+        EnumSet::try_from_u32(bag_remain).unwrap_or_default(),
+        convert_hold(hold),
+        b2b,
+        0,
+        0
+    );
+    let a = convert_from_c_weights(weights).analyze(&board);
+    analysis.write(CCAnalysis {
+        back_to_back: a.back_to_back,
+        bumpiness: a.bumpiness,
+        bumpiness_sq: a.bumpiness_sq,
+        row_transitions: a.row_transitions,
+        height: a.height,
+        top_half: a.top_half,
+        top_quarter: a.top_quarter,
+        cavity_cells: a.cavity_cells,
+        cavity_cells_sq: a.cavity_cells_sq,
+        overhang_cells: a.overhang_cells,
+        overhang_cells_sq: a.overhang_cells_sq,
+        covered_cells: a.covered_cells,
+        covered_cells_sq: a.covered_cells_sq,
+        tslot: a.tslot,
+        well_depth: a.well_depth,
+        well_column: a.well_column,
+        total: a.total()
+    });
+}
+
 #[no_mangle]
 unsafe extern "C" fn cc_load_book_from_file(path: *const c_char) -> *const CCBook {
     let result = (|| {
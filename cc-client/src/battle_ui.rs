@@ -3,6 +3,7 @@ use battle::{ Battle, BattleUpdate };
 use crate::player_draw::PlayerDrawState;
 use crate::res::Resources;
 
+#[derive(Clone)]
 pub struct BattleUi {
     player_1_graphics: PlayerDrawState,
     player_2_graphics: PlayerDrawState,
@@ -12,8 +13,8 @@ pub struct BattleUi {
 impl BattleUi {
     pub fn new(battle: &Battle, p1_name: String, p2_name: String) -> Self {
         BattleUi {
-            player_1_graphics: PlayerDrawState::new(battle.player_1.board.next_queue(), p1_name),
-            player_2_graphics: PlayerDrawState::new(battle.player_2.board.next_queue(), p2_name),
+            player_1_graphics: PlayerDrawState::new(battle.players[0].board.next_queue(), p1_name),
+            player_2_graphics: PlayerDrawState::new(battle.players[1].board.next_queue(), p2_name),
             time: 0
         }
     }
@@ -26,7 +27,7 @@ impl BattleUi {
         p2_info_update: Option<cold_clear::Info>
     ) {
         let mut move_sound_played_this_frame = false;
-        for event in update.player_1.events.iter().chain(update.player_2.events.iter()) {
+        for event in update.players.iter().flat_map(|p| &p.events) {
             use battle::Event::*;
             match event {
                 PieceMoved | SoftDropped | PieceRotated => if !move_sound_played_this_frame {
@@ -45,8 +46,19 @@ impl BattleUi {
             }
         }
 
-        self.player_1_graphics.update(update.player_1, p1_info_update, update.time);
-        self.player_2_graphics.update(update.player_2, p2_info_update, update.time);
+        self.apply(update, p1_info_update, p2_info_update);
+    }
+
+    /// Updates the UI state without playing any sounds, for fast-forwarding.
+    pub fn apply(
+        &mut self,
+        update: BattleUpdate,
+        p1_info_update: Option<cold_clear::Info>,
+        p2_info_update: Option<cold_clear::Info>
+    ) {
+        let mut players = update.players.into_iter();
+        self.player_1_graphics.update(players.next().unwrap(), p1_info_update, update.time);
+        self.player_2_graphics.update(players.next().unwrap(), p2_info_update, update.time);
         self.time = update.time;
     }
 
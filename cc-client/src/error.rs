@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+use gilrs::Gamepad;
+use game_util::text::Alignment;
+use game_util::winit::event::VirtualKeyCode;
+use game_util::winit::event_loop::EventLoopProxy;
+use game_util::LocalExecutor;
+use crate::res::Resources;
+
+/// Shows why the client couldn't start instead of crashing.
+pub struct ErrorScreen {
+    message: String
+}
+
+impl ErrorScreen {
+    pub fn new(message: impl Into<String>) -> Self {
+        ErrorScreen { message: message.into() }
+    }
+}
+
+impl crate::State for ErrorScreen {
+    fn update(
+        &mut self,
+        _el_proxy: &EventLoopProxy<Box<dyn crate::State>>,
+        _executor: &LocalExecutor,
+        _log: &mut crate::LogFile,
+        _res: &mut Resources,
+        _keys: &HashSet<VirtualKeyCode>,
+        _p1: Option<Gamepad>,
+        _p2: Option<Gamepad>
+    ) {}
+
+    fn render(&mut self, res: &mut Resources) {
+        res.text.draw_text(
+            &self.message,
+            20.0, 12.25,
+            Alignment::Center,
+            [0xFF; 4], 0.75, 0
+        );
+    }
+}
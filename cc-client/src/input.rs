@@ -1,5 +1,5 @@
 use libtetris::*;
-use battle::{ Event, PieceMoveExecutor };
+use battle::{ Event, Game, PieceMoveExecutor };
 use game_util::winit::event::VirtualKeyCode;
 use gilrs::{ Gamepad, Axis, Button };
 use serde::{ Serialize, Deserialize };
@@ -8,7 +8,11 @@ use std::collections::HashSet;
 pub trait InputSource {
     fn controller(&self, keys: &HashSet<VirtualKeyCode>, gamepad: Option<Gamepad>) -> Controller;
     fn update(
-        &mut self, board: &Board<ColoredRow>, events: &[Event], incoming: u32
+        &mut self,
+        board: &Board<ColoredRow>,
+        events: &[Event],
+        incoming: &[IncomingGarbage],
+        opponent: &Game
     ) -> Option<cold_clear::Info>;
 }
 
@@ -36,18 +40,30 @@ impl InputSource for BotInput {
     }
 
     fn update(
-        &mut self, board: &Board<ColoredRow>, events: &[Event], incoming: u32
+        &mut self,
+        board: &Board<ColoredRow>,
+        events: &[Event],
+        incoming: &[IncomingGarbage],
+        opponent: &Game
     ) -> Option<cold_clear::Info> {
         for event in events {
             match event {
                 Event::PieceSpawned { new_in_queue } => {
                     self.interface.add_next_piece(*new_in_queue);
                     if self.executing.is_none() {
+                        self.interface.update_opponent(cold_clear::Opponent::from_board(
+                            &opponent.board,
+                            &opponent.garbage_queue.incoming(),
+                            opponent.config().max_garbage_add,
+                            opponent.config().garbage.delay
+                        ));
                         self.interface.suggest_next_move(incoming);
                     }
                 }
                 Event::GarbageAdded(_) => {
-                    self.interface.reset(board.get_field(), board.b2b_bonus, board.combo);
+                    self.interface.reset(
+                        board.get_field(), board.b2b_bonus, board.b2b_chain, board.combo
+                    );
                 }
                 _ => {}
             }
@@ -64,7 +80,9 @@ impl InputSource for BotInput {
         if let Some((expected, ref mut executor)) = self.executing {
             if let Some(loc) = executor.update(&mut self.controller, board, events) {
                 if loc != expected {
-                    self.interface.reset(board.get_field(), board.b2b_bonus, board.combo);
+                    self.interface.reset(
+                        board.get_field(), board.b2b_bonus, board.b2b_chain, board.combo
+                    );
                 }
                 self.executing = None;
             }
@@ -85,6 +103,9 @@ struct Config<T> {
     right: T,
     rotate_left: T,
     rotate_right: T,
+    /// Unbound if missing, since older option files don't have it
+    #[serde(default)]
+    rotate_180: Option<T>,
     hard_drop: T,
     soft_drop: T,
     hold: T
@@ -97,6 +118,7 @@ impl Default for Config<VirtualKeyCode> {
             right: VirtualKeyCode::Right,
             rotate_left: VirtualKeyCode::Z,
             rotate_right: VirtualKeyCode::X,
+            rotate_180: Some(VirtualKeyCode::A),
             hard_drop: VirtualKeyCode::Space,
             soft_drop: VirtualKeyCode::Down,
             hold: VirtualKeyCode::C,
@@ -111,6 +133,7 @@ impl Default for Config<GamepadControl> {
             right: GamepadControl::Button(Button::DPadRight),
             rotate_left: GamepadControl::Button(Button::South),
             rotate_right: GamepadControl::Button(Button::East),
+            rotate_180: Some(GamepadControl::Button(Button::North)),
             hard_drop: GamepadControl::Button(Button::DPadUp),
             soft_drop: GamepadControl::Button(Button::DPadDown),
             hold: GamepadControl::Button(Button::LeftTrigger)
@@ -133,6 +156,8 @@ impl InputSource for UserInput {
             rotate_right: self.read_input(
                 keys, gamepad, self.keyboard.rotate_right, self.gamepad.rotate_right
             ),
+            rotate_180: self.keyboard.rotate_180.map_or(false, |k| keys.contains(&k)) ||
+                self.gamepad.rotate_180.map_or(false, |g| read_gamepad(gamepad, g)),
             hard_drop: self.read_input(
                 keys, gamepad, self.keyboard.hard_drop, self.gamepad.hard_drop
             ),
@@ -145,7 +170,9 @@ impl InputSource for UserInput {
         }
     }
 
-    fn update(&mut self, _: &Board<ColoredRow>, _: &[Event], _: u32) -> Option<cold_clear::Info> {
+    fn update(
+        &mut self, _: &Board<ColoredRow>, _: &[Event], _: &[IncomingGarbage], _: &Game
+    ) -> Option<cold_clear::Info> {
         None
     }
 }
@@ -156,14 +183,18 @@ impl UserInput {
         keys: &HashSet<VirtualKeyCode>, controller: Option<Gamepad>,
         keyboard: VirtualKeyCode, gamepad: GamepadControl
     ) -> bool {
-        keys.contains(&keyboard) || controller.map_or(false, |c| match gamepad {
-            GamepadControl::Button(button) => c.is_pressed(button),
-            GamepadControl::PositiveAxis(axis) => c.value(axis) > 0.5,
-            GamepadControl::NegativeAxis(axis) => c.value(axis) < -0.5,
-        })
+        keys.contains(&keyboard) || read_gamepad(controller, gamepad)
     }
 }
 
+fn read_gamepad(controller: Option<Gamepad>, gamepad: GamepadControl) -> bool {
+    controller.map_or(false, |c| match gamepad {
+        GamepadControl::Button(button) => c.is_pressed(button),
+        GamepadControl::PositiveAxis(axis) => c.value(axis) > 0.5,
+        GamepadControl::NegativeAxis(axis) => c.value(axis) < -0.5,
+    })
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 enum GamepadControl {
     Button(Button),
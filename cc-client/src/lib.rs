@@ -20,9 +20,11 @@ mod res;
 mod realtime;
 mod replay;
 mod input;
+mod error;
 
 use realtime::RealtimeGame;
 use replay::ReplayGame;
+use error::ErrorScreen;
 
 struct CCGui {
     log: LogFile,
@@ -145,6 +147,13 @@ pub fn main() {
                     Options::default()
                 }
             };
+            let options = match options.p1.game.validate().and(options.p2.game.validate()) {
+                Ok(()) => options,
+                Err(e) => {
+                    writeln!(log, "options.yaml contained invalid game rules: {}", e).ok();
+                    Options::default()
+                }
+            };
 
             let gilrs = Gilrs::new().unwrap_or_else(|e| match e {
                 gilrs::Error::NotImplemented(g) => {
@@ -161,15 +170,28 @@ pub fn main() {
             let p2_gamepad = gamepads.next().map(|(id, _)| id);
 
             async move {
+                let state: Box<dyn State> = match replay_file {
+                    Some(f) => match ReplayGame::new(&f) {
+                        Ok(game) => Box::new(game),
+                        Err(e) => {
+                            writeln!(log, "Could not view replay {}: {}", f, e).ok();
+                            Box::new(ErrorScreen::new(e))
+                        }
+                    },
+                    None => match RealtimeGame::new(options, 0, 0).await {
+                        Ok(game) => Box::new(game),
+                        Err(e) => {
+                            writeln!(log, "Could not start the game: {}", e).ok();
+                            Box::new(ErrorScreen::new(e))
+                        }
+                    }
+                };
                 CCGui {
                     log, psize,
                     res: res::Resources::load(&gl, &executor).await,
                     el_proxy,
                     executor,
-                    state: match replay_file {
-                        Some(f) => Box::new(ReplayGame::new(f)),
-                        None => Box::new(RealtimeGame::new(options, 0, 0).await)
-                    },
+                    state,
                     p1: p1_gamepad, p2: p2_gamepad, gilrs,
                     keys: HashSet::new(),
                     gl
@@ -228,7 +250,7 @@ where
 {
     pub async fn to_player(
         &self, board: libtetris::Board
-    ) -> (Box<dyn input::InputSource>, String) {
+    ) -> Result<(Box<dyn input::InputSource>, String), String> {
         use crate::input::BotInput;
         if self.is_bot {
             let mut name = format!("Cold Clear\n{}", self.bot_config.weights.name());
@@ -238,9 +260,9 @@ where
                 );
             }
             #[cfg(not(target_arch = "wasm32"))]
-            let result = (Box::new(BotInput::new(cold_clear::Interface::launch(
+            let bot = cold_clear::Interface::launch(
                 board,
-                self.bot_config.options,
+                self.bot_config.options.clone(),
                 self.bot_config.weights.clone(),
                 self.bot_config.book_path.as_ref().and_then(|path| {
                     let mut book_cache = self.bot_config.book_cache.borrow_mut();
@@ -255,19 +277,20 @@ where
                         }
                     }
                 })
-            ), self.bot_config.speed_limit)) as Box<_>, name);
+            );
 
             #[cfg(target_arch = "wasm32")]
-            let result = (Box::new(BotInput::new(cold_clear::Interface::launch(
+            let bot = cold_clear::Interface::launch(
                 "./worker.js",
                 board,
-                self.bot_config.options,
+                self.bot_config.options.clone(),
                 self.bot_config.weights.clone()
-            ).await, self.bot_config.speed_limit)) as Box<_>, name);
+            ).await;
 
-            result
+            let bot = bot.map_err(|e| e.to_string())?;
+            Ok((Box::new(BotInput::new(bot, self.bot_config.speed_limit)) as Box<_>, name))
         } else {
-            (Box::new(self.controls), "Human".to_owned())
+            Ok((Box::new(self.controls) as Box<_>, "Human".to_owned()))
         }
     }
 }
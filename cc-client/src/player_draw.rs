@@ -6,6 +6,7 @@ use battle::{ PlayerUpdate, Event };
 use arrayvec::ArrayVec;
 use crate::res::Resources;
 
+#[derive(Clone)]
 pub struct PlayerDrawState {
     board: ArrayVec<[ColoredRow; 40]>,
     state: State,
@@ -22,6 +23,7 @@ pub struct PlayerDrawState {
     info: Option<cold_clear::Info>
 }
 
+#[derive(Clone)]
 enum State {
     Falling(FallingPiece, FallingPiece),
     LineClearAnimation(ArrayVec<[i32; 4]>, i32),
@@ -50,7 +52,7 @@ impl PlayerDrawState {
     pub fn update(
         &mut self, update: PlayerUpdate, info_update: Option<cold_clear::Info>, time: u32
     ) {
-        self.garbage_queue = update.garbage_queue;
+        self.garbage_queue = update.garbage_queue.iter().map(|g| g.lines).sum();
         self.info = info_update.or(self.info.take());
         self.game_time = time;
         if let State::LineClearAnimation(_, ref mut frames) = self.state {
@@ -253,6 +255,7 @@ impl PlayerDrawState {
                     lines.push(("Freestyle", "".to_owned()));
                     lines.push(("Depth", format!("{}", info.depth)));
                     lines.push(("Nodes", format!("{}", info.nodes)));
+                    lines.push(("Memory", format!("{} MB", info.memory >> 20)));
                     lines.push(("O. Rank", format!("{}", info.original_rank)));
                 }
                 cold_clear::Info::Book => {
@@ -11,7 +11,7 @@ use crate::Options;
 use crate::res::Resources;
 use crate::battle_ui::BattleUi;
 use crate::input::InputSource;
-use crate::replay::InfoReplay;
+use replay_file::InfoReplay;
 
 pub struct RealtimeGame {
     ui: BattleUi,
@@ -37,20 +37,20 @@ impl RealtimeGame {
         options: Options,
         p1_wins: u32,
         p2_wins: u32
-    ) -> Self {
+    ) -> Result<Self, String> {
         let mut battle = Battle::new(
-            options.p1.game, options.p2.game,
-            thread_rng().gen(), thread_rng().gen(), thread_rng().gen()
-        );
+            vec![options.p1.game.clone(), options.p2.game.clone()],
+            vec![thread_rng().gen(), thread_rng().gen()],
+            thread_rng().gen()
+        ).map_err(|e| e.to_string())?;
         let (p1_input, p1_name) = options.p1.to_player(
-            battle.player_1.board.to_compressed()
-        ).await;
+            battle.players[0].board.to_compressed()
+        ).await?;
         let (p2_input, p2_name) = options.p2.to_player(
-            battle.player_2.board.to_compressed()
-        ).await;
-        battle.replay.p1_name = p1_name.clone();
-        battle.replay.p2_name = p2_name.clone();
-        RealtimeGame {
+            battle.players[1].board.to_compressed()
+        ).await?;
+        battle.replay.names = vec![p1_name.clone(), p2_name.clone()];
+        Ok(RealtimeGame {
             ui: BattleUi::new(&battle, p1_name, p2_name),
             battle,
             options: Some(options),
@@ -59,7 +59,7 @@ impl RealtimeGame {
             p1_info_updates: VecDeque::new(),
             p2_info_updates: VecDeque::new(),
             state: State::Starting(180),
-        }
+        })
     }
 }
 
@@ -77,18 +77,12 @@ impl crate::State for RealtimeGame {
         let do_update = match self.state {
             State::GameOver(0) => if let Some(options) = self.options.take() {
                 let r: Result<(), Box<dyn std::error::Error>> = (|| {
-                    let mut encoder = libflate::deflate::Encoder::new(
-                        std::fs::File::create("replay.dat")?
-                    );
-                    bincode::serialize_into(
-                        &mut encoder,
-                        &InfoReplay {
-                            replay: self.battle.replay.clone(),
-                            p1_info_updates: self.p1_info_updates.clone(),
-                            p2_info_updates: self.p2_info_updates.clone()
-                        }
-                    )?;
-                    encoder.finish().into_result()?;
+                    InfoReplay {
+                        replay: self.battle.replay.clone(),
+                        info_updates: vec![
+                            self.p1_info_updates.clone(), self.p2_info_updates.clone()
+                        ]
+                    }.save(std::fs::File::create("replay.dat")?)?;
                     Ok(())
                 })();
                 if let Err(e) = r {
@@ -99,8 +93,13 @@ impl crate::State for RealtimeGame {
                 let p2_wins = self.p2_wins;
                 let el_proxy = el_proxy.clone();
                 executor.spawn(async move {
-                    let next_state = RealtimeGame::new(options, p1_wins, p2_wins).await;
-                    el_proxy.send_event(Box::new(next_state)).ok();
+                    let next_state: Box<dyn crate::State> = match RealtimeGame::new(
+                        options, p1_wins, p2_wins
+                    ).await {
+                        Ok(game) => Box::new(game),
+                        Err(e) => Box::new(crate::error::ErrorScreen::new(e))
+                    };
+                    el_proxy.send_event(next_state).ok();
                 });
                 false
             } else {
@@ -125,22 +124,24 @@ impl crate::State for RealtimeGame {
             let p1_controller = self.p1_input.controller(keys, p1);
             let p2_controller = self.p2_input.controller(keys, p2.or(p1));
 
-            let update = self.battle.update(p1_controller, p2_controller);
+            let update = self.battle.update(&[p1_controller, p2_controller]);
 
             let p1_info_update = self.p1_input.update(
-                &self.battle.player_1.board, &update.player_1.events,
-                self.battle.player_1.garbage_queue
+                &self.battle.players[0].board, &update.players[0].events,
+                &update.players[0].garbage_queue,
+                &self.battle.players[1]
             );
             let p2_info_update = self.p2_input.update(
-                &self.battle.player_2.board, &update.player_2.events,
-                self.battle.player_2.garbage_queue
+                &self.battle.players[1].board, &update.players[1].events,
+                &update.players[1].garbage_queue,
+                &self.battle.players[0]
             );
 
             self.p1_info_updates.push_back(p1_info_update.clone());
             self.p2_info_updates.push_back(p2_info_update.clone());
 
             if let State::Playing = self.state {
-                for event in &update.player_1.events {
+                for event in &update.players[0].events {
                     use battle::Event::*;
                     match event {
                         GameOver => {
@@ -150,7 +151,7 @@ impl crate::State for RealtimeGame {
                         _ => {}
                     }
                 }
-                for event in &update.player_2.events {
+                for event in &update.players[1].events {
                     use battle::Event::*;
                     match event {
                         GameOver => {
@@ -1,9 +1,7 @@
-use battle::Replay;
-use serde::{ Serialize, Deserialize };
-use std::collections::{ HashSet, VecDeque };
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::fs::File;
-use battle::Battle;
+use battle::{ Battle, BattleUpdate, Event };
 use libtetris::Controller;
 use gilrs::Gamepad;
 use game_util::text::Alignment;
@@ -12,37 +10,217 @@ use game_util::winit::event_loop::EventLoopProxy;
 use game_util::LocalExecutor;
 use crate::battle_ui::BattleUi;
 use crate::res::Resources;
+use replay_file::InfoReplay;
+
+/// Number of ticks between battle snapshots kept for seeking.
+const KEYFRAME_INTERVAL: u32 = 600;
+
+/// Playback speeds selectable with the up and down arrow keys, in ticks per frame.
+const SPEEDS: &[f32] = &[0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 3;
 
 pub struct ReplayGame {
     ui: BattleUi,
     battle: Battle,
     file: PathBuf,
-    updates: VecDeque<(Controller, Controller)>,
-    p1_info_updates: VecDeque<Option<cold_clear::Info>>,
-    p2_info_updates: VecDeque<Option<cold_clear::Info>>,
-    start_delay: u32
+    updates: Vec<Vec<Controller>>,
+    p1_info_updates: Vec<Option<cold_clear::Info>>,
+    p2_info_updates: Vec<Option<cold_clear::Info>>,
+    keyframes: Vec<(Battle, BattleUi)>,
+    p1_placements: Vec<u32>,
+    p2_placements: Vec<u32>,
+    start_delay: u32,
+    paused: bool,
+    speed: usize,
+    progress: f32,
+    piece_input: String,
+    prev_keys: HashSet<VirtualKeyCode>
 }
 
 impl ReplayGame {
-    pub fn new(file: impl Into<PathBuf>) -> Self {
+    /// Loads a replay file, failing if it can't be read or isn't a two player replay.
+    pub fn new(file: impl Into<PathBuf>) -> Result<Self, String> {
         let file = file.into();
-        let InfoReplay {
-            replay, p1_info_updates, p2_info_updates
-        } = bincode::deserialize_from(
-            libflate::deflate::Decoder::new(File::open(&file).unwrap())
-        ).unwrap();
-        let battle = Battle::new(
-            replay.p1_config, replay.p2_config,
-            replay.p1_seed, replay.p2_seed,
-            replay.garbage_seed
-        );
-        ReplayGame {
-            ui: BattleUi::new(&battle, replay.p1_name, replay.p2_name),
-            battle,
-            updates: replay.updates,
-            p1_info_updates, p2_info_updates,
-            start_delay: 500,
-            file
+        let f = File::open(&file).map_err(|e| e.to_string())?;
+        let (_, replay) = InfoReplay::load(f).map_err(|e| e.to_string())?;
+        ReplayGame::from_replay(file, replay, 500)
+    }
+
+    /// Plays through the whole replay once, recording keyframes and the time of every placement.
+    fn from_replay(file: PathBuf, replay: InfoReplay, start_delay: u32) -> Result<Self, String> {
+        let InfoReplay { replay, info_updates } = replay;
+        if replay.names.len() != 2 {
+            return Err(format!(
+                "Only two player replays can be viewed, this one has {} players",
+                replay.names.len()
+            ));
+        }
+        let mut battle = Battle::new(replay.configs, replay.seeds, replay.garbage_seed)
+            .map_err(|e| e.to_string())?;
+        let mut names = replay.names.into_iter();
+        let mut ui = BattleUi::new(&battle, names.next().unwrap(), names.next().unwrap());
+        let updates: Vec<_> = replay.updates.into();
+        let mut info_updates = info_updates.into_iter().map(Vec::from);
+        let p1_info_updates = info_updates.next().unwrap_or_default();
+        let p2_info_updates = info_updates.next().unwrap_or_default();
+
+        let mut keyframes = vec![(battle.clone(), ui.clone())];
+        let mut p1_placements = vec![];
+        let mut p2_placements = vec![];
+        for (i, controllers) in updates.iter().enumerate() {
+            let update = battle.update(controllers);
+            if placed(&update.players[0].events) {
+                p1_placements.push(update.time);
+            }
+            if placed(&update.players[1].events) {
+                p2_placements.push(update.time);
+            }
+            ui.apply(
+                update,
+                p1_info_updates.get(i).cloned().flatten(),
+                p2_info_updates.get(i).cloned().flatten()
+            );
+            if battle.time % KEYFRAME_INTERVAL == 0 {
+                // The battle records its own copy of the inputs, which we don't need here.
+                battle.replay.updates.clear();
+                keyframes.push((battle.clone(), ui.clone()));
+            }
+        }
+
+        let (battle, ui) = keyframes[0].clone();
+        Ok(ReplayGame {
+            ui, battle, file,
+            updates, p1_info_updates, p2_info_updates,
+            keyframes, p1_placements, p2_placements,
+            start_delay,
+            paused: false,
+            speed: NORMAL_SPEED,
+            progress: 0.0,
+            piece_input: String::new(),
+            prev_keys: HashSet::new()
+        })
+    }
+
+    fn reload(&mut self) {
+        let replay;
+        loop {
+            match std::fs::File::open(&self.file) {
+                Ok(f) => {
+                    match InfoReplay::load(f) {
+                        Ok((_, r)) => {
+                            replay = r;
+                            break
+                        }
+                        Err(_) => {}
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+        match ReplayGame::from_replay(self.file.clone(), replay, 180) {
+            Ok(game) => {
+                let speed = self.speed;
+                let prev_keys = std::mem::take(&mut self.prev_keys);
+                *self = game;
+                self.speed = speed;
+                self.prev_keys = prev_keys;
+            }
+            // The file was replaced by a replay we can't show; stay on the end of this one.
+            Err(_) => self.paused = true
+        }
+    }
+
+    fn step(&mut self) -> (BattleUpdate, Option<cold_clear::Info>, Option<cold_clear::Info>) {
+        let i = self.battle.time as usize;
+        (
+            self.battle.update(&self.updates[i]),
+            self.p1_info_updates.get(i).cloned().flatten(),
+            self.p2_info_updates.get(i).cloned().flatten()
+        )
+    }
+
+    /// Jumps to the given tick by restoring the closest earlier keyframe and fast-forwarding.
+    fn seek(&mut self, time: u32) {
+        let time = time.min(self.updates.len() as u32);
+        let (battle, ui) = &self.keyframes[(time / KEYFRAME_INTERVAL) as usize];
+        self.battle = battle.clone();
+        self.ui = ui.clone();
+        while self.battle.time < time {
+            let (update, p1_info, p2_info) = self.step();
+            self.ui.apply(update, p1_info, p2_info);
+        }
+        self.start_delay = 0;
+        self.progress = 0.0;
+    }
+
+    /// The tick at which either player placed their `piece`th piece.
+    fn piece_time(&self, piece: usize) -> u32 {
+        if piece == 0 {
+            return 0;
+        }
+        [&self.p1_placements, &self.p2_placements].iter()
+            .filter_map(|p| p.get(piece - 1).copied())
+            .min()
+            .unwrap_or(self.updates.len() as u32)
+    }
+
+    fn current_piece(&self) -> usize {
+        pieces_placed(&self.p1_placements, self.battle.time)
+            .max(pieces_placed(&self.p2_placements, self.battle.time))
+    }
+
+    fn next_placement(&self) -> u32 {
+        let time = self.battle.time;
+        [&self.p1_placements, &self.p2_placements].iter()
+            .filter_map(|p| p.get(pieces_placed(p, time)).copied())
+            .min()
+            .unwrap_or(self.updates.len() as u32)
+    }
+
+    fn previous_placement(&self) -> u32 {
+        let time = self.battle.time;
+        if time == 0 {
+            return 0;
+        }
+        [&self.p1_placements, &self.p2_placements].iter()
+            .filter_map(|p| pieces_placed(p, time - 1).checked_sub(1).map(|i| p[i]))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn handle_keys(&mut self, keys: &HashSet<VirtualKeyCode>) {
+        let pressed: Vec<_> = keys.difference(&self.prev_keys).copied().collect();
+        self.prev_keys = keys.clone();
+        for key in pressed {
+            use VirtualKeyCode::*;
+            match key {
+                Space => self.paused = !self.paused,
+                Right => {
+                    self.paused = true;
+                    self.seek(self.next_placement());
+                }
+                Left => {
+                    self.paused = true;
+                    self.seek(self.previous_placement());
+                }
+                Up => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+                Down => self.speed = self.speed.saturating_sub(1),
+                Home => self.seek(0),
+                End => {
+                    self.paused = true;
+                    self.seek(self.updates.len() as u32);
+                }
+                Back => {
+                    self.piece_input.pop();
+                }
+                Return => if let Ok(piece) = self.piece_input.parse() {
+                    self.piece_input.clear();
+                    self.seek(self.piece_time(piece));
+                }
+                _ => if let Some(c) = digit(key) {
+                    self.piece_input.push(c);
+                }
+            }
         }
     }
 }
@@ -54,49 +232,28 @@ impl crate::State for ReplayGame {
         _executor: &LocalExecutor,
         _log: &mut crate::LogFile,
         res: &mut Resources,
-        _keys: &HashSet<VirtualKeyCode>,
+        keys: &HashSet<VirtualKeyCode>,
         _p1: Option<Gamepad>,
         _p2: Option<Gamepad>
     ) {
-        if self.start_delay == 0 {
-            if let Some((p1_controller, p2_controller)) = self.updates.pop_front() {
-                let update = self.battle.update(p1_controller, p2_controller);
-                self.ui.update(
-                    res, update,
-                    self.p1_info_updates.pop_front().flatten(),
-                    self.p2_info_updates.pop_front().flatten()
-                );
+        self.handle_keys(keys);
+        if self.start_delay != 0 {
+            self.start_delay -= 1;
+            return
+        }
+        if self.paused {
+            return
+        }
+        self.progress += SPEEDS[self.speed];
+        while self.progress >= 1.0 {
+            self.progress -= 1.0;
+            if (self.battle.time as usize) < self.updates.len() {
+                let (update, p1_info, p2_info) = self.step();
+                self.ui.update(res, update, p1_info, p2_info);
             } else {
-                let replay;
-                loop {
-                    match std::fs::File::open(&self.file) {
-                        Ok(f) => {
-                            match bincode::deserialize_from(libflate::deflate::Decoder::new(f)) {
-                                Ok(r) => {
-                                    replay = r;
-                                    break
-                                }
-                                Err(_) => {}
-                            }
-                        }
-                        Err(_) => {}
-                    }
-                }
-                let InfoReplay { replay, p1_info_updates, p2_info_updates } = replay;
-                let battle = Battle::new(
-                    replay.p1_config, replay.p2_config,
-                    replay.p1_seed, replay.p2_seed,
-                    replay.garbage_seed
-                );
-                self.ui = BattleUi::new(&battle, replay.p1_name, replay.p2_name);
-                self.battle = battle;
-                self.updates = replay.updates;
-                self.p1_info_updates = p1_info_updates;
-                self.p2_info_updates = p2_info_updates;
-                self.start_delay = 180;
+                self.reload();
+                break
             }
-        } else {
-            self.start_delay -= 1;
         }
     }
 
@@ -115,13 +272,52 @@ impl crate::State for ReplayGame {
                 [0xFF; 4], 3.0, 0
             );
         }
+        let mut status = format!(
+            "{}x  piece {}/{}",
+            SPEEDS[self.speed], self.current_piece(),
+            self.p1_placements.len().max(self.p2_placements.len())
+        );
+        if self.paused {
+            status.push_str("  paused");
+        }
+        if !self.piece_input.is_empty() {
+            status.push_str(&format!("  go to piece {}", self.piece_input));
+        }
+        res.text.draw_text(
+            &status,
+            20.0, 2.5,
+            Alignment::Center,
+            [0xFF; 4], 0.5, 0
+        );
         self.ui.draw(res);
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InfoReplay {
-    pub replay: Replay,
-    pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
-    pub p2_info_updates: VecDeque<Option<cold_clear::Info>>
-}
\ No newline at end of file
+fn placed(events: &[Event]) -> bool {
+    events.iter().any(|e| matches!(e, Event::PiecePlaced { .. }))
+}
+
+/// Number of pieces placed at or before the given tick.
+fn pieces_placed(placements: &[u32], time: u32) -> usize {
+    match placements.binary_search(&time) {
+        Ok(i) => i + 1,
+        Err(i) => i
+    }
+}
+
+fn digit(key: VirtualKeyCode) -> Option<char> {
+    use VirtualKeyCode::*;
+    Some(match key {
+        Key0 | Numpad0 => '0',
+        Key1 | Numpad1 => '1',
+        Key2 | Numpad2 => '2',
+        Key3 | Numpad3 => '3',
+        Key4 | Numpad4 => '4',
+        Key5 | Numpad5 => '5',
+        Key6 | Numpad6 => '6',
+        Key7 | Numpad7 => '7',
+        Key8 | Numpad8 => '8',
+        Key9 | Numpad9 => '9',
+        _ => return None
+    })
+}
@@ -1,6 +1,7 @@
-use libtetris::{ Board, ColoredRow, FallingPiece, Controller };
-use battle::{ Event, PieceMoveExecutor };
+use libtetris::{ Board, ColoredRow, FallingPiece, Controller, IncomingGarbage };
+use battle::{ Event, Game, PieceMoveExecutor };
 use std::time::{ Instant, Duration };
+use cold_clear::Opponent;
 use cold_clear::evaluation::Evaluator;
 
 pub struct BotInput<E: Evaluator> {
@@ -14,12 +15,12 @@ pub struct BotInput<E: Evaluator> {
 const THINK_AMOUNT: Duration = Duration::from_millis(4);
 
 impl<E: Evaluator> BotInput<E> {
-    pub fn new(board: Board, eval: E) -> Self {
+    pub fn new(board: Board, options: cold_clear::Options, eval: E) -> Self {
         let mut this = BotInput {
             controller: Controller::default(),
             executing: None,
             time_budget: Duration::new(0, 0),
-            bot: cold_clear::BotState::new(board, Default::default()),
+            bot: cold_clear::BotState::new(board, options),
             eval
         };
         for _ in 0..180 {
@@ -49,7 +50,11 @@ impl<E: Evaluator> BotInput<E> {
     }
 
     pub fn update(
-        &mut self, board: &Board<ColoredRow>, events: &[Event], incoming: u32
+        &mut self,
+        board: &Board<ColoredRow>,
+        events: &[Event],
+        incoming: &[IncomingGarbage],
+        opponent: Option<&Game>
     ) -> Option<cold_clear::Info> {
         self.think();
 
@@ -59,7 +64,16 @@ impl<E: Evaluator> BotInput<E> {
                 Event::PieceSpawned { new_in_queue } => {
                     self.bot.add_next_piece(*new_in_queue);
                     if self.executing.is_none() {
-                        if let Some((mv, inf)) = self.bot.suggest_move(&self.eval, None, incoming) {
+                        let opponent = opponent.map(|opponent| Opponent::from_board(
+                            &opponent.board,
+                            &opponent.garbage_queue.incoming(),
+                            opponent.config().max_garbage_add,
+                            opponent.config().garbage.delay
+                        ));
+                        let suggestion = self.bot.suggest_move(
+                            &self.eval, None, incoming, opponent.as_ref()
+                        );
+                        if let Some((mv, inf)) = suggestion {
                             info = Some(inf);
                             self.executing = Some((
                                 mv.expected_location,
@@ -70,7 +84,9 @@ impl<E: Evaluator> BotInput<E> {
                     }
                 }
                 Event::GarbageAdded(_) => {
-                    self.bot.reset(board.get_field(), board.b2b_bonus, board.combo);
+                    self.bot.reset(
+                        board.get_field(), board.b2b_bonus, board.b2b_chain, board.combo
+                    );
                 }
                 _ => {}
             }
@@ -79,7 +95,9 @@ impl<E: Evaluator> BotInput<E> {
         if let Some((expected, ref mut executor)) = self.executing {
             if let Some(loc) = executor.update(&mut self.controller, board, events) {
                 if loc != expected {
-                    self.bot.reset(board.get_field(), board.b2b_bonus, board.combo);
+                    self.bot.reset(
+                        board.get_field(), board.b2b_bonus, board.b2b_chain, board.combo
+                    );
                 }
                 self.executing = None;
             }
@@ -1,10 +1,10 @@
 use std::collections::VecDeque;
-use serde::{ Serialize, Deserialize };
-use battle::{ Replay, Battle, GameConfig };
+use std::cmp::Ordering;
+use battle::{ Battle, GameConfig, Mode, Outcome, SoloGame, load_game_config };
+use replay_file::InfoReplay;
 use cold_clear::evaluation::Evaluator;
 use rand::prelude::*;
 use statrs::distribution::{ Binomial, Univariate };
-use libflate::deflate;
 
 mod input;
 use input::BotInput;
@@ -17,14 +17,31 @@ fn main() {
 
     let p2_eval = changed::Standard::default();
 
+    let game_config = load_game_config("game.json").unwrap_or_else(|e| {
+        eprintln!("game.json contained invalid data: {}", e);
+        std::process::exit(1)
+    });
+    let p1_options = load_bot_options("p1-options.json");
+    let p2_options = load_bot_options("p2-options.json");
+
+    if let Some(mode) = load_mode() {
+        compare_solo((p1_eval, p1_options), (p2_eval, p2_options), game_config, mode);
+        return
+    }
+
     let (send, recv) = std::sync::mpsc::channel();
 
     for _ in 0..12 {
         let p1_eval = p1_eval.clone();
         let p2_eval = p2_eval.clone();
         let send = send.clone();
+        let p1_options = p1_options.clone();
+        let p2_options = p2_options.clone();
+        let game_config = game_config.clone();
         std::thread::spawn(move || loop {
-            if send.send(do_battle(p1_eval.clone(), p2_eval.clone())).is_err() {
+            let p1 = (p1_eval.clone(), p1_options.clone());
+            let p2 = (p2_eval.clone(), p2_options.clone());
+            if send.send(do_battle(p1, p2, &game_config)).is_err() {
                 break
             };
         });
@@ -44,11 +61,7 @@ fn main() {
                     p2_wins += 1;
                 }
 
-                let mut encoder = deflate::Encoder::new(
-                    std::fs::File::create("recent-game.dat"
-                ).unwrap());
-                bincode::serialize_into(&mut encoder, &replay).unwrap();
-                encoder.finish().unwrap();
+                replay.save(std::fs::File::create("recent-game.dat").unwrap()).unwrap();
 
                 println!("{} of {}", p1_wins + p2_wins, games);
                 println!("{} - {}", p1_wins, p2_wins);
@@ -61,34 +74,160 @@ fn main() {
     println!("p = {:.4}", p);
 }
 
-fn do_battle(p1: impl Evaluator + Clone, p2: impl Evaluator + Clone) -> (InfoReplay, bool) {
+/// Reads the options of one of the bots, such as its selection policy, from `path` if it exists.
+fn load_bot_options(path: &str) -> cold_clear::Options {
+    match std::fs::File::open(path) {
+        Ok(file) => serde_json::from_reader(file).unwrap_or_else(|e| {
+            eprintln!("{} contained invalid data: {}", path, e);
+            std::process::exit(1)
+        }),
+        Err(_) => Default::default()
+    }
+}
+
+/// Reads a single player mode from `mode.json` if it exists. Without one, bots play versus.
+fn load_mode() -> Option<Mode> {
+    let file = std::fs::File::open("mode.json").ok()?;
+    Some(serde_json::from_reader(file).unwrap_or_else(|e| {
+        eprintln!("mode.json contained invalid data: {}", e);
+        std::process::exit(1)
+    }))
+}
+
+/// Plays both bots through the same single player games and counts which does better.
+fn compare_solo(
+    p1: (impl Evaluator + Clone + 'static, cold_clear::Options),
+    p2: (impl Evaluator + Clone + 'static, cold_clear::Options),
+    game_config: GameConfig,
+    mode: Mode
+) {
+    let (send, recv) = std::sync::mpsc::channel();
+
+    for _ in 0..12 {
+        let p1 = p1.clone();
+        let p2 = p2.clone();
+        let send = send.clone();
+        let game_config = game_config.clone();
+        let mode = mode.clone();
+        std::thread::spawn(move || loop {
+            let seed = thread_rng().gen();
+            let garbage_seed = thread_rng().gen();
+            let p1 = do_solo(p1.clone(), &game_config, &mode, seed, garbage_seed);
+            let p2 = do_solo(p2.clone(), &game_config, &mode, seed, garbage_seed);
+            if send.send((p1, p2)).is_err() {
+                break
+            };
+        });
+    }
+
+    let mut p1_wins = 0;
+    let mut p2_wins = 0;
+    let mut ties = 0;
+
+    let games = 1000;
+
+    while p1_wins + p2_wins < games {
+        match recv.recv() {
+            Ok((p1, p2)) => {
+                match compare_results(&p1, &p2) {
+                    Ordering::Greater => p1_wins += 1,
+                    Ordering::Less => p2_wins += 1,
+                    Ordering::Equal => ties += 1
+                }
+
+                println!("{} of {}", p1_wins + p2_wins, games);
+                println!("{} - {} ({} ties)", p1_wins, p2_wins, ties);
+                println!("{} vs {}", describe(&p1), describe(&p2));
+            },
+            Err(_) => break
+        }
+    }
+    let distr = Binomial::new(0.5, p1_wins + p2_wins).unwrap();
+    let p = distr.cdf(p1_wins as f64);
+    println!("p = {:.4}", p);
+}
+
+fn do_solo(
+    (eval, options): (impl Evaluator + Clone, cold_clear::Options),
+    config: &GameConfig,
+    mode: &Mode,
+    seed: [u8; 16],
+    garbage_seed: [u8; 16]
+) -> SoloGame {
+    let mut game = SoloGame::new(config.clone(), mode.clone(), seed, garbage_seed)
+        .expect("the game config was validated when it was loaded");
+    let mut bot = BotInput::new(game.game.board.to_compressed(), options, eval);
+    while game.outcome().is_none() && game.time < 54000 { // 15 minutes
+        let update = game.update(bot.controller);
+        bot.update(&game.game.board, &update.events, &update.garbage_queue, None);
+    }
+    game
+}
+
+/// Which of two games of the same mode went better.
+fn compare_results(a: &SoloGame, b: &SoloGame) -> Ordering {
+    let won = |g: &SoloGame| g.outcome() == Some(Outcome::Won);
+    match a.mode {
+        Mode::Sprint { .. } | Mode::Cheese { .. } => won(a).cmp(&won(b))
+            .then(b.remaining().cmp(&a.remaining()))
+            .then(b.time.cmp(&a.time)),
+        Mode::Ultra { .. } => a.score.points.cmp(&b.score.points),
+        Mode::Survival { .. } => a.time.cmp(&b.time)
+            .then(a.score.points.cmp(&b.score.points))
+    }
+}
+
+fn describe(game: &SoloGame) -> String {
+    let seconds = game.time as f64 / 60.0;
+    match (&game.mode, game.outcome()) {
+        (Mode::Sprint { .. }, Some(Outcome::Won)) | (Mode::Cheese { .. }, Some(Outcome::Won)) => {
+            format!("{:.2}s", seconds)
+        }
+        (Mode::Sprint { .. }, _) | (Mode::Cheese { .. }, _) => {
+            format!("{} lines left", game.remaining())
+        }
+        (Mode::Ultra { .. }, _) => format!("{} points", game.score.points),
+        (Mode::Survival { .. }, _) => format!("survived {:.2}s", seconds)
+    }
+}
+
+fn do_battle(
+    (p1, p1_options): (impl Evaluator + Clone, cold_clear::Options),
+    (p2, p2_options): (impl Evaluator + Clone, cold_clear::Options),
+    config: &GameConfig
+) -> (InfoReplay, bool) {
     let mut battle = Battle::new(
-        GameConfig::default(), GameConfig::default(),
-        thread_rng().gen(), thread_rng().gen(), thread_rng().gen()
-    );
+        vec![config.clone(), config.clone()],
+        vec![thread_rng().gen(), thread_rng().gen()],
+        thread_rng().gen()
+    ).expect("the game config was validated when it was loaded");
 
-    battle.replay.p1_name = format!("Cold Clear\n{}", p1.name());
-    battle.replay.p2_name = format!("Cold Clear\n{}", p2.name());
+    battle.replay.names = vec![
+        format!("Cold Clear\n{}", p1.name()),
+        format!("Cold Clear\n{}", p2.name())
+    ];
 
-    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1);
-    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2);
+    let mut p1 = BotInput::new(battle.players[0].board.to_compressed(), p1_options, p1);
+    let mut p2 = BotInput::new(battle.players[1].board.to_compressed(), p2_options, p2);
 
     let mut p1_info_updates = VecDeque::new();
     let mut p2_info_updates = VecDeque::new();
 
     let p1_won;
     'battle: loop {
-        let update = battle.update(p1.controller, p2.controller);
+        let update = battle.update(&[p1.controller, p2.controller]);
         p1_info_updates.push_back(p1.update(
-            &battle.player_1.board, &update.player_1.events,
-            battle.player_1.garbage_queue
+            &battle.players[0].board, &update.players[0].events,
+            &update.players[0].garbage_queue,
+            Some(&battle.players[1])
         ));
         p2_info_updates.push_back(p2.update(
-            &battle.player_2.board, &update.player_2.events,
-            battle.player_2.garbage_queue
+            &battle.players[1].board, &update.players[1].events,
+            &update.players[1].garbage_queue,
+            Some(&battle.players[0])
         ));
 
-        for event in &update.player_1.events {
+        for event in &update.players[0].events {
             use battle::Event::*;
             match event {
                 GameOver => {
@@ -98,7 +237,7 @@ fn do_battle(p1: impl Evaluator + Clone, p2: impl Evaluator + Clone) -> (InfoRep
                 _ => {}
             }
         }
-        for event in &update.player_2.events {
+        for event in &update.players[1].events {
             use battle::Event::*;
             match event {
                 GameOver => {
@@ -111,21 +250,13 @@ fn do_battle(p1: impl Evaluator + Clone, p2: impl Evaluator + Clone) -> (InfoRep
     }
 
     for _ in 0..180 {
-        battle.replay.updates.push_back(Default::default());
+        battle.replay.updates.push_back(vec![Default::default(); 2]);
         p1_info_updates.push_back(None);
         p2_info_updates.push_back(None);
     }
 
     (InfoReplay {
         replay: battle.replay,
-        p1_info_updates,
-        p2_info_updates
+        info_updates: vec![p1_info_updates, p2_info_updates]
     }, p1_won)
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InfoReplay {
-    pub replay: Replay,
-    pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
-    pub p2_info_updates: VecDeque<Option<cold_clear::Info>>
 }
\ No newline at end of file
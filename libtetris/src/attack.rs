@@ -0,0 +1,163 @@
+use arrayvec::ArrayVec;
+use serde::{ Serialize, Deserialize };
+
+use crate::{ PlacementKind, COMBO_GARBAGE };
+
+/// Describes how much garbage line clears send.
+///
+/// The default table is Puyo Puyo Tetris guideline scoring.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttackTable {
+    /// Damage for clearing 0 to 4 lines without a spin.
+    pub clears: [u32; 5],
+    /// Damage for T-spins clearing 0 to 3 lines.
+    pub tspins: [u32; 4],
+    /// Damage for mini T-spins clearing 0 to 2 lines.
+    pub mini_tspins: [u32; 3],
+    /// Damage for spins with other pieces clearing 0 to 4 lines when `all_spins` is `Full`.
+    pub spins: [u32; 5],
+    /// Damage for spins with other pieces clearing 0 to 4 lines when `all_spins` is `Mini`.
+    pub mini_spins: [u32; 5],
+    pub combo: ComboRule,
+    /// Back-to-back bonus levels as `(chain, bonus)` pairs sorted by chain length. The chain is the
+    /// number of back-to-back clears in a row, so the first back-to-back clear has chain 1. The
+    /// bonus of the highest level reached is added to the damage.
+    pub b2b_levels: ArrayVec<[(u32, u32); 16]>,
+    /// Damage for perfect clears.
+    pub perfect_clear: u32,
+    /// Whether a perfect clear sends only `perfect_clear` instead of adding it to the damage.
+    pub perfect_clear_overrides: bool,
+    pub all_spins: AllSpinRule
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ComboRule {
+    /// Adds the entry for the current combo. The last entry is used for longer combos.
+    Table(ArrayVec<[u32; 24]>),
+    /// Multiplies damage by `1 + combo / 4`. Combos that would otherwise send nothing send
+    /// `ln(1 + 1.25 * combo)`. This is what TETR.IO does.
+    Multiplier
+}
+
+/// How spins with pieces other than T are treated.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AllSpinRule {
+    /// Only T-spins are recognized.
+    TspinsOnly,
+    /// Spins with other pieces are scored using `mini_spins`, like in TETR.IO.
+    Mini,
+    /// Spins with other pieces are scored using `spins`.
+    Full
+}
+
+impl Default for AttackTable {
+    fn default() -> Self {
+        AttackTable {
+            clears: [0, 0, 1, 2, 4],
+            tspins: [0, 2, 4, 6],
+            mini_tspins: [0, 0, 1],
+            spins: [0, 2, 4, 6, 10],
+            mini_spins: [0, 0, 1, 2, 4],
+            combo: ComboRule::Table(COMBO_GARBAGE.iter().copied().collect()),
+            b2b_levels: [(1, 1)].iter().copied().collect(),
+            perfect_clear: 10,
+            perfect_clear_overrides: true,
+            all_spins: AllSpinRule::TspinsOnly
+        }
+    }
+}
+
+impl AttackTable {
+    /// TETR.IO's attack table, including back-to-back chaining.
+    pub fn tetrio() -> Self {
+        AttackTable {
+            clears: [0, 0, 1, 2, 4],
+            tspins: [0, 2, 4, 6],
+            mini_tspins: [0, 0, 1],
+            spins: [0, 2, 4, 6, 10],
+            mini_spins: [0, 0, 1, 2, 4],
+            combo: ComboRule::Multiplier,
+            b2b_levels: [
+                (1, 1), (3, 2), (8, 3), (24, 4), (67, 5), (185, 6), (504, 7), (1370, 8)
+            ].iter().copied().collect(),
+            perfect_clear: 10,
+            perfect_clear_overrides: false,
+            all_spins: AllSpinRule::Mini
+        }
+    }
+
+    /// The damage of a placement before back-to-back, combo and perfect clear bonuses.
+    pub fn base(&self, kind: PlacementKind) -> u32 {
+        use PlacementKind::*;
+        match kind {
+            None => self.clears[0],
+            Clear1 => self.clears[1],
+            Clear2 => self.clears[2],
+            Clear3 => self.clears[3],
+            Clear4 => self.clears[4],
+            MiniTspin => self.mini_tspins[0],
+            MiniTspin1 => self.mini_tspins[1],
+            MiniTspin2 => self.mini_tspins[2],
+            Tspin => self.tspins[0],
+            Tspin1 => self.tspins[1],
+            Tspin2 => self.tspins[2],
+            Tspin3 => self.tspins[3],
+            MiniSpin => self.mini_spins[0],
+            MiniSpin1 => self.mini_spins[1],
+            MiniSpin2 => self.mini_spins[2],
+            MiniSpin3 => self.mini_spins[3],
+            MiniSpin4 => self.mini_spins[4],
+            Spin => self.spins[0],
+            Spin1 => self.spins[1],
+            Spin2 => self.spins[2],
+            Spin3 => self.spins[3],
+            Spin4 => self.spins[4]
+        }
+    }
+
+    /// The bonus for a back-to-back clear with the specified chain length.
+    pub fn b2b_bonus(&self, chain: u32) -> u32 {
+        self.b2b_levels.iter()
+            .take_while(|&&(level, _)| level <= chain)
+            .last()
+            .map_or(0, |&(_, bonus)| bonus)
+    }
+
+    /// Applies the combo bonus to `damage`. `combo` is the number of consecutive line clears
+    /// before this one.
+    pub fn apply_combo(&self, damage: u32, combo: u32) -> u32 {
+        match &self.combo {
+            ComboRule::Table(table) => damage + table.get(combo as usize)
+                .or(table.last())
+                .copied()
+                .unwrap_or(0),
+            ComboRule::Multiplier => {
+                let multiplied = damage as f64 * (1.0 + 0.25 * combo as f64);
+                if combo > 1 {
+                    multiplied.max((1.25 * combo as f64).ln_1p()) as u32
+                } else {
+                    multiplied as u32
+                }
+            }
+        }
+    }
+
+    /// Applies the perfect clear bonus to `damage`.
+    pub fn apply_perfect_clear(&self, damage: u32) -> u32 {
+        if self.perfect_clear_overrides {
+            self.perfect_clear
+        } else {
+            damage + self.perfect_clear
+        }
+    }
+}
+
+/// An attack waiting to enter the board.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IncomingGarbage {
+    pub lines: u32,
+    /// The number of ticks until the garbage can enter the board. Garbage with no delay enters the
+    /// board the next time a piece is placed without clearing lines.
+    pub delay: u32
+}
@@ -6,21 +6,32 @@ use serde::{ Serialize, Deserialize };
 
 use crate::*;
 
+/// The widest supported board.
+pub const MAX_WIDTH: usize = 16;
+/// The number of rows stored by every board, including the rows above the visible area.
+pub const ROWS: usize = 40;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Board<R=u16> {
-    cells: ArrayVec<[R; 40]>,
-    column_heights: [i32; 10],
+    cells: ArrayVec<[R; ROWS]>,
+    column_heights: [i32; MAX_WIDTH],
+    width: usize,
+    height: usize,
     pub combo: u32,
     pub b2b_bonus: bool,
+    /// The number of back-to-back clears in a row.
+    pub b2b_chain: u32,
     pub hold_piece: Option<Piece>,
     next_pieces: VecDeque<Piece>,
-    pub bag: EnumSet<Piece>,
+    /// Decides the pieces added to the next queue. It has seen every piece added so far.
+    pub randomizer: AnyRandomizer,
 }
 
 pub trait Row: Copy + Clone + 'static {
     fn set(&mut self, x: usize, color: CellColor);
     fn get(&self, x: usize) -> bool;
-    fn is_full(&self) -> bool;
+    /// Whether the first `width` cells are filled.
+    fn is_full(&self, width: usize) -> bool;
     fn is_empty(&self) -> bool;
     fn cell_color(&self, x: usize) -> CellColor;
 
@@ -29,53 +40,70 @@ pub trait Row: Copy + Clone + 'static {
 }
 
 impl<R: Row> Board<R> {
-    /// Creates a blank board with an empty queue.
+    /// Creates a blank 10x20 board with an empty queue.
     pub fn new() -> Self {
+        Self::with_dimensions(10, 20)
+    }
+
+    /// Creates a blank board with the specified width and visible height and an empty queue.
+    ///
+    /// The width must be between 4 and `MAX_WIDTH`. Pieces spawn just above the visible area, which
+    /// has to leave room for them in the `ROWS` rows the board stores.
+    pub fn with_dimensions(width: usize, height: usize) -> Self {
+        assert!((4..=MAX_WIDTH).contains(&width), "unsupported board width {}", width);
+        assert!(height >= 4 && height + 4 <= ROWS, "unsupported board height {}", height);
         Board {
-            cells: [*R::EMPTY; 40].into(),
-            column_heights: [0; 10],
+            cells: [*R::EMPTY; ROWS].into(),
+            column_heights: [0; MAX_WIDTH],
+            width,
+            height,
             combo: 0,
             b2b_bonus: false,
+            b2b_chain: 0,
             hold_piece: None,
             next_pieces: VecDeque::new(),
-            bag: EnumSet::all(),
+            randomizer: AnyRandomizer::default(),
         }
     }
 
-    /// Creates a board with existing field, remain pieces in the bag, hold piece, back-to-back status and combo count.
-    pub fn new_with_state(field: [[bool; 10]; 40], bag_remain: EnumSet<Piece>, hold: Option<Piece>, b2b: bool, combo: u32) -> Self {
+    /// Creates a board with existing field, remain pieces in the bag, hold piece, back-to-back status, back-to-back chain length and combo count.
+    ///
+    /// The board uses a 7-bag randomizer.
+    ///
+    /// The board is as wide as the rows of the field and has a visible height of 20.
+    pub fn new_with_state<F: AsRef<[bool]>>(field: &[F], bag_remain: EnumSet<Piece>, hold: Option<Piece>, b2b: bool, b2b_chain: u32, combo: u32) -> Self {
+        let width = field.first().map_or(10, |r| r.as_ref().len());
         let mut board = Board {
-            cells: [*R::EMPTY; 40].into(),
-            column_heights: [0; 10],
             combo: combo,
             b2b_bonus: b2b,
+            b2b_chain,
             hold_piece: hold,
-            next_pieces: VecDeque::new(),
-            bag: if bag_remain.is_empty() {
-                EnumSet::all()
-            } else {
-                bag_remain
-            },
+            randomizer: AnyRandomizer::SevenBag(SevenBag {
+                remaining: if bag_remain.is_empty() {
+                    EnumSet::all()
+                } else {
+                    bag_remain
+                }
+            }),
+            ..Self::with_dimensions(width, 20)
         };
         board.set_field(field);
         board
     }
 
-    /// Randomly selects a piece from the bag.
+    /// Randomly selects the next piece using the randomizer.
     /// 
-    /// This function does not remove the generated piece from the bag.
+    /// This function does not update the randomizer.
     /// Use add_next_piece() to add it to the queue.
     pub fn generate_next_piece(&self, rng: &mut impl rand::Rng) -> Piece {
-        use rand::prelude::*;
-        let choices: ArrayVec<[_; 7]> = self.bag.iter().collect();
-        *choices.choose(rng).unwrap()
+        self.randomizer.generate(rng)
     }
 
     /// Retrieves the next piece in the queue.
     /// 
     /// If the queue is empty, returns the set of possible next pieces.
     pub fn get_next_piece(&self) -> Result<Piece, EnumSet<Piece>> {
-        self.next_pieces.front().copied().ok_or(self.bag)
+        self.next_pieces.front().copied().ok_or_else(|| self.randomizer.possible_pieces())
     }
 
     /// Retrieves the piece after the next piece in the queue if it is known.
@@ -83,22 +111,18 @@ impl<R: Row> Board<R> {
         self.next_pieces.get(1).copied()
     }
 
-    /// Adds the piece to the next queue and removes it from the bag.
-    /// 
-    /// If the bag becomes empty, the bag is refilled.
+    /// Adds the piece to the next queue and shows it to the randomizer.
     pub fn add_next_piece(&mut self, piece: Piece) {
-        self.bag.remove(piece);
-        if self.bag.is_empty() {
-            self.bag = EnumSet::all();
-        }
+        self.randomizer.observe(piece);
         self.next_pieces.push_back(piece);
     }
 
     fn remove_cleared_lines(&mut self) -> ArrayVec<[i32; 4]> {
         let mut cleared = ArrayVec::new();
         let mut lineno = 0;
+        let width = self.width;
         self.cells.retain(|r| {
-            let full = r.is_full();
+            let full = r.is_full(width);
             if full {
                 cleared.push(lineno);
             }
@@ -109,7 +133,7 @@ impl<R: Row> Board<R> {
         for _ in 0..cleared.len() {
             self.cells.push(*R::EMPTY);
         }
-        for x in 0..10 {
+        for x in 0..self.width {
             self.column_heights[x] -= cleared.len() as i32;
             while self.column_heights[x] > 0 &&
                     !self.cells[self.column_heights[x] as usize-1].get(x) {
@@ -120,13 +144,14 @@ impl<R: Row> Board<R> {
     }
 
     pub fn occupied(&self, x: i32, y: i32) -> bool {
-        x < 0 || y < 0 || x >= 10 || y >= 40 || (self.cells[y as usize].get(x as usize))
+        x < 0 || y < 0 || x >= self.width as i32 || y >= ROWS as i32 ||
+            self.cells[y as usize].get(x as usize)
     }
 
     pub fn get_row(&self, y: i32) -> &R {
         if y < 0 {
             R::SOLID
-        } else if y >= 40 {
+        } else if y >= ROWS as i32 {
             R::EMPTY
         } else {
             &self.cells[y as usize]
@@ -155,53 +180,56 @@ impl<R: Row> Board<R> {
     /// 
     /// Clears lines, detects clear kind, calculates garbage, maintains combo and back-to-back
     /// state, detects perfect clears, detects lockout.
-    pub fn lock_piece(&mut self, piece: FallingPiece) -> LockResult {
+    pub fn lock_piece(&mut self, piece: FallingPiece, attack: &AttackTable) -> LockResult {
         let mut locked_out = true;
         for &(x, y) in &piece.cells() {
             self.cells[y as usize].set(x as usize, piece.kind.0.color());
             if self.column_heights[x as usize] < y+1 {
                 self.column_heights[x as usize] = y+1;
             }
-            if y < 20 {
+            if y < self.height as i32 {
                 locked_out = false;
             }
         }
         let cleared = self.remove_cleared_lines();
 
-        let placement_kind = PlacementKind::get(cleared.len(), piece.tspin);
+        let placement_kind = PlacementKind::get(cleared.len(), piece.tspin, attack.all_spins);
 
-        let mut garbage_sent = placement_kind.garbage();
+        let mut garbage_sent = attack.base(placement_kind);
+        let mut combo_garbage = 0;
 
         let mut did_b2b = false;
         if placement_kind.is_clear() {
             if placement_kind.is_hard() {
                 if self.b2b_bonus {
-                    garbage_sent += 1;
+                    self.b2b_chain += 1;
+                    garbage_sent += attack.b2b_bonus(self.b2b_chain);
                     did_b2b = true;
+                } else {
+                    self.b2b_chain = 0;
                 }
                 self.b2b_bonus = true;
             } else {
                 self.b2b_bonus = false;
+                self.b2b_chain = 0;
             }
 
-            if self.combo as usize >= COMBO_GARBAGE.len() {
-                garbage_sent += COMBO_GARBAGE.last().unwrap();
-            } else {
-                garbage_sent += COMBO_GARBAGE[self.combo as usize];
-            }
+            let with_combo = attack.apply_combo(garbage_sent, self.combo);
+            combo_garbage = with_combo.saturating_sub(garbage_sent);
+            garbage_sent = with_combo;
 
             self.combo += 1;
         } else {
             self.combo = 0;
         }
 
-        let perfect_clear = self.column_heights == [0; 10];
+        let perfect_clear = self.column_heights().iter().all(|&h| h == 0);
         if perfect_clear {
-            garbage_sent = 10;
+            garbage_sent = attack.apply_perfect_clear(garbage_sent);
         }
 
         let l = LockResult {
-            placement_kind, garbage_sent, perfect_clear, locked_out,
+            placement_kind, garbage_sent, combo_garbage, perfect_clear, locked_out,
             combo: if self.combo == 0 { None } else { Some(self.combo-1) },
             b2b: did_b2b,
             cleared_lines: cleared
@@ -228,13 +256,24 @@ impl<R: Row> Board<R> {
         self.next_pieces.pop_front()
     }
 
-    pub fn column_heights(&self) -> &[i32; 10] {
-        &self.column_heights
+    pub fn column_heights(&self) -> &[i32] {
+        &self.column_heights[..self.width]
+    }
+
+    /// The number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the visible area. Pieces spawn just above it, and locking a piece entirely
+    /// above it is a lock out.
+    pub fn height(&self) -> usize {
+        self.height
     }
 
     pub fn add_garbage(&mut self, col: usize) -> bool {
         let mut row = *R::EMPTY;
-        for x in 0..10 {
+        for x in 0..self.width {
             if x == col {
                 if self.column_heights[x] != 0 {
                     self.column_heights[x] += 1;
@@ -253,27 +292,33 @@ impl<R: Row> Board<R> {
         Board {
             cells: self.cells.iter().map(|r| {
                 let mut row = 0;
-                for x in 0..10 {
+                for x in 0..self.width {
                     row.set(x, r.cell_color(x));
                 }
                 row
             }).collect(),
             b2b_bonus: self.b2b_bonus,
+            b2b_chain: self.b2b_chain,
             combo: self.combo,
             column_heights: self.column_heights,
+            width: self.width,
+            height: self.height,
             next_pieces: self.next_pieces.clone(),
             hold_piece: self.hold_piece,
-            bag: self.bag
+            randomizer: self.randomizer.clone()
         }
     }
 
-    pub fn set_field(&mut self, field: [[bool; 10]; 40]) {
+    /// Replaces the contents of the board with `field`, which is indexed by row and then column
+    /// starting from the bottom left. Cells outside the field are empty.
+    pub fn set_field<F: AsRef<[bool]>>(&mut self, field: &[F]) {
         self.cells.clear();
-        self.column_heights = [0; 10];
-        for y in 0..40 {
+        self.column_heights = [0; MAX_WIDTH];
+        for y in 0..ROWS {
             let mut r = *R::EMPTY;
-            for x in 0..10 {
-                if field[y][x] {
+            let row = field.get(y).map_or(&[][..], |r| r.as_ref());
+            for x in 0..self.width {
+                if row.get(x).copied().unwrap_or(false) {
                     r.set(x, CellColor::Garbage);
                     self.column_heights[x] = y as i32 + 1;
                 }
@@ -282,18 +327,18 @@ impl<R: Row> Board<R> {
         }
     }
 
-    pub fn get_field(&self) -> [[bool; 10]; 40] {
-        let mut field = [[false; 10]; 40];
-        for y in 0..40 {
-            for x in 0..10 {
-                field[y][x] = self.occupied(x as i32, y as i32)
-            }
-        }
-        field
+    /// Returns all `ROWS` rows of the board, starting from the bottom.
+    pub fn get_field(&self) -> Vec<Vec<bool>> {
+        (0..ROWS as i32).map(|y|
+            (0..self.width as i32).map(|x| self.occupied(x, y)).collect()
+        ).collect()
     }
 
+    /// The pieces left in the 7-bag the next piece came from, counting the next piece.
+    ///
+    /// Only meaningful with a 7-bag randomizer.
     pub fn next_bag(&self) -> EnumSet<Piece> {
-        let mut bag = self.bag;
+        let mut bag = self.randomizer.possible_pieces();
         for p in self.next_queue().rev() {
             if bag == EnumSet::all() {
                 bag = EnumSet::empty();
@@ -318,8 +363,9 @@ impl Row for u16 {
         *self & (1 << x) != 0
     }
 
-    fn is_full(&self) -> bool {
-        self == Self::SOLID
+    fn is_full(&self, width: usize) -> bool {
+        let mask = !0 >> (16 - width);
+        *self & mask == mask
     }
 
     fn is_empty(&self) -> bool {
@@ -334,16 +380,16 @@ impl Row for u16 {
         }
     }
 
-    const SOLID: &'static u16 = &0b11111_11111;
+    const SOLID: &'static u16 = &!0;
     const EMPTY: &'static u16 = &0;
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct ColoredRow([CellColor; 10]);
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct ColoredRow([CellColor; MAX_WIDTH]);
 
 impl Default for ColoredRow {
     fn default() -> Self {
-        ColoredRow([CellColor::Empty; 10])
+        ColoredRow([CellColor::Empty; MAX_WIDTH])
     }
 }
 
@@ -356,8 +402,8 @@ impl Row for ColoredRow {
         self.0[x] != CellColor::Empty
     }
 
-    fn is_full(&self) -> bool {
-        self.0.iter().all(|&c| c != CellColor::Empty)
+    fn is_full(&self, width: usize) -> bool {
+        self.0[..width].iter().all(|&c| c != CellColor::Empty)
     }
 
     fn cell_color(&self, x: usize) -> CellColor {
@@ -368,6 +414,67 @@ impl Row for ColoredRow {
         self.0.iter().all(|&c| c == CellColor::Empty)
     }
 
-    const SOLID: &'static Self = &ColoredRow([CellColor::Unclearable; 10]);
-    const EMPTY: &'static Self = &ColoredRow([CellColor::Empty; 10]);
-}
\ No newline at end of file
+    const SOLID: &'static Self = &ColoredRow([CellColor::Unclearable; MAX_WIDTH]);
+    const EMPTY: &'static Self = &ColoredRow([CellColor::Empty; MAX_WIDTH]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_garbage_leaves_hole() {
+        let mut board = Board::<u16>::new();
+        board.set_field(&[[true, true, false, false, false, false, false, false, false, false]]);
+        assert!(!board.add_garbage(0));
+        assert!(!board.add_garbage(3));
+        assert_eq!(board.column_heights(), &[3, 3, 2, 2, 2, 2, 2, 2, 2, 2]);
+        assert!(!board.occupied(3, 0));
+        assert!(!board.occupied(0, 1));
+        assert!(board.occupied(0, 2));
+    }
+
+    #[test]
+    fn add_garbage_top_out() {
+        let mut board = Board::<u16>::new();
+        let mut field = vec![[false; 10]; ROWS - 1];
+        for row in &mut field {
+            row[0] = true;
+        }
+        board.set_field(&field);
+        assert!(!board.add_garbage(0), "the top row was empty");
+        assert!(board.add_garbage(0), "a filled row was pushed off the board");
+    }
+
+    #[test]
+    fn lock_out() {
+        let mut board = Board::<u16>::new();
+        let piece = FallingPiece {
+            kind: PieceState(Piece::O, RotationState::North),
+            x: 4, y: 20,
+            tspin: TspinStatus::None
+        };
+        assert!(board.lock_piece(piece, &AttackTable::default()).locked_out);
+        let piece = FallingPiece { y: 19, ..piece };
+        assert!(!board.lock_piece(piece, &AttackTable::default()).locked_out);
+    }
+
+    #[test]
+    fn clears_lines() {
+        let mut board = Board::<u16>::new();
+        board.set_field(&[
+            [true, true, true, true, false, false, true, true, true, true],
+            [true, true, true, true, false, false, true, true, true, false],
+            [false, false, false, false, false, false, false, false, false, true]
+        ]);
+        let piece = FallingPiece {
+            kind: PieceState(Piece::O, RotationState::North),
+            x: 4, y: 0,
+            tspin: TspinStatus::None
+        };
+        let result = board.lock_piece(piece, &AttackTable::default());
+        assert_eq!(result.placement_kind, PlacementKind::Clear1);
+        assert_eq!(&result.cleared_lines[..], &[0]);
+        assert_eq!(board.column_heights(), &[1, 1, 1, 1, 1, 1, 1, 1, 1, 2]);
+    }
+}
@@ -71,3 +71,94 @@ impl From<RotationState> for fumen::RotationState {
         }
     }
 }
+
+impl From<fumen::CellColor> for CellColor {
+    fn from(v: fumen::CellColor) -> CellColor {
+        match v {
+            fumen::CellColor::Empty => CellColor::Empty,
+            fumen::CellColor::I => CellColor::I,
+            fumen::CellColor::O => CellColor::O,
+            fumen::CellColor::T => CellColor::T,
+            fumen::CellColor::L => CellColor::L,
+            fumen::CellColor::J => CellColor::J,
+            fumen::CellColor::S => CellColor::S,
+            fumen::CellColor::Z => CellColor::Z,
+            fumen::CellColor::Grey => CellColor::Garbage,
+        }
+    }
+}
+
+impl From<CellColor> for fumen::CellColor {
+    fn from(v: CellColor) -> fumen::CellColor {
+        match v {
+            CellColor::Empty => fumen::CellColor::Empty,
+            CellColor::I => fumen::CellColor::I,
+            CellColor::O => fumen::CellColor::O,
+            CellColor::T => fumen::CellColor::T,
+            CellColor::L => fumen::CellColor::L,
+            CellColor::J => fumen::CellColor::J,
+            CellColor::S => fumen::CellColor::S,
+            CellColor::Z => fumen::CellColor::Z,
+            CellColor::Garbage | CellColor::Unclearable => fumen::CellColor::Grey,
+        }
+    }
+}
+
+impl<R: Row> Board<R> {
+    /// Creates a board like `Board::new` and fills its bottom 23 rows from the field of a fumen
+    /// page. The rows above stay empty and the garbage row is ignored.
+    ///
+    /// Only the occupancy of the cells is kept, so every filled cell becomes garbage.
+    pub fn from_fumen_page(page: &fumen::Page) -> Self {
+        let field: Vec<Vec<_>> = page
+            .field
+            .iter()
+            .map(|row| row.iter().map(|&c| c != fumen::CellColor::Empty).collect())
+            .collect();
+        let mut board = Board::new();
+        board.set_field(&field);
+        board
+    }
+
+    /// Copies the field into a fumen page. Fumen fields are 10 wide and 23 tall, so any other
+    /// cells are left out.
+    pub fn write_fumen_page(&self, page: &mut fumen::Page) {
+        for (y, row) in page.field.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = if x < self.width() {
+                    self.get_row(y as i32).cell_color(x).into()
+                } else {
+                    fumen::CellColor::Empty
+                };
+            }
+        }
+        page.garbage_row = [fumen::CellColor::Empty; 10];
+    }
+
+    /// Creates a single page fumen of the field.
+    pub fn to_fumen(&self) -> fumen::Fumen {
+        placements_to_fumen(self, std::iter::empty())
+    }
+}
+
+/// Creates a fumen with a page for each placement, starting from `board`.
+///
+/// Every page shows the field before its piece locks, so the result is what the pieces actually
+/// did on this board even where fumen's own line clear logic would disagree.
+pub fn placements_to_fumen<R: Row>(
+    board: &Board<R>,
+    placements: impl IntoIterator<Item = FallingPiece>,
+) -> fumen::Fumen {
+    let mut fumen = fumen::Fumen::default();
+    let mut board = board.clone();
+    for piece in placements {
+        let page = fumen.add_page();
+        board.write_fumen_page(page);
+        page.piece = Some(piece.into());
+        board.lock_piece(piece, &AttackTable::default());
+    }
+    if fumen.pages.is_empty() {
+        board.write_fumen_page(fumen.add_page());
+    }
+    fumen
+}
@@ -2,9 +2,17 @@ mod board;
 mod piece;
 mod lock_data;
 mod moves;
+mod rotation;
+mod attack;
+mod score;
+mod randomizer;
 
 #[cfg(feature = "fumen")]
 mod fumen_conv;
+#[cfg(feature = "fumen")]
+pub use fumen_conv::placements_to_fumen;
+#[cfg(feature = "fumen")]
+pub use fumen;
 
 #[cfg(feature = "pcf")]
 mod pcf_conv;
@@ -13,6 +21,13 @@ pub use board::*;
 pub use piece::*;
 pub use lock_data::*;
 pub use moves::*;
+pub use rotation::*;
+pub use attack::*;
+pub use score::*;
+pub use randomizer::*;
+
+/// The version of libtetris, as recorded in replay files.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
 pub struct Controller {
@@ -20,6 +35,7 @@ pub struct Controller {
     pub right: bool,
     pub rotate_right: bool,
     pub rotate_left: bool,
+    pub rotate_180: bool,
     pub soft_drop: bool,
     pub hard_drop: bool,
     pub hold: bool
@@ -28,6 +44,7 @@ pub struct Controller {
 impl serde::Serialize for Controller {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         serializer.serialize_u8(
+            (self.rotate_180 as u8)        |
             (self.left as u8)         << 1 |
             (self.right as u8)        << 2 |
             (self.rotate_left as u8)  << 3 |
@@ -49,6 +66,7 @@ impl<'de> serde::Deserialize<'de> for Controller {
             }
             fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Controller, E> {
                 Ok(Controller {
+                    rotate_180:   v        & 1 != 0,
                     left:         (v >> 1) & 1 != 0,
                     right:        (v >> 2) & 1 != 0,
                     rotate_left:  (v >> 3) & 1 != 0,
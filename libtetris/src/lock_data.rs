@@ -2,6 +2,7 @@ use arrayvec::ArrayVec;
 use serde::{ Serialize, Deserialize };
 
 use crate::piece::TspinStatus;
+use crate::AllSpinRule;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
 pub struct LockResult {
@@ -11,6 +12,8 @@ pub struct LockResult {
     pub perfect_clear: bool,
     pub combo: Option<u32>,
     pub garbage_sent: u32,
+    /// The part of `garbage_sent` that comes from the combo bonus.
+    pub combo_garbage: u32,
     pub cleared_lines: ArrayVec<[i32; 4]>
 }
 
@@ -27,19 +30,33 @@ pub enum PlacementKind {
     Tspin,
     Tspin1,
     Tspin2,
-    Tspin3
+    Tspin3,
+    MiniSpin,
+    MiniSpin1,
+    MiniSpin2,
+    MiniSpin3,
+    MiniSpin4,
+    Spin,
+    Spin1,
+    Spin2,
+    Spin3,
+    Spin4
 }
 
 impl PlacementKind {
-    /// The amount of garbage this clear kind normally sends.
+    /// The amount of garbage this clear kind sends under guideline rules.
+    ///
+    /// See `AttackTable` for other rules.
     pub fn garbage(self) -> u32 {
         use PlacementKind::*;
         match self {
             None | MiniTspin | Tspin | Clear1 | MiniTspin1 => 0,
-            Clear2 | MiniTspin2 => 1,
-            Clear3 | Tspin1 => 2,
-            Clear4 | Tspin2 => 4,
-            Tspin3 => 6
+            MiniSpin | MiniSpin1 | Spin => 0,
+            Clear2 | MiniTspin2 | MiniSpin2 => 1,
+            Clear3 | Tspin1 | MiniSpin3 | Spin1 => 2,
+            Clear4 | Tspin2 | MiniSpin4 | Spin2 => 4,
+            Tspin3 | Spin3 => 6,
+            Spin4 => 10
         }
     }
 
@@ -49,7 +66,9 @@ impl PlacementKind {
         match self {
             Clear4 |
             MiniTspin | MiniTspin1 | MiniTspin2 |
-            Tspin | Tspin1 | Tspin2 | Tspin3 => true,
+            Tspin | Tspin1 | Tspin2 | Tspin3 |
+            MiniSpin | MiniSpin1 | MiniSpin2 | MiniSpin3 | MiniSpin4 |
+            Spin | Spin1 | Spin2 | Spin3 | Spin4 => true,
             _ => false
         }
     }
@@ -57,12 +76,31 @@ impl PlacementKind {
     /// Whether or not this placement did a line clear.
     pub fn is_clear(self) -> bool {
         match self {
-            PlacementKind::None | PlacementKind::MiniTspin | PlacementKind::Tspin => false,
+            PlacementKind::None | PlacementKind::MiniTspin | PlacementKind::Tspin |
+            PlacementKind::MiniSpin | PlacementKind::Spin => false,
             _ => true
         }
     }
 
-    pub(crate) fn get(cleared: usize, tspin: TspinStatus) -> Self {
+    pub(crate) fn get(cleared: usize, tspin: TspinStatus, all_spins: AllSpinRule) -> Self {
+        let tspin = match (tspin, all_spins) {
+            (TspinStatus::Immobile, AllSpinRule::TspinsOnly) => TspinStatus::None,
+            (TspinStatus::Immobile, AllSpinRule::Mini) => return match cleared {
+                0 => PlacementKind::MiniSpin,
+                1 => PlacementKind::MiniSpin1,
+                2 => PlacementKind::MiniSpin2,
+                3 => PlacementKind::MiniSpin3,
+                _ => PlacementKind::MiniSpin4
+            },
+            (TspinStatus::Immobile, AllSpinRule::Full) => return match cleared {
+                0 => PlacementKind::Spin,
+                1 => PlacementKind::Spin1,
+                2 => PlacementKind::Spin2,
+                3 => PlacementKind::Spin3,
+                _ => PlacementKind::Spin4
+            },
+            (tspin, _) => tspin
+        };
         match (cleared, tspin) {
             (0, TspinStatus::None) => PlacementKind::None,
             (0, TspinStatus::Mini) => PlacementKind::MiniTspin,
@@ -95,6 +133,16 @@ impl PlacementKind {
             PlacementKind::Tspin1     => "T-Spin Single",
             PlacementKind::Tspin2     => "T-Spin Double",
             PlacementKind::Tspin3     => "T-Spin Triple",
+            PlacementKind::MiniSpin   => "Mini Spin",
+            PlacementKind::MiniSpin1  => "Mini Spin Single",
+            PlacementKind::MiniSpin2  => "Mini Spin Double",
+            PlacementKind::MiniSpin3  => "Mini Spin Triple",
+            PlacementKind::MiniSpin4  => "Mini Spin Quad",
+            PlacementKind::Spin       => "Spin",
+            PlacementKind::Spin1      => "Spin Single",
+            PlacementKind::Spin2      => "Spin Double",
+            PlacementKind::Spin3      => "Spin Triple",
+            PlacementKind::Spin4      => "Spin Quad",
         }
     }
 
@@ -112,6 +160,16 @@ impl PlacementKind {
             PlacementKind::Tspin1     => "TSS",
             PlacementKind::Tspin2     => "TSD",
             PlacementKind::Tspin3     => "TST",
+            PlacementKind::MiniSpin   => "sp",
+            PlacementKind::MiniSpin1  => "sps",
+            PlacementKind::MiniSpin2  => "spd",
+            PlacementKind::MiniSpin3  => "spt",
+            PlacementKind::MiniSpin4  => "spq",
+            PlacementKind::Spin       => "SP",
+            PlacementKind::Spin1      => "SPS",
+            PlacementKind::Spin2      => "SPD",
+            PlacementKind::Spin3      => "SPT",
+            PlacementKind::Spin4      => "SPQ",
         }
     }
 }
@@ -148,6 +206,16 @@ pub struct Statistics {
     pub mini_tspin_zeros: u64,
     pub mini_tspin_singles: u64,
     pub mini_tspin_doubles: u64,
+    pub spin_zeros: u64,
+    pub spin_singles: u64,
+    pub spin_doubles: u64,
+    pub spin_triples: u64,
+    pub spin_quads: u64,
+    pub mini_spin_zeros: u64,
+    pub mini_spin_singles: u64,
+    pub mini_spin_doubles: u64,
+    pub mini_spin_triples: u64,
+    pub mini_spin_quads: u64,
     pub perfect_clears: u64,
     pub max_combo: u64
 }
@@ -179,7 +247,17 @@ impl Statistics {
             PlacementKind::Tspin3 => self.tspin_triples += 1,
             PlacementKind::MiniTspin => self.mini_tspin_zeros += 1,
             PlacementKind::MiniTspin1 => self.mini_tspin_singles += 1,
-            PlacementKind::MiniTspin2 => self.mini_tspin_doubles += 1
+            PlacementKind::MiniTspin2 => self.mini_tspin_doubles += 1,
+            PlacementKind::Spin => self.spin_zeros += 1,
+            PlacementKind::Spin1 => self.spin_singles += 1,
+            PlacementKind::Spin2 => self.spin_doubles += 1,
+            PlacementKind::Spin3 => self.spin_triples += 1,
+            PlacementKind::Spin4 => self.spin_quads += 1,
+            PlacementKind::MiniSpin => self.mini_spin_zeros += 1,
+            PlacementKind::MiniSpin1 => self.mini_spin_singles += 1,
+            PlacementKind::MiniSpin2 => self.mini_spin_doubles += 1,
+            PlacementKind::MiniSpin3 => self.mini_spin_triples += 1,
+            PlacementKind::MiniSpin4 => self.mini_spin_quads += 1
         }
     }
 }
\ No newline at end of file
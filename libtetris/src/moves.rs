@@ -1,4 +1,7 @@
-use crate::{ Board, FallingPiece, Piece, PieceState, RotationState, TspinStatus, PieceMovement };
+use crate::{
+    Board, FallingPiece, Piece, PieceState, RotationState, TspinStatus, PieceMovement,
+    RotationSystem
+};
 use arrayvec::ArrayVec;
 use std::cmp::Ordering;
 use std::collections::{ BinaryHeap, HashMap, HashSet };
@@ -48,16 +51,21 @@ impl PartialOrd for Placement {
 pub fn find_moves(
     board: &Board,
     mut spawned: FallingPiece,
-    mode: MovementMode
+    mode: MovementMode,
+    rotation_system: &RotationSystem,
+    use_180: bool
 ) -> Vec<Placement> {
     let mut locks = HashMap::with_capacity(128);
     let mut checked = HashSet::with_capacity(128);
     let mut check_queue = Vec::with_capacity(64);
     let fast_mode;
 
-    if board.column_heights().iter().all(|&v| v < 16) {
+    let fast_height = board.height() as i32 - 4;
+    if board.width() == 10 && board.column_heights().iter().all(|&v| v < fast_height) &&
+            rotation_system.rotates_in_place() {
         // We know that we can reach any column and rotation state without bumping into the terrain
-        // at 0G here, so we can just grab those starting positions.
+        // at 0G here, so we can just grab those starting positions. The precomputed paths assume
+        // that rotations in open space don't move the piece and are only valid for 10 wide boards.
         let starts = match mode {
             MovementMode::TwentyG => vec![
                 (spawned, InputList {
@@ -65,18 +73,20 @@ pub fn find_moves(
                     time: 0
                 })
             ],
-            _ => zero_g_starts(spawned.kind.0),
+            _ => zero_g_starts(spawned.kind.0, use_180),
         };
         // Fast mode prevents checking a lot of stack movement that is unlikely (but still could)
         // to lead to new placements. Use ZeroGComplete to get these missed positions.
         fast_mode = mode == MovementMode::ZeroG;
         for (mut place, mut inputs) in starts {
+            // The precomputed starts are in the spawn row of a board with a visible height of 20.
+            place.y += board.height() as i32 - 20;
             let orig_y = place.y;
             place.sonic_drop(board);
             if !fast_mode {
                 checked.insert(place);
             }
-            lock_check(place, &mut locks, inputs.clone());
+            lock_check(board, place, &mut locks, inputs.clone());
             if mode != MovementMode::HardDropOnly {
                 // Initialize stack movement starting positions.
                 inputs.movements.push(PieceMovement::SonicDrop);
@@ -109,13 +119,13 @@ pub fn find_moves(
             attempt(
                 board, &moves, position,
                 &mut checked, &mut check_queue,
-                mode, fast_mode,
+                mode, rotation_system, fast_mode,
                 PieceMovement::Left, false
             );
             attempt(
                 board, &moves, position,
                 &mut checked, &mut check_queue,
-                mode, fast_mode,
+                mode, rotation_system, fast_mode,
                 PieceMovement::Right, false
             );
 
@@ -123,30 +133,39 @@ pub fn find_moves(
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, rotation_system, fast_mode,
                     PieceMovement::Cw, false
                 );
 
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, rotation_system, fast_mode,
                     PieceMovement::Ccw, false
                 );
+
+                if use_180 {
+                    attempt(
+                        board, &moves, position,
+                        &mut checked, &mut check_queue,
+                        mode, rotation_system, fast_mode,
+                        PieceMovement::Flip, false
+                    );
+                }
             }
 
             if mode == MovementMode::ZeroG {
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, rotation_system, fast_mode,
                     PieceMovement::Left, true
                 );
 
                 attempt(
                     board, &moves, position,
                     &mut checked, &mut check_queue,
-                    mode, fast_mode,
+                    mode, rotation_system, fast_mode,
                     PieceMovement::Right, true
                 );
             }
@@ -154,31 +173,33 @@ pub fn find_moves(
             attempt(
                 board, &moves, position,
                 &mut checked, &mut check_queue,
-                mode, fast_mode,
+                mode, rotation_system, fast_mode,
                 PieceMovement::SonicDrop, false
             );
         }
 
         let mut position = position;
         position.sonic_drop(board);
-        lock_check(position, &mut locks, moves);
+        lock_check(board, position, &mut locks, moves);
     }
 
     locks.into_iter().map(|(_, v)| v).collect()
 }
 
 fn lock_check(
+    board: &Board,
     piece: FallingPiece,
     locks: &mut HashMap<FallingPiece, Placement>,
     moves: InputList
 ) {
-    if piece.cells().iter().all(|&(_, y)| y >= 20) {
+    if piece.cells().iter().all(|&(_, y)| y >= board.height() as i32) {
         return
     }
 
     // Since the first path to a location is always the shortest path to that location,
     // we know that if there is already an entry here this isn't a faster path, so only
-    // insert placement if there isn't one there already.
+    // insert placement if there isn't one there already. The key includes the spin status, so
+    // spins and non-spins that end up in the same cells are both kept.
     locks.entry(piece.canonical()).or_insert(Placement {
         inputs: moves,
         location: piece,
@@ -192,12 +213,13 @@ fn attempt(
     checked: &mut HashSet<FallingPiece>,
     check_queue: &mut BinaryHeap<Placement>,
     mode: MovementMode,
+    rotation_system: &RotationSystem,
     fast_mode: bool,
     input: PieceMovement,
     repeat: bool
 ) -> FallingPiece {
     let orig_y = piece.y;
-    if input.apply(&mut piece, board) {
+    if input.apply(&mut piece, board, rotation_system) {
         let mut moves = moves.clone();
         if input == PieceMovement::SonicDrop {
             // We don't actually know the soft drop speed, but 1 cell every 2 ticks is probably a
@@ -214,7 +236,8 @@ fn attempt(
             }
         }
         moves.movements.push(input);
-        while repeat && !moves.movements.is_full() && input.apply(&mut piece, board) {
+        while repeat && !moves.movements.is_full() &&
+                input.apply(&mut piece, board, rotation_system) {
             // This is the DAS left/right case
             moves.movements.push(input);
             moves.time += 2;
@@ -239,10 +262,38 @@ fn attempt(
     piece
 }
 
-fn zero_g_starts(p: Piece) -> Vec<(FallingPiece, InputList)> {
+fn zero_g_starts(p: Piece, use_180: bool) -> Vec<(FallingPiece, InputList)> {
     use Piece::*;
     use RotationState::*;
     use PieceMovement::*;
+    if use_180 {
+        // Flipping is always at least as fast as two rotations, so replace the south starts.
+        let mut starts = zero_g_starts(p, false);
+        starts.retain(|(piece, _)| piece.kind.1 != South);
+        match p {
+            O => {}
+            I => starts.extend(vec![
+                start(I, South, 2, &[Left, Flip, Left, Left], 5),
+                start(I, South, 3, &[Left, Flip, Left], 3),
+                start(I, South, 4, &[Left, Flip], 2),
+                start(I, South, 5, &[Flip], 1),
+                start(I, South, 6, &[Right, Flip], 2),
+                start(I, South, 7, &[Right, Flip, Right], 3),
+                start(I, South, 8, &[Right, Flip, Right, Right], 5),
+            ]),
+            _ => starts.extend(vec![
+                start(p, South, 1, &[Left, Flip, Left, Left], 5),
+                start(p, South, 2, &[Left, Flip, Left], 3),
+                start(p, South, 3, &[Left, Flip], 2),
+                start(p, South, 4, &[Flip], 1),
+                start(p, South, 5, &[Right, Flip], 2),
+                start(p, South, 6, &[Right, Flip, Right], 3),
+                start(p, South, 7, &[Right, Flip, Right, Right], 5),
+                start(p, South, 8, &[Right, Flip, Right, Right, Right], 7),
+            ])
+        }
+        return starts
+    }
     match p {
         O => vec![
             start(O, North, 0, &[Left, Left, Left, Left], 7),
@@ -2,7 +2,7 @@ use enumset::{ EnumSet, EnumSetType, enum_set };
 use enum_map::Enum;
 use serde::{ Serialize, Deserialize };
 
-use crate::{ Board, Row };
+use crate::{ Board, Row, RotationSystem };
 
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct FallingPiece {
@@ -72,36 +72,44 @@ impl FallingPiece {
         }
     }
 
-    fn rotate<R: Row>(&mut self, target: PieceState, board: &Board<R>) -> bool {
+    /// Whether the piece is stuck, unable to move left, right or up.
+    pub fn immobile<R: Row>(&self, board: &Board<R>) -> bool {
+        [(-1, 0), (1, 0), (0, 1)].iter().all(|&(dx, dy)| board.obstructed(&FallingPiece {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }))
+    }
+
+    fn rotate<R: Row>(
+        &mut self, target: RotationState, board: &Board<R>, rotation_system: &RotationSystem
+    ) -> bool {
         let initial = *self;
-        self.kind = target;
-        let initial_offsets = initial.kind.rotation_points();
-        let target_offsets = target.rotation_points();
-        let kicks = initial_offsets.iter()
-            .zip(target_offsets.iter())
-            .map(|(&(x1, y1), &(x2, y2))| (x1 - x2, y1 - y2));
-
-        for (i, (dx, dy)) in kicks.enumerate() {
+        self.kind.1 = target;
+        let kicks = rotation_system.kicks(initial.kind.0, initial.kind.1, target);
+
+        for (i, &(dx, dy)) in kicks.iter().enumerate() {
             self.x = initial.x + dx;
             self.y = initial.y + dy;
             if !board.obstructed(self) {
-                if target.0 == Piece::T {
+                if self.kind.0 == Piece::T {
                     let mut mini_corners = 0;
-                    for &(dx, dy) in &target.1.mini_tspin_corners() {
+                    for &(dx, dy) in &target.mini_tspin_corners() {
                         if board.occupied(self.x + dx, self.y + dy) {
                             mini_corners += 1;
                         }
                     }
 
                     let mut non_mini_corners = 0;
-                    for &(dx, dy) in &target.1.non_mini_tspin_corners() {
+                    for &(dx, dy) in &target.non_mini_tspin_corners() {
                         if board.occupied(self.x + dx, self.y + dy) {
                             non_mini_corners += 1;
                         }
                     }
 
                     if non_mini_corners + mini_corners >= 3 {
-                        if i == 4 || mini_corners == 2 {
+                        let tst = rotation_system.forces_full_tspin(initial.kind.1, target, i);
+                        if tst || mini_corners == 2 {
                             self.tspin = TspinStatus::Full;
                         } else {
                             self.tspin = TspinStatus::Mini;
@@ -109,25 +117,38 @@ impl FallingPiece {
                     } else {
                         self.tspin = TspinStatus::None;
                     }
+                } else if self.kind.0 != Piece::O && self.immobile(board) {
+                    self.tspin = TspinStatus::Immobile;
+                } else {
+                    self.tspin = TspinStatus::None;
                 }
                 return true
             }
+            if i == 0 && !rotation_system.can_kick(board, self) {
+                break
+            }
         }
         
         *self = initial;
         false
     }
 
-    pub fn cw<R: Row>(&mut self, board: &Board<R>) -> bool {
-        let mut target = self.kind;
+    pub fn cw<R: Row>(&mut self, board: &Board<R>, rotation_system: &RotationSystem) -> bool {
+        let mut target = self.kind.1;
         target.cw();
-        self.rotate(target, board)
+        self.rotate(target, board, rotation_system)
     }
 
-    pub fn ccw<R: Row>(&mut self, board: &Board<R>) -> bool {
-        let mut target = self.kind;
+    pub fn ccw<R: Row>(&mut self, board: &Board<R>, rotation_system: &RotationSystem) -> bool {
+        let mut target = self.kind.1;
         target.ccw();
-        self.rotate(target, board)
+        self.rotate(target, board, rotation_system)
+    }
+
+    pub fn flip<R: Row>(&mut self, board: &Board<R>, rotation_system: &RotationSystem) -> bool {
+        let mut target = self.kind.1;
+        target.flip();
+        self.rotate(target, board, rotation_system)
     }
 
     pub fn same_location(&self, other: &Self) -> bool {
@@ -195,7 +216,7 @@ impl FallingPiece {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum CellColor {
     I, O, T, L, J, S, Z,
     Garbage,
@@ -221,6 +242,8 @@ pub enum TspinStatus {
     None,
     Mini,
     Full,
+    /// A piece other than T rotated into a spot it can't move out of.
+    Immobile,
 }
 
 impl RotationState {
@@ -244,6 +267,16 @@ impl RotationState {
         }
     }
 
+    pub fn flip(&mut self) {
+        use RotationState::*;
+        match self {
+            North => *self = South,
+            East  => *self = West,
+            South => *self = North,
+            West  => *self = East
+        }
+    }
+
     pub fn mini_tspin_corners(self) -> [(i32, i32); 2] {
         use RotationState::*;
         match self {
@@ -274,6 +307,10 @@ impl PieceState {
         self.1.ccw()
     }
 
+    pub fn flip(&mut self) {
+        self.1.flip()
+    }
+
     /// Returns the cells this piece and orientation occupy relative to rotation point 1, as well
     /// as the connection directions, in no particular order.
     #[inline(always)]
@@ -424,16 +461,21 @@ pub enum PieceMovement {
     Right,
     Cw,
     Ccw,
+    /// 180 degree rotation
+    Flip,
     SonicDrop
 }
 
 impl PieceMovement {
-    pub fn apply(self, piece: &mut FallingPiece, board: &Board) -> bool {
+    pub fn apply(
+        self, piece: &mut FallingPiece, board: &Board, rotation_system: &RotationSystem
+    ) -> bool {
         match self {
             PieceMovement::Left => piece.shift(board, -1, 0),
             PieceMovement::Right => piece.shift(board, 1, 0),
-            PieceMovement::Ccw => piece.ccw(board),
-            PieceMovement::Cw => piece.cw(board),
+            PieceMovement::Ccw => piece.ccw(board, rotation_system),
+            PieceMovement::Cw => piece.cw(board, rotation_system),
+            PieceMovement::Flip => piece.flip(board, rotation_system),
             PieceMovement::SonicDrop => piece.sonic_drop(board)
         }
     }
@@ -485,7 +527,7 @@ impl SpawnRule {
             SpawnRule::Row19Or20 => {
                 let mut spawned = FallingPiece {
                     kind: PieceState(piece, RotationState::North),
-                    x: 4, y: 19,
+                    x: spawn_x(board), y: board.height() as i32 - 1,
                     tspin: TspinStatus::None
                 };
                 if !board.obstructed(&spawned) {
@@ -499,7 +541,7 @@ impl SpawnRule {
             SpawnRule::Row21AndFall => {
                 let mut spawned = FallingPiece {
                     kind: PieceState(piece, RotationState::North),
-                    x: 4, y: 21,
+                    x: spawn_x(board), y: board.height() as i32 + 1,
                     tspin: TspinStatus::None
                 };
                 if !board.obstructed(&spawned) {
@@ -510,4 +552,9 @@ impl SpawnRule {
         }
         None
     }
+}
+
+/// Pieces spawn in the middle of the board, rounding to the left.
+fn spawn_x<R: Row>(board: &Board<R>) -> i32 {
+    (board.width() as i32 - 1) / 2
 }
\ No newline at end of file
@@ -0,0 +1,334 @@
+use arrayvec::ArrayVec;
+use enumset::EnumSet;
+use enum_map::EnumMap;
+use rand::prelude::*;
+use serde::{ Serialize, Deserialize };
+use std::convert::TryFrom;
+
+use crate::Piece;
+
+/// Decides which pieces are added to the next queue.
+///
+/// The state of a randomizer only depends on the pieces it has produced so far, so a bot that sees
+/// the same pieces can keep an identical copy and know the real chance of each piece coming next.
+pub trait Randomizer {
+    /// The chance of each piece being the next one. The chances add up to 1.
+    fn distribution(&self) -> EnumMap<Piece, f64>;
+
+    /// Updates the state after `piece` was added to the queue.
+    fn observe(&mut self, piece: Piece);
+
+    /// Randomly picks the next piece without updating the state.
+    fn generate(&self, rng: &mut dyn RngCore) -> Piece {
+        let distribution = self.distribution();
+        let pieces: ArrayVec<[_; 7]> = distribution.iter().collect();
+        pieces.choose_weighted(rng, |&(_, &chance)| chance).unwrap().0
+    }
+
+    /// The pieces that can be the next one.
+    fn possible_pieces(&self) -> EnumSet<Piece> {
+        self.distribution().iter().filter(|&(_, &chance)| chance > 0.0).map(|(p, _)| p).collect()
+    }
+}
+
+/// Any of the built-in randomizers. This is what boards and game configurations store.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AnyRandomizer {
+    SevenBag(SevenBag),
+    FourteenBag(FourteenBag),
+    Memoryless(Memoryless),
+    Tgm(TgmHistory),
+    SevenPlusOne(SevenPlusOne),
+    Scripted(Scripted)
+}
+
+/// Deals out every piece once in a random order, then starts over.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SevenBag {
+    /// The pieces left in the current bag. Never empty.
+    pub remaining: EnumSet<Piece>
+}
+
+/// Deals out every piece twice in a random order, then starts over.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FourteenBag {
+    /// The pieces dealt at least once from the current bag.
+    pub once: EnumSet<Piece>,
+    /// The pieces dealt twice from the current bag.
+    pub twice: EnumSet<Piece>
+}
+
+/// Every piece is equally likely every time.
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Memoryless;
+
+/// The TGM history randomizer. A piece is rerolled if it is one of the last four pieces, up to
+/// `rolls` times in total. The first piece is never S, Z or O.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TgmHistory {
+    /// The last four pieces, oldest first.
+    pub history: [Piece; 4],
+    /// 4 in TGM, 6 in TGM2.
+    pub rolls: u32,
+    pub first: bool
+}
+
+/// Deals out every piece once plus one random extra piece in a random order, then starts over.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SevenPlusOne {
+    /// The pieces dealt from the current bag.
+    pub dealt: EnumSet<Piece>,
+    /// The extra piece, once it has been dealt for the second time.
+    pub extra: Option<Piece>
+}
+
+/// Deals out a fixed sequence of pieces, repeating it forever. Meant for puzzles and tests.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ScriptedData")]
+pub struct Scripted {
+    pieces: Vec<Piece>,
+    position: usize
+}
+
+#[derive(Deserialize)]
+struct ScriptedData {
+    pieces: Vec<Piece>,
+    #[serde(default)]
+    position: usize
+}
+
+impl Default for AnyRandomizer {
+    fn default() -> Self {
+        AnyRandomizer::SevenBag(SevenBag::default())
+    }
+}
+
+impl Default for SevenBag {
+    fn default() -> Self {
+        SevenBag { remaining: EnumSet::all() }
+    }
+}
+
+impl Default for TgmHistory {
+    fn default() -> Self {
+        TgmHistory {
+            history: [Piece::Z, Piece::S, Piece::S, Piece::Z],
+            rolls: 6,
+            first: true
+        }
+    }
+}
+
+impl Scripted {
+    /// Creates a randomizer that deals out `pieces` in order, or `None` if there are no pieces.
+    pub fn new(pieces: Vec<Piece>) -> Option<Self> {
+        if pieces.is_empty() {
+            None
+        } else {
+            Some(Scripted { pieces, position: 0 })
+        }
+    }
+
+    /// The sequence of pieces. Never empty.
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    /// The index of the next piece in `pieces`.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl TryFrom<ScriptedData> for Scripted {
+    type Error = &'static str;
+
+    fn try_from(data: ScriptedData) -> Result<Self, Self::Error> {
+        let mut scripted = Scripted::new(data.pieces).ok_or("scripted randomizer has no pieces")?;
+        scripted.position = data.position % scripted.pieces.len();
+        Ok(scripted)
+    }
+}
+
+impl AnyRandomizer {
+    fn get(&self) -> &dyn Randomizer {
+        match self {
+            AnyRandomizer::SevenBag(r) => r,
+            AnyRandomizer::FourteenBag(r) => r,
+            AnyRandomizer::Memoryless(r) => r,
+            AnyRandomizer::Tgm(r) => r,
+            AnyRandomizer::SevenPlusOne(r) => r,
+            AnyRandomizer::Scripted(r) => r
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut dyn Randomizer {
+        match self {
+            AnyRandomizer::SevenBag(r) => r,
+            AnyRandomizer::FourteenBag(r) => r,
+            AnyRandomizer::Memoryless(r) => r,
+            AnyRandomizer::Tgm(r) => r,
+            AnyRandomizer::SevenPlusOne(r) => r,
+            AnyRandomizer::Scripted(r) => r
+        }
+    }
+}
+
+impl Randomizer for AnyRandomizer {
+    fn distribution(&self) -> EnumMap<Piece, f64> {
+        self.get().distribution()
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        self.get_mut().observe(piece)
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Piece {
+        self.get().generate(rng)
+    }
+
+    fn possible_pieces(&self) -> EnumSet<Piece> {
+        self.get().possible_pieces()
+    }
+}
+
+impl Randomizer for SevenBag {
+    fn distribution(&self) -> EnumMap<Piece, f64> {
+        proportional(|p| self.remaining.contains(p) as u32)
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        self.remaining.remove(piece);
+        if self.remaining.is_empty() {
+            self.remaining = EnumSet::all();
+        }
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Piece {
+        // This is how pieces were picked before there were other randomizers, which old replays
+        // depend on.
+        let choices: ArrayVec<[_; 7]> = self.remaining.iter().collect();
+        *choices.choose(rng).unwrap()
+    }
+
+    fn possible_pieces(&self) -> EnumSet<Piece> {
+        self.remaining
+    }
+}
+
+impl Randomizer for FourteenBag {
+    fn distribution(&self) -> EnumMap<Piece, f64> {
+        proportional(|p| 2 - self.once.contains(p) as u32 - self.twice.contains(p) as u32)
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        if self.once.contains(piece) {
+            self.twice.insert(piece);
+        } else {
+            self.once.insert(piece);
+        }
+        if self.twice == EnumSet::all() {
+            *self = FourteenBag::default();
+        }
+    }
+}
+
+impl Randomizer for Memoryless {
+    fn distribution(&self) -> EnumMap<Piece, f64> {
+        proportional(|_| 1)
+    }
+
+    fn observe(&mut self, _: Piece) {}
+}
+
+impl Randomizer for TgmHistory {
+    fn distribution(&self) -> EnumMap<Piece, f64> {
+        if self.first {
+            return proportional(|p| !matches!(p, Piece::S | Piece::Z | Piece::O) as u32);
+        }
+        // Every roll but the last is rerolled if it lands in the history, and the last roll is
+        // kept no matter what.
+        let history: EnumSet<Piece> = self.history.iter().copied().collect();
+        let in_history = history.len() as f64 / 7.0;
+        let all_rerolled = in_history.powi(self.rolls.max(1) as i32 - 1);
+        let mut distribution = EnumMap::default();
+        for (piece, chance) in &mut distribution {
+            *chance = all_rerolled / 7.0;
+            if !history.contains(piece) {
+                *chance += (1.0 - all_rerolled) / (7 - history.len()) as f64;
+            }
+        }
+        distribution
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+        self.first = false;
+    }
+}
+
+impl Randomizer for SevenPlusOne {
+    fn distribution(&self) -> EnumMap<Piece, f64> {
+        let dealt = self.dealt.len() as f64;
+        match self.extra {
+            // Only the pieces that haven't been dealt yet are left.
+            Some(_) => proportional(|p| !self.dealt.contains(p) as u32),
+            None => {
+                // Each piece that has been dealt once is twice as likely to be the extra piece
+                // as a piece that hasn't been dealt, since it could have been either copy.
+                let weight_sum = 7.0 + dealt;
+                let mut distribution = EnumMap::default();
+                for (piece, chance) in &mut distribution {
+                    *chance = if self.dealt.contains(piece) {
+                        2.0 / weight_sum / (8.0 - dealt)
+                    } else {
+                        (1.0 + 1.0 / weight_sum) / (8.0 - dealt)
+                    };
+                }
+                distribution
+            }
+        }
+    }
+
+    fn observe(&mut self, piece: Piece) {
+        if self.dealt.contains(piece) {
+            self.extra = Some(piece);
+        } else {
+            self.dealt.insert(piece);
+        }
+        if self.dealt == EnumSet::all() && self.extra.is_some() {
+            *self = SevenPlusOne::default();
+        }
+    }
+}
+
+impl Randomizer for Scripted {
+    fn distribution(&self) -> EnumMap<Piece, f64> {
+        let next = self.pieces[self.position];
+        proportional(|p| (p == next) as u32)
+    }
+
+    fn observe(&mut self, _: Piece) {
+        self.position = (self.position + 1) % self.pieces.len();
+    }
+}
+
+/// Gives each piece a chance proportional to its weight.
+fn proportional(weight: impl Fn(Piece) -> u32) -> EnumMap<Piece, f64> {
+    let mut distribution = EnumMap::default();
+    let mut total = 0;
+    for (piece, chance) in &mut distribution {
+        let w = weight(piece);
+        total += w;
+        *chance = w as f64;
+    }
+    for (_, chance) in &mut distribution {
+        *chance /= total as f64;
+    }
+    distribution
+}
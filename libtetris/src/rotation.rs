@@ -0,0 +1,211 @@
+use arrayvec::ArrayVec;
+use serde::{ Serialize, Deserialize };
+use std::sync::Arc;
+
+use crate::{ Board, Row, FallingPiece, Piece, PieceState, RotationState };
+
+/// The maximum number of kicks a single rotation can attempt, including the unkicked rotation.
+pub const MAX_KICKS: usize = 8;
+
+pub type Kicks = ArrayVec<[(i32, i32); MAX_KICKS]>;
+
+/// The rules used to rotate pieces.
+///
+/// Every rotation system uses the same piece shapes as `PieceState::cells`. They differ in where
+/// the piece ends up after rotating in open space and in the list of kicks tried when that
+/// location is obstructed. Systems without 180 degree kicks rotate 180 degrees in place.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RotationSystem {
+    /// The Super Rotation System used by guideline games.
+    #[default]
+    Srs,
+    /// SRS with the symmetric I piece kicks and the 180 degree kicks used by TETR.IO.
+    SrsPlus,
+    /// Arika Rotation System. Pieces rest against the bottom of their bounding box, kick one cell
+    /// right and then one cell left, and the I piece never kicks. L, J and T pieces can't kick
+    /// when the center column is blocked.
+    Ars,
+    /// SRS rotation in place with no kicks.
+    NoKicks,
+    /// A user-provided kick table, usually loaded from a data file.
+    Custom(Arc<KickTable>)
+}
+
+/// A custom kick table.
+///
+/// Kick offsets are relative to where the piece ends up after an in-place SRS rotation, which is
+/// how kick tables are usually published. The first offset is normally `(0, 0)`. Rotations which
+/// aren't listed in the table cannot be performed.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KickTable {
+    pub entries: Vec<KickTableEntry>,
+    /// The index of the kick that always turns a T-spin mini into a full T-spin, if any.
+    #[serde(default)]
+    pub tst_kick: Option<usize>
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KickTableEntry {
+    pub pieces: Vec<Piece>,
+    pub from: RotationState,
+    pub to: RotationState,
+    pub kicks: Vec<(i32, i32)>
+}
+
+impl RotationSystem {
+    /// Returns the offsets to try, in order, when rotating the piece from `from` to `to`.
+    ///
+    /// The offsets are applied directly to the position of the `FallingPiece`.
+    pub fn kicks(&self, piece: Piece, from: RotationState, to: RotationState) -> Kicks {
+        let base = srs_in_place(piece, from, to);
+        let flip = is_flip(from, to);
+        match self {
+            RotationSystem::Srs if flip => offset_all(base, &[(0, 0)]),
+            RotationSystem::Srs => srs_kicks(piece, from, to),
+            RotationSystem::SrsPlus if flip => offset_all(base, srs_plus_180_kicks(from, to)),
+            RotationSystem::SrsPlus => match piece {
+                Piece::I => offset_all(base, srs_plus_i_kicks(from, to)),
+                _ => srs_kicks(piece, from, to)
+            },
+            RotationSystem::Ars => {
+                let (fx, fy) = ars_offset(piece, from);
+                let (tx, ty) = ars_offset(piece, to);
+                let base = (base.0 + tx - fx, base.1 + ty - fy);
+                match piece {
+                    Piece::I | Piece::O => offset_all(base, &[(0, 0)]),
+                    _ => offset_all(base, &[(0, 0), (1, 0), (-1, 0)])
+                }
+            }
+            RotationSystem::NoKicks => offset_all(base, &[(0, 0)]),
+            RotationSystem::Custom(table) => table.entries.iter()
+                .find(|e| e.from == from && e.to == to && e.pieces.contains(&piece))
+                .map_or(Kicks::new(), |e| offset_all(base, &e.kicks))
+        }
+    }
+
+    /// Whether a T piece that rotated from `from` to `to` using the kick at index `kick` is always
+    /// a full T-spin.
+    pub fn forces_full_tspin(&self, from: RotationState, to: RotationState, kick: usize) -> bool {
+        match self {
+            RotationSystem::Srs | RotationSystem::SrsPlus => kick == 4 && !is_flip(from, to),
+            RotationSystem::Ars | RotationSystem::NoKicks => false,
+            RotationSystem::Custom(table) => table.tst_kick == Some(kick)
+        }
+    }
+
+    /// Whether rotating in open space leaves the rotation point of every piece where it was.
+    ///
+    /// Movement code can use precomputed paths when this is true.
+    pub fn rotates_in_place(&self) -> bool {
+        match self {
+            RotationSystem::Srs | RotationSystem::SrsPlus | RotationSystem::NoKicks => true,
+            RotationSystem::Ars => false,
+            RotationSystem::Custom(table) => table.entries.iter()
+                .all(|e| e.kicks.first().is_none_or(|&k| k == (0, 0)))
+        }
+    }
+
+    /// Checks rules that prevent a rotation from kicking at all.
+    ///
+    /// `target` is the location of the piece after the unkicked rotation.
+    pub(crate) fn can_kick<R: Row>(&self, board: &Board<R>, target: &FallingPiece) -> bool {
+        match self {
+            RotationSystem::Ars => match target.kind.0 {
+                Piece::T | Piece::L | Piece::J => !ars_center_column_blocked(board, target),
+                _ => true
+            },
+            _ => true
+        }
+    }
+}
+
+fn offset_all((bx, by): (i32, i32), kicks: &[(i32, i32)]) -> Kicks {
+    kicks.iter().take(MAX_KICKS).map(|&(x, y)| (bx + x, by + y)).collect()
+}
+
+fn srs_kicks(piece: Piece, from: RotationState, to: RotationState) -> Kicks {
+    let from = PieceState(piece, from).rotation_points();
+    let to = PieceState(piece, to).rotation_points();
+    from.iter()
+        .zip(to.iter())
+        .map(|(&(x1, y1), &(x2, y2))| (x1 - x2, y1 - y2))
+        .collect()
+}
+
+/// The movement of the rotation point when the piece rotates in open space under SRS.
+fn srs_in_place(piece: Piece, from: RotationState, to: RotationState) -> (i32, i32) {
+    let (x1, y1) = PieceState(piece, from).rotation_points()[0];
+    let (x2, y2) = PieceState(piece, to).rotation_points()[0];
+    (x1 - x2, y1 - y2)
+}
+
+fn is_flip(from: RotationState, to: RotationState) -> bool {
+    let mut opposite = from;
+    opposite.flip();
+    to == opposite
+}
+
+fn srs_plus_i_kicks(from: RotationState, to: RotationState) -> &'static [(i32, i32)] {
+    use RotationState::*;
+    match (from, to) {
+        (North, East)  => &[(0, 0), ( 1, 0), (-2, 0), (-2, -1), ( 1,  2)],
+        (East, North)  => &[(0, 0), (-1, 0), ( 2, 0), (-1, -2), ( 2,  1)],
+        (East, South)  => &[(0, 0), (-1, 0), ( 2, 0), (-1,  2), ( 2, -1)],
+        (South, East)  => &[(0, 0), (-2, 0), ( 1, 0), (-2,  1), ( 1, -2)],
+        (South, West)  => &[(0, 0), ( 2, 0), (-1, 0), ( 2,  1), (-1, -2)],
+        (West, South)  => &[(0, 0), ( 1, 0), (-2, 0), ( 1, -2), (-2,  1)],
+        (West, North)  => &[(0, 0), ( 1, 0), (-2, 0), ( 1,  2), (-2, -1)],
+        (North, West)  => &[(0, 0), (-1, 0), ( 2, 0), (-1,  2), ( 2, -1)],
+        _ => &[(0, 0)]
+    }
+}
+
+fn srs_plus_180_kicks(from: RotationState, to: RotationState) -> &'static [(i32, i32)] {
+    use RotationState::*;
+    match (from, to) {
+        (North, South) => &[(0, 0), ( 0,  1), ( 1,  1), (-1,  1), ( 1, 0), (-1, 0)],
+        (South, North) => &[(0, 0), ( 0, -1), (-1, -1), ( 1, -1), (-1, 0), ( 1, 0)],
+        (East, West)   => &[(0, 0), ( 1,  0), ( 1,  2), ( 1,  1), ( 0, 2), ( 0, 1)],
+        (West, East)   => &[(0, 0), (-1,  0), (-1,  2), (-1,  1), ( 0, 2), ( 0, 1)],
+        _ => &[(0, 0)]
+    }
+}
+
+/// Where ARS places each orientation relative to where SRS would place it.
+fn ars_offset(piece: Piece, state: RotationState) -> (i32, i32) {
+    use Piece::*;
+    use RotationState::*;
+    match (piece, state) {
+        (O, _) => (0, 0),
+
+        (I, South) => (0, 1),
+        (I, West) => (1, 0),
+        (I, _) => (0, 0),
+
+        (S, East) => (-1, 0),
+        (Z, West) => (1, 0),
+
+        (_, North) => (0, -1),
+        (_, _) => (0, 0)
+    }
+}
+
+/// Implements the ARS center column rule.
+///
+/// The cells of the 3x3 rotation box covered by the rotated piece are examined in reading order.
+/// If the first obstructed cell is in the center column, the piece isn't allowed to kick.
+fn ars_center_column_blocked<R: Row>(board: &Board<R>, target: &FallingPiece) -> bool {
+    let (ox, oy) = ars_offset(target.kind.0, target.kind.1);
+    let cx = target.x - ox;
+    let cy = target.y - oy;
+    let cells = target.cells();
+    for dy in [1, 0, -1].iter() {
+        for dx in [-1, 0, 1].iter() {
+            let (x, y) = (cx + dx, cy + dy);
+            if cells.contains(&(x, y)) && board.occupied(x, y) {
+                return *dx == 0;
+            }
+        }
+    }
+    false
+}
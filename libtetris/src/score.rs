@@ -0,0 +1,127 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::{ LockResult, PlacementKind };
+
+/// Describes how many points placements and drops are worth in single player modes.
+///
+/// The default table is guideline scoring. Guideline has no spins for pieces other than T, so
+/// those follow the T-spin values.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreTable {
+    /// Points for clearing 0 to 4 lines without a spin.
+    pub clears: [u32; 5],
+    /// Points for T-spins clearing 0 to 3 lines.
+    pub tspins: [u32; 4],
+    /// Points for mini T-spins clearing 0 to 2 lines.
+    pub mini_tspins: [u32; 3],
+    /// Points for spins with other pieces clearing 0 to 4 lines when all spins are `Full`.
+    pub spins: [u32; 5],
+    /// Points for spins with other pieces clearing 0 to 4 lines when all spins are `Mini`.
+    pub mini_spins: [u32; 5],
+    /// The multiplier for back-to-back clears, in percent.
+    pub b2b_multiplier: u32,
+    /// Points for each consecutive line clear before this one.
+    pub combo: u32,
+    /// Bonus points for perfect clears clearing 1 to 4 lines. The first entry is unused.
+    pub perfect_clears: [u32; 5],
+    /// Points for each cell a piece is soft dropped.
+    pub soft_drop: u32,
+    /// Points for each cell a piece is hard dropped.
+    pub hard_drop: u32,
+    /// The number of lines to clear to go up a level. Placement points are multiplied by the
+    /// level. Zero keeps the level at 1.
+    pub lines_per_level: u32
+}
+
+impl Default for ScoreTable {
+    fn default() -> Self {
+        ScoreTable {
+            clears: [0, 100, 300, 500, 800],
+            tspins: [400, 800, 1200, 1600],
+            mini_tspins: [100, 200, 400],
+            spins: [400, 800, 1200, 1600, 2000],
+            mini_spins: [100, 200, 400, 600, 800],
+            b2b_multiplier: 150,
+            combo: 50,
+            perfect_clears: [0, 800, 1200, 1800, 2000],
+            soft_drop: 1,
+            hard_drop: 2,
+            lines_per_level: 10
+        }
+    }
+}
+
+impl ScoreTable {
+    /// The points for a placement before the back-to-back, combo and perfect clear bonuses.
+    pub fn base(&self, kind: PlacementKind) -> u32 {
+        use PlacementKind::*;
+        match kind {
+            None => self.clears[0],
+            Clear1 => self.clears[1],
+            Clear2 => self.clears[2],
+            Clear3 => self.clears[3],
+            Clear4 => self.clears[4],
+            MiniTspin => self.mini_tspins[0],
+            MiniTspin1 => self.mini_tspins[1],
+            MiniTspin2 => self.mini_tspins[2],
+            Tspin => self.tspins[0],
+            Tspin1 => self.tspins[1],
+            Tspin2 => self.tspins[2],
+            Tspin3 => self.tspins[3],
+            MiniSpin => self.mini_spins[0],
+            MiniSpin1 => self.mini_spins[1],
+            MiniSpin2 => self.mini_spins[2],
+            MiniSpin3 => self.mini_spins[3],
+            MiniSpin4 => self.mini_spins[4],
+            Spin => self.spins[0],
+            Spin1 => self.spins[1],
+            Spin2 => self.spins[2],
+            Spin3 => self.spins[3],
+            Spin4 => self.spins[4]
+        }
+    }
+
+    /// The points for a placement at the given level, not counting drops.
+    pub fn placement(&self, lock: &LockResult, level: u32) -> u32 {
+        let mut points = self.base(lock.placement_kind);
+        if lock.b2b {
+            points = points * self.b2b_multiplier / 100;
+        }
+        if let Some(combo) = lock.combo {
+            points += combo * self.combo;
+        }
+        if lock.perfect_clear {
+            points += self.perfect_clears[lock.cleared_lines.len().min(4)];
+        }
+        points * level
+    }
+}
+
+/// The score of a single player game.
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub points: u64,
+    pub lines: u32
+}
+
+impl Score {
+    pub fn level(&self, table: &ScoreTable) -> u32 {
+        match table.lines_per_level {
+            0 => 1,
+            n => 1 + self.lines / n
+        }
+    }
+
+    /// Adds the points for a placement. The lines it cleared count towards the next level.
+    pub fn placed(&mut self, table: &ScoreTable, lock: &LockResult) {
+        self.points += table.placement(lock, self.level(table)) as u64;
+        self.lines += lock.cleared_lines.len() as u32;
+    }
+
+    /// Adds the points for dropping a piece by `cells` cells.
+    pub fn dropped(&mut self, table: &ScoreTable, cells: u32, hard: bool) {
+        let per_cell = if hard { table.hard_drop } else { table.soft_drop };
+        self.points += (cells * per_cell) as u64;
+    }
+}
@@ -0,0 +1,62 @@
+#![allow(dead_code)]
+
+use libtetris::*;
+
+/// The TST slot: the T slides under the overhang and kicks down two rows. Hard drops can't get
+/// under the overhang.
+pub const TST: &str = "v115@zgB8HeA8IeA8AeI8BeH8AeH8JetJJ";
+/// `TST`, mirrored.
+pub const TST_MIRRORED: &str = "v115@7gB8IeI8AeH8BeI8AeA8JedNJ";
+
+/// One page of a fumen: the field as a 10x20 board and the piece placed on it, if any.
+pub struct Page {
+    pub board: Board,
+    pub piece: Option<FallingPiece>
+}
+
+/// Decodes fumen data, panicking if it is invalid.
+pub fn decode(data: &str) -> Vec<Page> {
+    let fumen = fumen::Fumen::decode(data).unwrap_or_else(|_| panic!("invalid fumen {}", data));
+    fumen.pages.iter().map(|page| Page {
+        board: Board::from_fumen_page(page),
+        piece: page.piece.map(FallingPiece::from)
+    }).collect()
+}
+
+/// Finds every placement of `piece` on `board` from the normal spawn location.
+pub fn placements(
+    board: &Board, piece: Piece, mode: MovementMode, rotation_system: &RotationSystem
+) -> Vec<Placement> {
+    let spawned = SpawnRule::Row19Or20.spawn(piece, board).expect("spawn position is blocked");
+    find_moves(board, spawned, mode, rotation_system, false)
+}
+
+/// The ways `target` can be reached, which can differ in their spin status.
+pub fn reachable(
+    board: &Board, target: &FallingPiece, mode: MovementMode, rotation_system: &RotationSystem
+) -> Vec<FallingPiece> {
+    placements(board, target.kind.0, mode, rotation_system).into_iter()
+        .map(|p| p.location)
+        .filter(|p| p.same_location(target))
+        .collect()
+}
+
+/// Checks that the placement on every page is reachable at 0G with SRS, that locking it is
+/// `expected`, and that the resulting field matches the next page.
+pub fn check_sequence(data: &str, expected: &[PlacementKind]) {
+    let pages = decode(data);
+    assert_eq!(pages.len(), expected.len(), "page count of {}", data);
+    let mut board = pages[0].board.clone();
+    for (i, (page, &kind)) in pages.iter().zip(expected).enumerate() {
+        assert_eq!(board.get_field(), page.board.get_field(), "field of page {}", i + 1);
+        let target = page.piece.unwrap_or_else(|| panic!("no piece on page {}", i + 1));
+        let found = reachable(&board, &target, MovementMode::ZeroG, &RotationSystem::Srs);
+        let location = found.iter()
+            .copied()
+            .find(|p| board.clone().lock_piece(*p, &AttackTable::default()).placement_kind == kind)
+            .unwrap_or_else(|| panic!(
+                "page {}: no path to {:?} gives {:?}, found {:?}", i + 1, target, kind, found
+            ));
+        board.lock_piece(location, &AttackTable::default());
+    }
+}
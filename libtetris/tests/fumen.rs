@@ -0,0 +1,72 @@
+//! Conversions between boards and fumen data survive an encode and decode.
+
+mod common;
+
+use libtetris::*;
+use common::*;
+
+fn piece(piece: Piece, rotation: RotationState, x: i32, y: i32) -> FallingPiece {
+    FallingPiece { kind: PieceState(piece, rotation), x, y, tspin: TspinStatus::None }
+}
+
+/// Three garbage rows, the bottom one with its hole in column 3 and the others in column 0.
+fn garbage_board() -> Board<ColoredRow> {
+    let mut board = Board::new();
+    board.add_garbage(0);
+    board.add_garbage(0);
+    board.add_garbage(3);
+    board
+}
+
+#[test]
+fn field_round_trip() {
+    let mut board = garbage_board();
+    board.lock_piece(piece(Piece::T, RotationState::North, 5, 3), &AttackTable::default());
+
+    let data = board.to_fumen().encode();
+    let fumen = fumen::Fumen::decode(&data).unwrap();
+    assert_eq!(fumen.pages.len(), 1);
+    let page = &fumen.pages[0];
+    assert_eq!(page.piece, None);
+    assert_eq!(page.garbage_row, [fumen::CellColor::Empty; 10]);
+    for (y, row) in page.field.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            assert_eq!(
+                CellColor::from(cell), board.get_row(y as i32).cell_color(x), "cell {},{}", x, y
+            );
+        }
+    }
+
+    let decoded = Board::<ColoredRow>::from_fumen_page(page);
+    assert_eq!(decoded.get_field(), board.get_field());
+    assert_eq!(decoded.height(), 20);
+}
+
+#[test]
+fn placements_round_trip() {
+    let mut board = garbage_board();
+    // The O is held on the real board; fumen only records where it went.
+    board.hold_piece = Some(Piece::O);
+    board.add_next_piece(Piece::I);
+    board.add_next_piece(Piece::T);
+    let placements = [
+        piece(Piece::O, RotationState::North, 7, 3),
+        // Fills the holes in column 0 and clears two garbage rows.
+        piece(Piece::I, RotationState::East, 0, 3),
+        piece(Piece::T, RotationState::South, 4, 2),
+    ];
+
+    let data = placements_to_fumen(&board, placements.iter().copied()).encode();
+    let pages = decode(&data);
+    assert_eq!(pages.len(), placements.len());
+
+    let mut expected = board.clone();
+    let mut cleared = 0;
+    for (i, (page, &placement)) in pages.iter().zip(&placements).enumerate() {
+        assert_eq!(page.board.get_field(), expected.get_field(), "field of page {}", i + 1);
+        let found = page.piece.unwrap_or_else(|| panic!("no piece on page {}", i + 1));
+        assert!(found.same_location(&placement), "page {}: {:?}", i + 1, found);
+        cleared += expected.lock_piece(placement, &AttackTable::default()).cleared_lines.len();
+    }
+    assert_eq!(cleared, 2);
+}
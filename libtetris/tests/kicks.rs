@@ -0,0 +1,61 @@
+//! Rotation and kick behaviour, using the fields of known spin setups.
+
+mod common;
+
+use libtetris::*;
+use common::*;
+
+fn t(rotation: RotationState, x: i32, y: i32) -> FallingPiece {
+    FallingPiece {
+        kind: PieceState(Piece::T, rotation),
+        x, y,
+        tspin: TspinStatus::None
+    }
+}
+
+#[test]
+fn tst_kick_cw() {
+    let page = &decode(TST)[0];
+    let mut piece = t(RotationState::North, 2, 3);
+    assert!(piece.cw(&page.board, &RotationSystem::Srs));
+    assert!(piece.same_location(&page.piece.unwrap()));
+    assert_eq!(piece.tspin, TspinStatus::Full);
+}
+
+#[test]
+fn tst_kick_ccw() {
+    let page = &decode(TST_MIRRORED)[0];
+    let mut piece = t(RotationState::North, 7, 3);
+    assert!(piece.ccw(&page.board, &RotationSystem::Srs));
+    assert!(piece.same_location(&page.piece.unwrap()));
+    assert_eq!(piece.tspin, TspinStatus::Full);
+}
+
+#[test]
+fn blocked_rotation_leaves_piece() {
+    let page = &decode(TST)[0];
+    let mut piece = t(RotationState::North, 2, 3);
+    assert!(!piece.cw(&page.board, &RotationSystem::NoKicks));
+    assert_eq!(piece, t(RotationState::North, 2, 3));
+}
+
+#[test]
+fn open_rotation_does_not_kick() {
+    let board = Board::<u16>::new();
+    for rs in &[RotationSystem::Srs, RotationSystem::SrsPlus, RotationSystem::NoKicks] {
+        let mut piece = t(RotationState::North, 4, 10);
+        assert!(piece.cw(&board, rs));
+        assert_eq!(piece, t(RotationState::East, 4, 10));
+        assert!(piece.ccw(&board, rs));
+        assert!(piece.ccw(&board, rs));
+        assert_eq!(piece, t(RotationState::West, 4, 10));
+    }
+}
+
+#[test]
+fn wall_kick() {
+    let board = Board::<u16>::new();
+    let mut piece = t(RotationState::East, 0, 10);
+    assert!(piece.ccw(&board, &RotationSystem::Srs));
+    assert_eq!(piece, t(RotationState::North, 1, 10));
+}
@@ -0,0 +1,71 @@
+//! Reachable placement sets from `find_moves`.
+
+mod common;
+
+use libtetris::*;
+use common::*;
+use std::collections::HashSet;
+
+fn cells(placements: &[Placement]) -> HashSet<[(i32, i32); 4]> {
+    placements.iter().map(|p| {
+        let mut cells = p.location.cells();
+        cells.sort();
+        cells
+    }).collect()
+}
+
+#[test]
+fn empty_board() {
+    let board = Board::<u16>::new();
+    let expected = [
+        (Piece::I, 17), (Piece::O, 9), (Piece::T, 34), (Piece::L, 34),
+        (Piece::J, 34), (Piece::S, 17), (Piece::Z, 17)
+    ];
+    for &(piece, count) in &expected {
+        for &mode in &[MovementMode::ZeroG, MovementMode::ZeroGComplete, MovementMode::HardDropOnly] {
+            let found = placements(&board, piece, mode, &RotationSystem::Srs);
+            assert_eq!(cells(&found).len(), count, "{:?} in {:?}", piece, mode);
+        }
+    }
+}
+
+#[test]
+fn placements_are_resting() {
+    let page = &decode(TST)[0];
+    for &piece in &[Piece::I, Piece::O, Piece::T, Piece::L, Piece::J, Piece::S, Piece::Z] {
+        for p in placements(&page.board, piece, MovementMode::ZeroGComplete, &RotationSystem::Srs) {
+            assert!(!page.board.obstructed(&p.location), "{:?} is obstructed", p.location);
+            assert!(page.board.on_stack(&p.location), "{:?} is floating", p.location);
+        }
+    }
+}
+
+#[test]
+fn hard_drops_are_a_subset() {
+    let page = &decode(TST)[0];
+    for &piece in &[Piece::I, Piece::O, Piece::T, Piece::L, Piece::J, Piece::S, Piece::Z] {
+        let all = cells(&placements(
+            &page.board, piece, MovementMode::ZeroGComplete, &RotationSystem::Srs
+        ));
+        let zero_g = cells(&placements(&page.board, piece, MovementMode::ZeroG, &RotationSystem::Srs));
+        let hard_drops = cells(&placements(
+            &page.board, piece, MovementMode::HardDropOnly, &RotationSystem::Srs
+        ));
+        assert!(zero_g.is_subset(&all), "{:?}", piece);
+        assert!(hard_drops.is_subset(&zero_g), "{:?}", piece);
+    }
+}
+
+#[test]
+fn inputs_reproduce_placements() {
+    let page = &decode(TST)[0];
+    let spawned = SpawnRule::Row19Or20.spawn(Piece::T, &page.board).unwrap();
+    for p in find_moves(&page.board, spawned, MovementMode::ZeroG, &RotationSystem::Srs, false) {
+        let mut piece = spawned;
+        for &input in &p.inputs.movements {
+            assert!(input.apply(&mut piece, &page.board, &RotationSystem::Srs));
+        }
+        piece.sonic_drop(&page.board);
+        assert_eq!(piece, p.location);
+    }
+}
@@ -0,0 +1,70 @@
+//! Known spin setups, checked with the default attack table and SRS.
+
+mod common;
+
+use libtetris::*;
+use common::*;
+
+/// A DT cannon style stack: the TSD clears the rows covering a TST slot.
+const DT_CANNON: &str = "v115@fgB8HeA8DeA8DeC8CeH8AeF8AeI8BeH8AeH8JeF8IvhAtpB";
+/// A T-spin mini double from a kick under a floating block.
+const MINI_TSD: &str = "v115@0gA8ReA8IeA8BeH8AeH8JetJJ";
+
+#[test]
+fn tst() {
+    check_sequence(TST, &[PlacementKind::Tspin3]);
+    check_sequence(TST_MIRRORED, &[PlacementKind::Tspin3]);
+}
+
+#[test]
+fn dt_cannon() {
+    check_sequence(DT_CANNON, &[PlacementKind::Tspin2, PlacementKind::Tspin3]);
+}
+
+#[test]
+fn mini_tsd() {
+    check_sequence(MINI_TSD, &[PlacementKind::MiniTspin2]);
+}
+
+#[test]
+fn dt_cannon_scores() {
+    let pages = decode(DT_CANNON);
+    let mut board = pages[0].board.clone();
+    let attack = AttackTable::default();
+    let mut sent = vec![];
+    for page in &pages {
+        let target = page.piece.unwrap();
+        let location = *reachable(&board, &target, MovementMode::ZeroG, &RotationSystem::Srs)
+            .iter()
+            .find(|p| p.tspin == TspinStatus::Full)
+            .expect("T-spin not reachable");
+        let result = board.lock_piece(location, &attack);
+        sent.push((result.garbage_sent, result.b2b));
+    }
+    assert_eq!(sent, vec![(4, false), (7, true)]);
+}
+
+#[test]
+fn tst_needs_kicks() {
+    let page = &decode(TST)[0];
+    let target = page.piece.unwrap();
+    assert!(reachable(&page.board, &target, MovementMode::HardDropOnly, &RotationSystem::Srs)
+        .is_empty());
+    // At 20G the T lands on the stack next to the slot and can still slide in.
+    assert!(reachable(&page.board, &target, MovementMode::TwentyG, &RotationSystem::Srs)
+        .iter().any(|p| p.tspin == TspinStatus::Full));
+    assert!(reachable(&page.board, &target, MovementMode::ZeroG, &RotationSystem::NoKicks)
+        .is_empty());
+    assert!(reachable(&page.board, &target, MovementMode::ZeroG, &RotationSystem::SrsPlus)
+        .iter().any(|p| p.tspin == TspinStatus::Full));
+}
+
+#[test]
+fn spin_detection_depends_on_last_move() {
+    let page = &decode(TST)[0];
+    let target = page.piece.unwrap();
+    let mut board = page.board.clone();
+    let result = board.lock_piece(target, &AttackTable::default());
+    assert_eq!(result.placement_kind, PlacementKind::Clear3);
+    assert!(!result.b2b);
+}
@@ -28,9 +28,8 @@ fn main() {
             }
         };
 
-        let mut b = Board::new();
-        b.set_field(field);
-        b.bag = enumset::EnumSet::empty();
+        let mut bag = enumset::EnumSet::empty();
+        let mut hold = None;
         for c in bagspec.chars() {
             let p = match c.to_ascii_uppercase() {
                 'S' => Piece::S,
@@ -42,16 +41,17 @@ fn main() {
                 'J' => Piece::J,
                 _ => continue
             };
-            if b.bag.contains(p) {
-                b.hold_piece = Some(p);
+            if bag.contains(p) {
+                hold = Some(p);
             } else {
-                b.bag |= p;
+                bag |= p;
             }
         }
-        if b.hold_piece.is_none() && b.bag.len() <= 1 {
-            b.hold_piece = b.bag.iter().next();
-            b.bag = enumset::EnumSet::all();
+        if hold.is_none() && bag.len() <= 1 {
+            hold = bag.iter().next();
+            bag = enumset::EnumSet::all();
         }
+        let b = Board::new_with_state(&field, bag, hold, false, 0, 0);
 
         if fumen.pages.len() == 1 {
             match value {
@@ -78,7 +78,7 @@ fn main() {
                         f[y][x] = p.field[y][x] != fumen::CellColor::Empty;
                     }
                 }
-                b.set_field(f);
+                b.set_field(&f);
                 let p = convert(p.piece.unwrap_or_else(
                     || panic!("no piece in fumen on line {}", line + 1)
                 ));
@@ -100,7 +100,7 @@ fn main() {
                     book.add_move(&b, p, None);
                     b.add_next_piece(p.kind.0);
                     b.advance_queue();
-                    offset += b.lock_piece(p).cleared_lines.len() as i32;
+                    offset += b.lock_piece(p, &AttackTable::default()).cleared_lines.len() as i32;
                 }
             }
         }
@@ -157,13 +157,15 @@ fn convert(p: fumen::Piece) -> FallingPiece {
 
 fn mirror_board(b: &Board) -> Board {
     let mut b = b.clone();
-    b.bag = b.bag.iter().map(mirror_piece).collect();
+    if let AnyRandomizer::SevenBag(bag) = &mut b.randomizer {
+        bag.remaining = bag.remaining.iter().map(mirror_piece).collect();
+    }
     b.hold_piece = b.hold_piece.map(mirror_piece);
     let mut f = b.get_field();
     for r in &mut f[..] {
         r.reverse();
     }
-    b.set_field(f);
+    b.set_field(&f);
     b
 }
 
@@ -318,7 +320,7 @@ fn add_pcs(book: &mut BookBuilder, b: &Board, pieces: u32) {
     );
     let mut count = 0;
     let mut pieceset = pcf::PieceSet::default();
-    for p in b.bag {
+    for p in b.randomizer.possible_pieces() {
         pieceset = pieceset.with(p.into());
         count += 1;
     }
@@ -344,7 +346,7 @@ fn add_pcs(book: &mut BookBuilder, b: &Board, pieces: u32) {
 
     let book = &std::sync::Mutex::new(book);
     rayon::scope(|s| {
-        all_sequences(b.bag, pieces as usize, |q| s.spawn(move |_| {
+        all_sequences(b.randomizer.possible_pieces(), pieces as usize, |q| s.spawn(move |_| {
             let set: pcf::PieceSet = q.iter().copied().collect();
             for combo in combinations.get(&set).map(|v| &**v).unwrap_or(&[]) {
                 pcf::solve_placement_combination(
@@ -361,7 +363,7 @@ fn add_pcs(book: &mut BookBuilder, b: &Board, pieces: u32) {
                             book.add_move(mirror_board(&b), mirror_placement(mv), score);
                             b.add_next_piece(mv.kind.0);
                             b.advance_queue();
-                            b.lock_piece(mv);
+                            b.lock_piece(mv, &AttackTable::default());
                             bitb = bitb.combine(p.board());
                         }
                     }
@@ -1,4 +1,4 @@
-use libtetris::{ FallingPiece, Piece, RotationState, Board };
+use libtetris::{ FallingPiece, Piece, RotationState, Board, AttackTable };
 use enumset::EnumSet;
 use serde::{ Serialize, Deserialize };
 use std::collections::HashMap;
@@ -94,9 +94,9 @@ impl Position {
                 field[y][x] = self.rows[y] & 1<<x != 0;
             }
         }
-        let mut board = Board::new_with_state(field, self.bag, self.extra, false, 0);
+        let mut board = Board::new_with_state(&field, self.bag, self.extra, false, 0, 0);
         let soft_drop = !board.above_stack(&mv);
-        let clear = board.lock_piece(mv).placement_kind.is_clear();
+        let clear = board.lock_piece(mv, &AttackTable::default()).placement_kind.is_clear();
         let mut position = *self;
         for y in 0..10 {
             position.rows[y] = *board.get_row(y as i32);
@@ -1,8 +1,8 @@
-use libtetris::{ Board, ColoredRow, FallingPiece, Controller };
-use battle::{ Battle, Replay, Event, PieceMoveExecutor, GameConfig };
+use libtetris::{ Board, ColoredRow, FallingPiece, Controller, IncomingGarbage };
+use battle::{ Battle, Event, PieceMoveExecutor, GameConfig };
+use replay_file::InfoReplay;
 use cold_clear::evaluation::Evaluator;
 use rand::prelude::*;
-use serde::{ Serialize, Deserialize };
 use std::collections::VecDeque;
 
 pub struct BotInput<E: Evaluator> {
@@ -44,7 +44,7 @@ impl<E: Evaluator> BotInput<E> {
     }
 
     pub fn update(
-        &mut self, board: &Board<ColoredRow>, events: &[Event], incoming: u32
+        &mut self, board: &Board<ColoredRow>, events: &[Event], incoming: &[IncomingGarbage]
     ) -> Option<cold_clear::Info> {
         self.think();
 
@@ -54,7 +54,8 @@ impl<E: Evaluator> BotInput<E> {
                 Event::PieceSpawned { new_in_queue } => {
                     self.bot.add_next_piece(*new_in_queue);
                     if self.executing.is_none() {
-                        if let Some((mv, inf)) = self.bot.suggest_move(&self.eval, None, incoming) {
+                        let suggestion = self.bot.suggest_move(&self.eval, None, incoming, None);
+                        if let Some((mv, inf)) = suggestion {
                             info = Some(inf);
                             self.executing = Some((
                                 mv.expected_location,
@@ -65,7 +66,9 @@ impl<E: Evaluator> BotInput<E> {
                     }
                 }
                 Event::GarbageAdded(_) => {
-                    self.bot.reset(board.get_field(), board.b2b_bonus, board.combo);
+                    self.bot.reset(
+                        board.get_field(), board.b2b_bonus, board.b2b_chain, board.combo
+                    );
                 }
                 _ => {}
             }
@@ -74,7 +77,9 @@ impl<E: Evaluator> BotInput<E> {
         if let Some((expected, ref mut executor)) = self.executing {
             if let Some(loc) = executor.update(&mut self.controller, board, events) {
                 if loc != expected {
-                    self.bot.reset(board.get_field(), board.b2b_bonus, board.combo);
+                    self.bot.reset(
+                        board.get_field(), board.b2b_bonus, board.b2b_chain, board.combo
+                    );
                 }
                 self.executing = None;
             }
@@ -84,37 +89,40 @@ impl<E: Evaluator> BotInput<E> {
 }
 
 pub fn do_battle(
-    p1: impl Evaluator + Clone, p2: impl Evaluator + Clone
+    p1: impl Evaluator + Clone, p2: impl Evaluator + Clone, config: &GameConfig
 ) -> Option<(InfoReplay, bool)> {
     let mut battle = Battle::new(
-        GameConfig::default(), GameConfig::default(),
-        thread_rng().gen(), thread_rng().gen(), thread_rng().gen()
-    );
+        vec![config.clone(), config.clone()],
+        vec![thread_rng().gen(), thread_rng().gen()],
+        thread_rng().gen()
+    ).expect("the game config was validated when it was loaded");
 
-    battle.replay.p1_name = format!("Cold Clear\n{}", p1.name());
-    battle.replay.p2_name = format!("Cold Clear\n{}", p2.name());
+    battle.replay.names = vec![
+        format!("Cold Clear\n{}", p1.name()),
+        format!("Cold Clear\n{}", p2.name())
+    ];
 
-    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1);
-    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2);
+    let mut p1 = BotInput::new(battle.players[0].board.to_compressed(), p1);
+    let mut p2 = BotInput::new(battle.players[1].board.to_compressed(), p2);
 
     let mut p1_info_updates = VecDeque::new();
     let mut p2_info_updates = VecDeque::new();
 
     let p1_won;
     'battle: loop {
-        let update = battle.update(p1.controller, p2.controller);
+        let update = battle.update(&[p1.controller, p2.controller]);
         p1_info_updates.push_back(p1.update(
-            &battle.player_1.board,
-            &update.player_1.events,
-            battle.player_1.garbage_queue
+            &battle.players[0].board,
+            &update.players[0].events,
+            &update.players[0].garbage_queue
         ));
         p2_info_updates.push_back(p2.update(
-            &battle.player_2.board,
-            &update.player_2.events,
-            battle.player_2.garbage_queue
+            &battle.players[1].board,
+            &update.players[1].events,
+            &update.players[1].garbage_queue
         ));
 
-        for event in &update.player_1.events {
+        for event in &update.players[0].events {
             use battle::Event::*;
             match event {
                 GameOver => {
@@ -124,7 +132,7 @@ pub fn do_battle(
                 _ => {}
             }
         }
-        for event in &update.player_2.events {
+        for event in &update.players[1].events {
             use battle::Event::*;
             match event {
                 GameOver => {
@@ -141,21 +149,13 @@ pub fn do_battle(
     }
 
     for _ in 0..180 {
-        battle.replay.updates.push_back(Default::default());
+        battle.replay.updates.push_back(vec![Default::default(); 2]);
         p1_info_updates.push_back(None);
         p2_info_updates.push_back(None);
     }
 
     Some((InfoReplay {
         replay: battle.replay,
-        p1_info_updates,
-        p2_info_updates
+        info_updates: vec![p1_info_updates, p2_info_updates]
     }, p1_won))
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InfoReplay {
-    pub replay: Replay,
-    pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
-    pub p2_info_updates: VecDeque<Option<cold_clear::Info>>
 }
\ No newline at end of file
@@ -1,7 +1,6 @@
 use serde::{ Serialize, Deserialize };
 use cold_clear::evaluation::Standard;
 use rand::prelude::*;
-use libflate::deflate;
 use std::sync::{ Arc, Mutex };
 use std::collections::VecDeque;
 use std::sync::mpsc::channel;
@@ -22,11 +21,17 @@ fn main() {
         Err(_) => new_population::<Standard>()
     };
 
+    let game_config = ::battle::load_game_config("game.json").unwrap_or_else(|e| {
+        eprintln!("game.json contained invalid data: {}", e);
+        std::process::exit(1)
+    });
+
     let matchups = Arc::new(Mutex::new((true, VecDeque::new())));
     let (send, game_results) = channel();
     for _ in 0..12 {
         let matchups = matchups.clone();
         let send = send.clone();
+        let game_config = game_config.clone();
         std::thread::spawn(move || {
             loop {
                 let (p1, p1_e, p2, p2_e) = {
@@ -37,7 +42,7 @@ fn main() {
                         None => continue
                     }
                 };
-                if let Some((replay, p1_won)) = battle::do_battle(p1_e, p2_e) {
+                if let Some((replay, p1_won)) = battle::do_battle(p1_e, p2_e, &game_config) {
                     send.send(Some((if p1_won { p1 } else { p2 }, replay))).ok();
                 } else {
                     send.send(None).ok();
@@ -72,11 +77,7 @@ fn main() {
             if let Some((winner, replay)) = game_results.recv().unwrap() {
                 results[winner].1 += 1;
 
-                let mut encoder = deflate::Encoder::new(
-                    std::fs::File::create("recent-game.dat").unwrap()
-                );
-                bincode::serialize_into(&mut encoder, &replay).unwrap();
-                encoder.finish().unwrap();
+                replay.save(std::fs::File::create("recent-game.dat").unwrap()).unwrap();
             }
             if (i+1) % 80 == 0 {
                 println!("Completed game {} of {}", i+1, count);
@@ -59,12 +59,15 @@ impl Mutateable for Standard {
             tspin3: thread_rng().gen_range(-999, 1000),
             mini_tspin1: thread_rng().gen_range(-999, 1000),
             mini_tspin2: thread_rng().gen_range(-999, 1000),
+            spin_clear: thread_rng().gen_range(-999, 1000),
+            mini_spin_clear: thread_rng().gen_range(-999, 1000),
             perfect_clear: thread_rng().gen_range(-999, 1000),
             combo_garbage: thread_rng().gen_range(-999, 1000),
 
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            prefer_kills: false,
             sub_name: Some(sub_name)
         }
     }
@@ -118,12 +121,15 @@ impl Mutateable for Standard {
             tspin3: crossover_gene(parent1.tspin3, parent2.tspin3),
             mini_tspin1: crossover_gene(parent1.mini_tspin1, parent2.mini_tspin1),
             mini_tspin2: crossover_gene(parent1.mini_tspin2, parent2.mini_tspin2),
+            spin_clear: crossover_gene(parent1.spin_clear, parent2.spin_clear),
+            mini_spin_clear: crossover_gene(parent1.mini_spin_clear, parent2.mini_spin_clear),
             perfect_clear: crossover_gene(parent1.perfect_clear, parent2.perfect_clear),
             combo_garbage: crossover_gene(parent1.combo_garbage, parent2.combo_garbage),
 
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            prefer_kills: false,
             sub_name: Some(sub_name)
         }
     }
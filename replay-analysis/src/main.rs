@@ -0,0 +1,307 @@
+use std::io::prelude::*;
+use serde::Serialize;
+use battle::{ Battle, Event, Replay };
+use libtetris::Statistics;
+use replay_file::InfoReplay;
+
+/// Battles are updated 60 times per second.
+const TICKS_PER_SECOND: f64 = 60.0;
+
+fn main() {
+    let mut format = Format::Json;
+    let mut table = Table::Summary;
+    let mut files = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = match args.next().as_deref() {
+                Some("json") => Format::Json,
+                Some("csv") => Format::Csv,
+                _ => usage()
+            },
+            "--table" => table = match args.next().as_deref() {
+                Some("summary") => Table::Summary,
+                Some("placements") => Table::Placements,
+                Some("garbage") => Table::Garbage,
+                _ => usage()
+            },
+            _ if arg.starts_with("--") => usage(),
+            _ => files.push(arg)
+        }
+    }
+    if files.is_empty() {
+        usage();
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    let mut header = true;
+    let mut failed = false;
+    for file in files {
+        let report = std::fs::File::open(&file).map_err(Into::into)
+            .and_then(|f| InfoReplay::load(std::io::BufReader::new(f)))
+            .map_err(|e| e.to_string())
+            .and_then(|(_, replay)| analyze(file.clone(), replay));
+        let report = match report {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                failed = true;
+                continue
+            }
+        };
+        let r = match format {
+            Format::Json => serde_json::to_writer(&mut out, &report)
+                .map_err(Into::into)
+                .and_then(|_| writeln!(out)),
+            Format::Csv => report.write_csv(&mut out, table, &mut header)
+        };
+        if let Err(e) = r {
+            eprintln!("Failure writing output: {}", e);
+            std::process::exit(1);
+        }
+    }
+    out.flush().ok();
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: replay-analysis [--format json|csv] [--table summary|placements|garbage] FILE..."
+    );
+    eprintln!("JSON output is one report per line. --table picks the CSV table to print.");
+    std::process::exit(2)
+}
+
+#[derive(Copy, Clone)]
+enum Format {
+    Json,
+    Csv
+}
+
+#[derive(Copy, Clone)]
+enum Table {
+    Summary,
+    Placements,
+    Garbage
+}
+
+#[derive(Serialize)]
+struct Report {
+    file: String,
+    /// The length of the game. The replay is only analyzed up to the first top out.
+    ticks: u32,
+    players: Vec<PlayerReport>,
+    garbage: Vec<GarbageEvent>,
+    placements: Vec<PlacementReport>
+}
+
+#[derive(Serialize, Default)]
+struct PlayerReport {
+    name: String,
+    won: bool,
+    topped_out_at: Option<u32>,
+    apm: f64,
+    pps: f64,
+    statistics: Statistics
+}
+
+#[derive(Serialize)]
+struct GarbageEvent {
+    time: u32,
+    player: u32,
+    /// Either `sent`, for attacks, or `added`, for garbage rows entering the board.
+    kind: &'static str,
+    lines: u32
+}
+
+#[derive(Serialize)]
+struct PlacementReport {
+    time: u32,
+    player: u32,
+    piece: libtetris::FallingPiece,
+    placement_kind: libtetris::PlacementKind,
+    garbage_sent: u32,
+    combo: Option<u32>,
+    b2b: bool,
+    /// The most recent info the bot reported before the piece was placed.
+    info: Option<cold_clear::Info>
+}
+
+/// Plays the replay back, failing if it can't be played.
+fn analyze(file: String, replay: InfoReplay) -> Result<Report, String> {
+    let InfoReplay { replay, mut info_updates } = replay;
+    check_replay(&replay)?;
+    let mut battle = Battle::new(replay.configs, replay.seeds, replay.garbage_seed)
+        .map_err(|e| e.to_string())?;
+    let mut players: Vec<_> = replay.names.into_iter()
+        .map(|name| PlayerReport { name, ..Default::default() })
+        .collect();
+    info_updates.resize_with(players.len(), Default::default);
+    let mut garbage = vec![];
+    let mut placements = vec![];
+    let mut infos = vec![None; players.len()];
+
+    for controllers in replay.updates {
+        let update = battle.update(&controllers);
+        for (i, player_update) in update.players.iter().enumerate() {
+            if let Some(info) = info_updates[i].pop_front().flatten() {
+                infos[i] = Some(info);
+            }
+            let player = i as u32 + 1;
+            for event in &player_update.events {
+                match event {
+                    Event::PiecePlaced { piece, locked, .. } => {
+                        players[i].statistics.update(locked);
+                        placements.push(PlacementReport {
+                            time: update.time,
+                            player,
+                            piece: *piece,
+                            placement_kind: locked.placement_kind,
+                            garbage_sent: locked.garbage_sent,
+                            combo: locked.combo,
+                            b2b: locked.b2b,
+                            info: infos[i].take()
+                        });
+                    }
+                    &Event::GarbageSent(lines) => garbage.push(GarbageEvent {
+                        time: update.time, player, kind: "sent", lines
+                    }),
+                    Event::GarbageAdded(columns) => garbage.push(GarbageEvent {
+                        time: update.time, player, kind: "added", lines: columns.len() as u32
+                    }),
+                    Event::GameOver => players[i].topped_out_at = Some(update.time),
+                    _ => {}
+                }
+            }
+        }
+        // Replays keep going for a while after the game ends, which isn't part of the game.
+        if game_over(&players) {
+            break
+        }
+    }
+
+    let ticks = battle.time;
+    let minutes = ticks as f64 / TICKS_PER_SECOND / 60.0;
+    let winner = players.len() > 1 && game_over(&players);
+    for p in &mut players {
+        p.won = winner && p.topped_out_at.is_none();
+        if minutes > 0.0 {
+            p.apm = p.statistics.attack as f64 / minutes;
+            p.pps = p.statistics.pieces as f64 / minutes / 60.0;
+        }
+    }
+
+    Ok(Report { file, ticks, players, garbage, placements })
+}
+
+/// Checks that the replay describes the same players everywhere and that their games can be
+/// played, so that a malformed file is reported instead of panicking during playback.
+fn check_replay(replay: &Replay) -> Result<(), String> {
+    let players = replay.names.len();
+    if replay.configs.len() != players || replay.seeds.len() != players {
+        return Err(format!(
+            "replay has {} names, {} configs and {} seeds",
+            players, replay.configs.len(), replay.seeds.len()
+        ));
+    }
+    for config in &replay.configs {
+        config.validate().map_err(|e| format!("invalid game rules: {}", e))?;
+    }
+    if let Some(tick) = replay.updates.iter().position(|c| c.len() != players) {
+        return Err(format!(
+            "tick {} has {} controllers for {} players",
+            tick + 1, replay.updates[tick].len(), players
+        ));
+    }
+    Ok(())
+}
+
+/// Whether at most one player is left, or nobody in a single player game.
+fn game_over(players: &[PlayerReport]) -> bool {
+    let alive = players.iter().filter(|p| p.topped_out_at.is_none()).count();
+    alive == 0 || (players.len() > 1 && alive == 1)
+}
+
+impl Report {
+    /// Writes the rows of one table, and the header before the first row of the output.
+    fn write_csv(
+        &self, out: &mut impl Write, table: Table, header: &mut bool
+    ) -> std::io::Result<()> {
+        let mut rows: Vec<Vec<(String, String)>> = vec![];
+        match table {
+            Table::Summary => for (i, p) in self.players.iter().enumerate() {
+                let mut row = vec![
+                    field("file", &self.file),
+                    field("player", i + 1),
+                    field("name", &p.name),
+                    field("won", p.won),
+                    field("topped_out_at", opt(p.topped_out_at)),
+                    field("ticks", self.ticks),
+                    field("apm", p.apm),
+                    field("pps", p.pps)
+                ];
+                if let Ok(serde_json::Value::Object(stats)) = serde_json::to_value(p.statistics) {
+                    row.extend(stats.into_iter().map(|(k, v)| (k, v.to_string())));
+                }
+                rows.push(row);
+            },
+            Table::Placements => for p in &self.placements {
+                rows.push(vec![
+                    field("file", &self.file),
+                    field("time", p.time),
+                    field("player", p.player),
+                    field("piece", format!("{:?}", p.piece.kind.0)),
+                    field("rotation", format!("{:?}", p.piece.kind.1)),
+                    field("x", p.piece.x),
+                    field("y", p.piece.y),
+                    field("placement_kind", format!("{:?}", p.placement_kind)),
+                    field("garbage_sent", p.garbage_sent),
+                    field("combo", opt(p.combo)),
+                    field("b2b", p.b2b),
+                    field("info", p.info.as_ref().map_or(String::new(), |info| {
+                        serde_json::to_string(info).unwrap_or_default()
+                    }))
+                ]);
+            },
+            Table::Garbage => for g in &self.garbage {
+                rows.push(vec![
+                    field("file", &self.file),
+                    field("time", g.time),
+                    field("player", g.player),
+                    field("kind", g.kind),
+                    field("lines", g.lines)
+                ]);
+            }
+        }
+
+        if let (true, Some(row)) = (*header, rows.first()) {
+            let names: Vec<_> = row.iter().map(|(k, _)| escape(k)).collect();
+            writeln!(out, "{}", names.join(","))?;
+            *header = false;
+        }
+        for row in rows {
+            let values: Vec<_> = row.iter().map(|(_, v)| escape(v)).collect();
+            writeln!(out, "{}", values.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+fn field(name: &str, value: impl ToString) -> (String, String) {
+    (name.to_owned(), value.to_string())
+}
+
+fn opt(value: Option<u32>) -> String {
+    value.map_or(String::new(), |v| v.to_string())
+}
+
+fn escape(value: &str) -> String {
+    if value.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
@@ -0,0 +1,140 @@
+//! The replay file format shared by the client and the tools.
+//!
+//! A replay file starts with `MAGIC`, followed by a bincode `Header` and the deflate compressed
+//! bincode `InfoReplay`. Files from before the header existed are just the compressed replay, and
+//! are read as format version 0.
+
+use std::collections::VecDeque;
+use std::io::prelude::*;
+use serde::{ Serialize, Deserialize };
+use bincode::Options;
+use battle::Replay;
+use libflate::deflate;
+
+/// The bytes every replay file starts with.
+pub const MAGIC: [u8; 8] = *b"CCREPLAY";
+
+/// The current version of the format. Increase it whenever the serialized layout of
+/// `InfoReplay` changes, and add a migration from the previous layout to `InfoReplay::load`.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A replay together with what the bots were thinking during it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InfoReplay {
+    pub replay: Replay,
+    /// What each player's bot reported on each tick, if they are a bot.
+    pub info_updates: Vec<VecDeque<Option<cold_clear::Info>>>
+}
+
+/// Describes the program that wrote a replay file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub format_version: u32,
+    /// Empty for files from before the header existed.
+    pub libtetris_version: String,
+    /// Empty for files from before the header existed.
+    pub bot_version: String
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Format(bincode::Error),
+    /// The file was written by a newer version.
+    UnsupportedVersion(u32)
+}
+
+/// Version 0 files: the bare compressed replay from before the rules became configurable.
+#[derive(Deserialize)]
+struct InfoReplayV0 {
+    replay: battle::legacy::ReplayV0,
+    p1_info_updates: VecDeque<Option<cold_clear::legacy::InfoV0>>,
+    p2_info_updates: VecDeque<Option<cold_clear::legacy::InfoV0>>
+}
+
+impl Header {
+    fn current() -> Self {
+        Header {
+            format_version: FORMAT_VERSION,
+            libtetris_version: libtetris::VERSION.to_owned(),
+            bot_version: cold_clear::VERSION.to_owned()
+        }
+    }
+}
+
+impl InfoReplay {
+    pub fn save(&self, mut writer: impl Write) -> Result<(), Error> {
+        writer.write_all(&MAGIC)?;
+        bincode::serialize_into(&mut writer, &Header::current())?;
+        let mut encoder = deflate::Encoder::new(writer);
+        bincode::serialize_into(&mut encoder, self)?;
+        encoder.finish().into_result()?;
+        Ok(())
+    }
+
+    /// Reads a replay file, migrating it to the current layout if it is from an older version.
+    pub fn load(mut reader: impl Read) -> Result<(Header, InfoReplay), Error> {
+        let mut magic = vec![];
+        (&mut reader).take(MAGIC.len() as u64).read_to_end(&mut magic)?;
+        if magic != MAGIC {
+            let mut data = vec![];
+            deflate::Decoder::new((&magic[..]).chain(reader)).read_to_end(&mut data)?;
+            return Ok((Header {
+                format_version: 0,
+                libtetris_version: String::new(),
+                bot_version: String::new()
+            }, InfoReplay::load_v0(&data)?));
+        }
+
+        let header: Header = bincode::deserialize_from(&mut reader)?;
+        match header.format_version {
+            FORMAT_VERSION => {
+                let replay = bincode::deserialize_from(deflate::Decoder::new(reader))?;
+                Ok((header, replay))
+            }
+            v => Err(Error::UnsupportedVersion(v))
+        }
+    }
+
+    fn load_v0(data: &[u8]) -> Result<InfoReplay, Error> {
+        // Match bincode::serialize, but fail instead of misreading files that are too long.
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .reject_trailing_bytes();
+        let v0: InfoReplayV0 = options.deserialize(data)?;
+        Ok(InfoReplay {
+            replay: v0.replay.into(),
+            info_updates: vec![migrate_infos(v0.p1_info_updates), migrate_infos(v0.p2_info_updates)]
+        })
+    }
+}
+
+fn migrate_infos<I: Into<cold_clear::Info>>(
+    infos: VecDeque<Option<I>>
+) -> VecDeque<Option<cold_clear::Info>> {
+    infos.into_iter().map(|info| info.map(Into::into)).collect()
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Error::Format(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Format(e) => write!(f, "invalid replay: {}", e),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported replay format version {}", v)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
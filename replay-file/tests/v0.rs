@@ -0,0 +1,198 @@
+//! Replays saved before the file format had a header, when the client wrote the bare deflate
+//! compressed bincode of its two player `InfoReplay`.
+
+use std::collections::VecDeque;
+use serde::Serialize;
+use battle::{ Battle, GameConfig };
+use libtetris::*;
+use replay_file::InfoReplay;
+
+#[derive(Serialize)]
+struct InfoReplayV0 {
+    replay: ReplayV0,
+    p1_info_updates: VecDeque<Option<InfoV0>>,
+    p2_info_updates: VecDeque<Option<InfoV0>>
+}
+
+#[derive(Serialize)]
+struct ReplayV0 {
+    p1_name: String,
+    p2_name: String,
+    p1_seed: [u8; 16],
+    p2_seed: [u8; 16],
+    garbage_seed: [u8; 16],
+    p1_config: GameConfigV0,
+    p2_config: GameConfigV0,
+    updates: VecDeque<(Controller, Controller)>
+}
+
+#[derive(Serialize)]
+struct GameConfigV0 {
+    spawn_delay: u32,
+    line_clear_delay: u32,
+    delayed_auto_shift: u32,
+    auto_repeat_rate: u32,
+    soft_drop_speed: u32,
+    lock_delay: u32,
+    gravity: i32,
+    next_queue_size: u32,
+    max_garbage_add: u32,
+    move_lock_rule: u32,
+    garbage_blocking: bool
+}
+
+/// The `PcLoop` variant came last, so it can be left out.
+#[derive(Serialize)]
+enum InfoV0 {
+    Normal(NormalInfoV0),
+    Book
+}
+
+#[derive(Serialize)]
+struct NormalInfoV0 {
+    nodes: u32,
+    depth: u32,
+    original_rank: u32,
+    plan: Vec<(FallingPiece, LockResultV0)>
+}
+
+#[derive(Serialize)]
+struct LockResultV0 {
+    placement_kind: PlacementKind,
+    locked_out: bool,
+    b2b: bool,
+    perfect_clear: bool,
+    combo: Option<u32>,
+    garbage_sent: u32,
+    cleared_lines: Vec<i32>
+}
+
+impl From<&GameConfig> for GameConfigV0 {
+    fn from(c: &GameConfig) -> Self {
+        GameConfigV0 {
+            spawn_delay: c.spawn_delay,
+            line_clear_delay: c.line_clear_delay,
+            delayed_auto_shift: c.delayed_auto_shift,
+            auto_repeat_rate: c.auto_repeat_rate,
+            soft_drop_speed: c.soft_drop_speed,
+            lock_delay: c.lock_delay,
+            gravity: c.gravity,
+            next_queue_size: c.next_queue_size,
+            max_garbage_add: c.max_garbage_add,
+            move_lock_rule: c.move_lock_rule,
+            garbage_blocking: c.garbage_blocking
+        }
+    }
+}
+
+/// Plays a battle with rules that were already configurable before the header existed.
+fn play_battle() -> Battle {
+    let config = GameConfig { spawn_delay: 3, garbage_blocking: true, ..GameConfig::default() };
+    let mut battle = Battle::new(
+        vec![config.clone(), config], vec![[1; 16], [2; 16]], [3; 16]
+    ).unwrap();
+    battle.replay.names = vec!["Cold Clear".to_owned(), "Human".to_owned()];
+    for tick in 0..1200 {
+        let p1 = Controller {
+            left: tick % 60 < 20,
+            rotate_right: tick % 30 == 10,
+            hard_drop: tick % 30 == 29,
+            ..Default::default()
+        };
+        let p2 = Controller {
+            right: tick % 50 < 10,
+            hold: tick % 100 == 0,
+            hard_drop: tick % 25 == 24,
+            ..Default::default()
+        };
+        battle.update(&[p1, p2]);
+    }
+    battle
+}
+
+#[test]
+fn headerless_replays_are_migrated() {
+    let battle = play_battle();
+    let replay = &battle.replay;
+    let piece = FallingPiece {
+        kind: PieceState(Piece::T, RotationState::South),
+        x: 4, y: 1,
+        tspin: TspinStatus::Full
+    };
+    let v0 = InfoReplayV0 {
+        replay: ReplayV0 {
+            p1_name: replay.names[0].clone(),
+            p2_name: replay.names[1].clone(),
+            p1_seed: replay.seeds[0],
+            p2_seed: replay.seeds[1],
+            garbage_seed: replay.garbage_seed,
+            p1_config: (&replay.configs[0]).into(),
+            p2_config: (&replay.configs[1]).into(),
+            updates: replay.updates.iter().map(|c| (c[0], c[1])).collect()
+        },
+        p1_info_updates: vec![
+            None,
+            Some(InfoV0::Normal(NormalInfoV0 {
+                nodes: 1500,
+                depth: 6,
+                original_rank: 2,
+                plan: vec![(piece, LockResultV0 {
+                    placement_kind: PlacementKind::Tspin2,
+                    locked_out: false,
+                    b2b: true,
+                    perfect_clear: false,
+                    combo: Some(0),
+                    garbage_sent: 5,
+                    cleared_lines: vec![0, 1]
+                })]
+            })),
+            Some(InfoV0::Book)
+        ].into(),
+        p2_info_updates: VecDeque::new()
+    };
+
+    let mut encoder = libflate::deflate::Encoder::new(vec![]);
+    bincode::serialize_into(&mut encoder, &v0).unwrap();
+    let data = encoder.finish().into_result().unwrap();
+
+    let (header, loaded) = InfoReplay::load(&data[..]).unwrap();
+    assert_eq!(header.format_version, 0);
+    assert_eq!(loaded.replay.names, replay.names);
+    assert_eq!(loaded.replay.seeds, replay.seeds);
+    assert_eq!(loaded.replay.garbage_seed, replay.garbage_seed);
+    assert_eq!(loaded.replay.configs, replay.configs);
+    assert_eq!(loaded.replay.updates, replay.updates);
+
+    assert_eq!(loaded.info_updates.len(), 2);
+    assert!(loaded.info_updates[1].is_empty());
+    let infos = &loaded.info_updates[0];
+    assert_eq!(infos.len(), 3);
+    assert!(infos[0].is_none());
+    match &infos[1] {
+        Some(cold_clear::Info::Normal(info)) => {
+            assert_eq!((info.nodes, info.depth, info.original_rank, info.memory), (1500, 6, 2, 0));
+            assert!(info.candidates.is_empty());
+            let lock = LockResult {
+                placement_kind: PlacementKind::Tspin2,
+                b2b: true,
+                combo: Some(0),
+                garbage_sent: 5,
+                cleared_lines: [0, 1].iter().copied().collect(),
+                ..Default::default()
+            };
+            assert_eq!(info.plan, vec![(piece, lock)]);
+        }
+        other => panic!("expected normal info, got {:?}", other)
+    }
+    assert_eq!(infos[2], Some(cold_clear::Info::Book));
+
+    assert!(battle.players.iter().all(|p| p.board.get_field().iter().any(|r| r.contains(&true))));
+    let played_back = loaded.replay.battle_at(replay.updates.len() as u32).unwrap();
+    assert_eq!(played_back.time, battle.time);
+    for (a, b) in played_back.players.iter().zip(&battle.players) {
+        assert_eq!(a.board.get_field(), b.board.get_field());
+        assert_eq!(a.board.hold_piece, b.board.hold_piece);
+        assert!(a.board.next_queue().eq(b.board.next_queue()));
+        assert_eq!(a.is_game_over(), b.is_game_over());
+    }
+}
@@ -1,28 +1,59 @@
-use std::io::{stdout, Result};
+use std::io::{stdout, BufReader, Error, ErrorKind, Result};
+use std::sync::Arc;
+use std::time::Instant;
 
-use tbi::Message;
+use cold_clear::evaluation::Evaluator;
+use serde::Deserialize;
+use tbi::{ErrorReason, Message, MoveInfo, Randomizer};
 
 mod tbi;
 
+/// The startup configuration of the bot, read from the file passed with `--config`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Config {
+    options: cold_clear::Options,
+    weights: cold_clear::evaluation::Standard,
+    /// Path to an opening book.
+    book: Option<String>,
+}
+
 fn main() -> Result<()> {
+    let (config, book) = parse_args()?;
+    let options = config.options.clone();
+    let mut board_randomizer = libtetris::AnyRandomizer::default();
     let mut bot = None;
+    let mut think_start = Instant::now();
 
-    serde_json::to_writer(
-        stdout(),
-        &Message::Ready {
-            name: "Cold Clear".to_string(),
-            version: "2020-03-17".to_string(),
-            author: "MinusKelvin".to_string(),
-        },
-    )?;
-    println!();
+    send(&Message::Info {
+        name: "Cold Clear".to_string(),
+        version: "2020-03-17".to_string(),
+        author: "MinusKelvin".to_string(),
+        features: vec![],
+    })?;
 
     let mut line = String::new();
     loop {
         line.clear();
-        std::io::stdin().read_line(&mut line)?;
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
         let msg = serde_json::from_str(&line)?;
         match msg {
+            Message::Rules { randomizer } => match randomizer {
+                Randomizer::SevenBag => {
+                    board_randomizer = libtetris::AnyRandomizer::default();
+                    send(&Message::Ready)?;
+                }
+                Randomizer::Unknown => {
+                    // Speculate as if every piece is equally likely.
+                    board_randomizer = libtetris::AnyRandomizer::Memoryless(libtetris::Memoryless);
+                    send(&Message::Ready)?;
+                }
+                Randomizer::Unsupported => send(&Message::Error {
+                    reason: ErrorReason::UnsupportedRules,
+                })?,
+            },
             Message::Start {
                 hold,
                 queue,
@@ -30,47 +61,57 @@ fn main() -> Result<()> {
                 back_to_back,
                 board,
             } => {
-                let mut b = libtetris::Board::new();
+                let width = board.first().map_or(10, |row| row.len());
+                if width < 4 || width > config.weights.max_width() {
+                    send(&Message::Error {
+                        reason: ErrorReason::UnsupportedRules,
+                    })?;
+                    continue;
+                }
+                let mut b = libtetris::Board::with_dimensions(width, 20);
                 b.hold_piece = hold.map(Into::into);
+                b.randomizer = board_randomizer.clone();
                 for piece in queue {
                     b.add_next_piece(piece.into());
                 }
                 b.combo = combo;
                 b.b2b_bonus = back_to_back;
-                let mut field = [[false; 10]; 40];
-                for y in 0..40 {
-                    for x in 0..10 {
-                        field[y][x] = board[y][x].is_some();
-                    }
-                }
-                b.set_field(field);
+                let field: Vec<Vec<bool>> = board.iter()
+                    .map(|row| row.iter().map(Option::is_some).collect())
+                    .collect();
+                b.set_field(&field);
 
-                bot = Some(cold_clear::Interface::launch(
+                let launched = cold_clear::Interface::launch(
                     b,
-                    cold_clear::Options {
-                        speculate: false,
-                        ..Default::default()
-                    },
-                    cold_clear::evaluation::Standard::default(),
-                    None,
-                ));
+                    options.clone(),
+                    config.weights.clone(),
+                    book.clone(),
+                );
+                bot = Some(launched.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?);
+                think_start = Instant::now();
             }
             Message::Stop => {
                 bot = None;
             }
             Message::Suggest => {
                 if let Some(ref mut bot) = bot {
-                    bot.suggest_next_move(0);
-                    let moves = bot
-                        .block_next_move()
-                        .map_or(vec![], |(mv, _)| vec![mv.expected_location.into()]);
-                    serde_json::to_writer(stdout(), &Message::Suggestion { moves })?;
-                    println!();
+                    bot.suggest_next_move(&[]);
+                    let (moves, move_info) = match bot.block_next_move() {
+                        Some((mv, info)) => suggestion(mv, info, think_start),
+                        None => (vec![], MoveInfo {
+                            nodes: 0.0,
+                            nps: 0.0,
+                            depth: 0.0,
+                            extra: "dead".to_string(),
+                        }),
+                    };
+                    send(&Message::Suggestion { moves, move_info })?;
                 }
             }
             Message::Play { mv } => {
                 if let Some(ref mut bot) = bot {
                     bot.play_next_move(mv.into());
+                    think_start = Instant::now();
                 }
             }
             Message::NewPiece { piece } => {
@@ -84,6 +125,102 @@ fn main() -> Result<()> {
     }
 }
 
+fn send(msg: &Message) -> Result<()> {
+    serde_json::to_writer(stdout(), msg)?;
+    println!();
+    Ok(())
+}
+
+/// Lists the chosen move first, followed by the other candidates best first.
+fn suggestion(
+    mv: libtetris::Move,
+    info: cold_clear::Info,
+    think_start: Instant,
+) -> (Vec<tbi::Move>, MoveInfo) {
+    let mut moves = vec![mv.expected_location];
+    for candidate in info.candidates() {
+        if let Some(&(placement, _)) = candidate.plan.first() {
+            if placement != mv.expected_location {
+                moves.push(placement);
+            }
+        }
+    }
+
+    let (nodes, depth, extra) = match &info {
+        cold_clear::Info::Normal(info) => (info.nodes, info.depth, format!(
+            "rank {}, evaluations {:?}",
+            info.original_rank,
+            info.candidates.iter().map(|c| c.evaluation).collect::<Vec<_>>()
+        )),
+        cold_clear::Info::PcLoop(info) => (0, info.depth, "pc loop".to_string()),
+        cold_clear::Info::Book => (0, 0, "book".to_string()),
+    };
+    let move_info = MoveInfo {
+        nodes: nodes as f64,
+        nps: nodes as f64 / think_start.elapsed().as_secs_f64(),
+        depth: depth as f64,
+        extra,
+    };
+    (moves.into_iter().map(Into::into).collect(), move_info)
+}
+
+/// Reads the command line. `--config FILE` loads a JSON `Config` and `--book FILE` overrides its
+/// opening book. The thinking time limits and number of moves to suggest can be overridden with
+/// e.g. `--min-think-ms 100`.
+fn parse_args() -> Result<(Config, Option<Arc<cold_clear::Book>>)> {
+    let mut config = Config::default();
+    let mut overrides = vec![];
+    let mut book_override = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, format!("{} expects a value", arg))
+        })?;
+        match arg.as_str() {
+            "--config" => {
+                let file = BufReader::new(std::fs::File::open(value)?);
+                config = serde_json::from_reader(file)?;
+            }
+            "--book" => book_override = Some(value),
+            "--min-think-ms" | "--max-think-ms" | "--move-deadline-ms" | "--multi-pv" => {
+                let value = value.parse().map_err(|_| {
+                    Error::new(ErrorKind::InvalidInput, format!("{} expects a number", arg))
+                })?;
+                overrides.push((arg, value));
+            }
+            _ => {
+                let msg = format!("unknown argument {}", arg);
+                return Err(Error::new(ErrorKind::InvalidInput, msg));
+            }
+        }
+    }
+
+    // overrides are applied after reading the whole command line so they win over --config
+    for (arg, value) in overrides {
+        match arg.as_str() {
+            "--min-think-ms" => config.options.min_think_ms = value,
+            "--max-think-ms" => config.options.max_think_ms = value,
+            "--move-deadline-ms" => config.options.move_deadline_ms = value,
+            "--multi-pv" => config.options.multi_pv = value,
+            _ => unreachable!(),
+        }
+    }
+    if book_override.is_some() {
+        config.book = book_override;
+    }
+
+    let book = match &config.book {
+        Some(path) => {
+            let file = BufReader::new(std::fs::File::open(path)?);
+            let book = cold_clear::Book::load(file)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+            Some(Arc::new(book))
+        }
+        None => None,
+    };
+    Ok((config, book))
+}
+
 impl From<tbi::Piece> for libtetris::Piece {
     fn from(v: tbi::Piece) -> libtetris::Piece {
         match v {
@@ -126,10 +263,11 @@ impl From<tbi::Move> for libtetris::FallingPiece {
             ),
             x: v.location.x,
             y: v.location.y,
-            tspin: match v.spin {
-                tbi::Spin::None => libtetris::TspinStatus::None,
-                tbi::Spin::Mini => libtetris::TspinStatus::Mini,
-                tbi::Spin::Full => libtetris::TspinStatus::Full,
+            tspin: match (v.spin, v.location.kind) {
+                (tbi::Spin::None, _) => libtetris::TspinStatus::None,
+                (tbi::Spin::Mini, tbi::Piece::T) => libtetris::TspinStatus::Mini,
+                (tbi::Spin::Full, tbi::Piece::T) => libtetris::TspinStatus::Full,
+                _ => libtetris::TspinStatus::Immobile,
             }
         }
     }
@@ -153,6 +291,7 @@ impl From<libtetris::FallingPiece> for tbi::Move {
                 libtetris::TspinStatus::None => tbi::Spin::None,
                 libtetris::TspinStatus::Mini => tbi::Spin::Mini,
                 libtetris::TspinStatus::Full => tbi::Spin::Full,
+                libtetris::TspinStatus::Immobile => tbi::Spin::Mini,
             }
         }
     }
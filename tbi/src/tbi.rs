@@ -1,16 +1,19 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(tag = "type")]
 #[serde(rename_all = "kebab-case")]
 pub enum Message {
+    Rules {
+        #[serde(default)]
+        randomizer: Randomizer,
+    },
     Start {
         hold: Option<Piece>,
         queue: Vec<Piece>,
         combo: u32,
         back_to_back: bool,
-        #[serde(with = "BigArray")]
-        board: [[Option<char>; 10]; 40],
+        board: Vec<Vec<Option<char>>>,
     },
     Stop,
     Suggest,
@@ -23,19 +26,49 @@ pub enum Message {
     },
     Quit,
 
-    Ready {
+    Info {
         name: String,
         version: String,
         author: String,
+        features: Vec<String>,
+    },
+    Ready,
+    Error {
+        reason: ErrorReason,
     },
     Suggestion {
         moves: Vec<Move>,
+        move_info: MoveInfo,
     },
 
     #[serde(other)]
     Unknown
 }
 
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Randomizer {
+    SevenBag,
+    #[default]
+    Unknown,
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorReason {
+    UnsupportedRules,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MoveInfo {
+    pub nodes: f64,
+    pub nps: f64,
+    pub depth: f64,
+    pub extra: String,
+}
+
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Piece {
     I,
@@ -79,4 +112,3 @@ pub enum Spin {
     Full,
 }
 
-serde_big_array::big_array!( BigArray; 40, );